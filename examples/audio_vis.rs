@@ -36,15 +36,16 @@ fn main() {
 
             let w = ro.res_x;
             let h = ro.res_y;
-            for y in 0..h {
+            let x_size = (ro.x_range.end - ro.x_range.start) as usize;
+            for y in ro.y_range.clone() {
                 let d = (y as f64 / h as f64) * 2.0 - 1.0;
-                for x in 0..w {
+                for x in ro.x_range.clone() {
                     let u = x as f64 / w as f64;
                     let i = ((10.0f64).powf(u) * fs as f64 / 20.0).floor() as usize;
                     let v1 = (output[i].norm() as f64).log10() * 0.15 + 0.2;
-                    let v2 = (1.0 - (input[x].re as f64 - d).abs() * h as f64).max(0.0);
+                    let v2 = (1.0 - (input[x as usize].re as f64 - d).abs() * h as f64).max(0.0);
                     let v = v1.max(v2);
-                    buffer[y * w + x] = Rgba(v, v, v, 1.0);
+                    buffer[(y - ro.y_range.start) as usize * x_size + (x - ro.x_range.start) as usize] = Rgba(v, v, v, 1.0);
                 }
             }
         }))));
@@ -40,9 +40,10 @@ fn main() {
             let n = 9;
             let w = ro.res_x;
             let h = ro.res_y;
-            for y in 0..h {
+            let x_size = (ro.x_range.end - ro.x_range.start) as usize;
+            for y in ro.y_range.clone() {
                 let cv = (y as f64 / h as f64) * 2.0 - 1.0;
-                for x in 0..w {
+                for x in ro.x_range.clone() {
                     let cu = -(x as f64 - w as f64 / 2.0) as f64 / (h / 2) as f64;
                     let theta = cv.atan2(cu);
                     let r = cv.hypot(cu);
@@ -57,7 +58,7 @@ fn main() {
                     };
                     //let e = (1.0 - (0.5 - a.fract()).abs() * 2.0).sqrt();
                     let (r, g, b) = hsl_to_rgb(0.8 - d * 1.2, 0.5, d.powi(3));
-                    buffer[y * w + x] = Rgba(r, g, b, 1.0);
+                    buffer[(y - ro.y_range.start) as usize * x_size + (x - ro.x_range.start) as usize] = Rgba(r, g, b, 1.0);
                 }
             }
         }))));
@@ -1,6 +1,6 @@
 extern crate kantera;
 
-use std::rc::Rc;
+use std::sync::Arc;
 use kantera::export::render_to_mp4;
 use kantera::renders::image_render::{ImageRender, Sizing};
 use kantera::path_to_image::{closed_path_to_image, closed_path_rect, expand_rect};
@@ -13,7 +13,7 @@ fn main() {
         .append(1.0, Vec2(90.0, 50.0), Point::Linear)
         .append(1.0, Vec2(1.0, 90.0), Point::Bezier3(Vec2(90.0, 70.0), Vec2(1.0, 60.0)));
     let rect = expand_rect(closed_path_rect(&path), 5);
-    let image = Rc::new(closed_path_to_image(rect, Rgba(1.0, 0.0, 0.0, 1.0), Rgba(0.1, 0.7, 0.0, 1.0), 3.0, &path));
+    let image = Arc::new(closed_path_to_image(rect, Rgba(1.0, 0.0, 0.0, 1.0), Rgba(0.1, 0.7, 0.0, 1.0), 3.0, &path));
     render_to_mp4(
         5.0,
         320,
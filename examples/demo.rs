@@ -2,6 +2,7 @@ extern crate kantera;
 
 use kantera::pixel::Rgba;
 use kantera::render::{Render, RenderOpt, Dummy};
+use kantera::framerate::Framerate;
 
 fn make_image() -> kantera::image::Image<Rgba> {
     let (width, height) = (640, 480);
@@ -21,7 +22,7 @@ fn make_image() -> kantera::image::Image<Rgba> {
 }
 
 fn main() {
-    use std::rc::Rc;
+    use std::sync::Arc;
     use kantera::export::{render_to_mp4, render_to_buffer};
     use kantera::renders::{
         sequence::Sequence,
@@ -38,7 +39,7 @@ fn main() {
     use kantera::path::{Path, Point};
     use kantera::util::hsl_to_rgb;
 
-    let image = Rc::new(make_image());
+    let image = Arc::new(make_image());
     let buffer = render_to_buffer(
         &RenderOpt {
             x_range: 0..20,
@@ -46,7 +47,8 @@ fn main() {
             res_x: 20,
             res_y: 20,
             frame_range: 0..100,
-            framerate: 4,
+            framerate: Framerate::from(4),
+            pixel_center: false,
         },
         &Dummy());
 
@@ -57,7 +59,8 @@ fn main() {
             res_x: 640,
             res_y: 480,
             frame_range: 0..30 * 7,
-            framerate: 30
+            framerate: Framerate::from(30),
+            pixel_center: false
         },
         &Composite::<Box<dyn Render<Rgba>>> {
             layers: vec![
@@ -81,12 +84,12 @@ fn main() {
                             interpolation: kantera::interpolation::Bilinear
                         }),
                         max_size: 10,
-                        size: Rc::new(Path::new(0.0)
+                        size: Arc::new(Path::new(0.0)
                             .append(6.0, 0.0, Point::Constant)
                             .append(1.0, 10.0, Point::Linear)
                     )}),
                     CompositeMode::Normal(
-                        Rc::new(Path::new(0.0).append(1.0, 1.0, Point::Linear))
+                        Arc::new(Path::new(0.0).append(1.0, 1.0, Point::Linear))
                     )
                 )
             ]
@@ -126,7 +129,7 @@ fn main() {
                 true,
                 Box::new(RgbTransform::<Box<dyn Render<Rgba>>> {
                     render: Box::new(TimeExtrapolate::<Rgba, Box<dyn Render<Rgba>>> {
-                        duration: buffer2.frame_num as f64 / buffer2.framerate as f64,
+                        duration: buffer2.framerate.frame_to_time(buffer2.frame_num as i64),
                         render: Box::new(Playback::from(buffer2)),
                         extrapolation_type: ExtrapolationType::Extend
                     }),
@@ -1,6 +1,6 @@
 extern crate kantera;
 
-use std::rc::Rc;
+use std::sync::Arc;
 use kantera::pixel::Rgba;
 use kantera::export::render_to_mp4;
 use kantera::ffmpeg::import_image;
@@ -9,7 +9,7 @@ use kantera::renders::image_render::{ImageRender, Sizing};
 fn main() {
     let (width, height) = (320, 240);
 
-    let image = Rc::new(import_image("./out.jpg"));
+    let image = Arc::new(import_image("./out.jpg"));
 
     render_to_mp4(
         5.0, width, height, 30, 1,
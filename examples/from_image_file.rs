@@ -1,7 +1,7 @@
 extern crate kantera;
 extern crate image;
 
-use std::rc::Rc;
+use std::sync::Arc;
 use kantera::pixel::Rgba;
 use kantera::image::Image;
 use kantera::export::render_to_mp4;
@@ -12,7 +12,7 @@ use kantera::renders::{
 fn main() {
     let (width, height) = (320, 240);
 
-    let img = Rc::new({
+    let img = Arc::new({
         let img = image::open("./out.jpg").unwrap();
         let buf = img.as_rgb8().unwrap();
         Image {
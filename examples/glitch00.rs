@@ -1,7 +1,7 @@
 extern crate kantera;
 extern crate image;
 
-use std::rc::Rc;
+use std::sync::Arc;
 use kantera::{
     pixel::Rgba,
     image::Image,
@@ -26,7 +26,7 @@ fn fractal_noise(n: usize, x: u32, y: u32, z: u32) -> f64 {
 fn main() {
     let (width, height) = (320, 240);
 
-    let img = Rc::new({
+    let img = Arc::new({
         let img = image::open("./out.jpg").unwrap();
         let buf = img.as_rgb8().unwrap();
         Image {
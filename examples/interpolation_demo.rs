@@ -1,6 +1,6 @@
 extern crate kantera;
 
-use std::rc::Rc;
+use std::sync::Arc;
 use kantera::{
     pixel::Rgba,
     export::render_to_mp4,
@@ -20,7 +20,7 @@ fn main() {
     let font = Font::from_bytes(&bytes).unwrap();
     let (width, height) = (320, 240);
 
-    let image = Rc::new(render(&font, 32.0, "あ").map(|v| Rgba(0.1, 0.1, 0.1, *v)));
+    let image = Arc::new(render(&font, 32.0, "あ").map(|v| Rgba(0.1, 0.1, 0.1, *v)));
 
     render_to_mp4(
         3.0, width, height, 30, 1,
@@ -45,7 +45,7 @@ fn main() {
                             image: image.clone(), sizing: Sizing::Contain, default: Rgba(0.0, 0.0, 0.0, 0.0),
                             interpolation: kantera::interpolation::Bicubic::new(1.0 / 3.0, 1.0 / 3.0)
                         }))),
-                    CompositeMode::Normal(Rc::new(1.0))
+                    CompositeMode::Normal(Arc::new(1.0))
                 )
             ]
         });
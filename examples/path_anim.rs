@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::sync::Arc;
 use kantera::{
     pixel::Rgba,
     v::Vec2,
@@ -24,7 +24,7 @@ fn main() {
         .append(1.0, Vec2(125.0, 150.0), Point::Bezier3(Vec2(100.0, 110.0), Vec2(125.0, 110.0)))
         .append(1.0, Vec2(130.0, 150.0), Point::Bezier3(Vec2(125.0, 70.0), Vec2(130.0, 70.0)));
 
-    let image = Rc::new(kantera::cairo::render_image(320, 240, &|ctx| {
+    let image = Arc::new(kantera::cairo::render_image(320, 240, &|ctx| {
         for w in path.points.windows(2) {
             let (left, right) = (w[0], w[1]);
             ctx.move_to((left.1).0, (left.1).1);
@@ -78,18 +78,17 @@ fn main() {
                 ),
                 (
                     Box::new(FunctionalRender(Box::new(move |ro, time, buffer| {
-                        let w = ro.res_x;
-                        let h = ro.res_y;
-                        for y in 0..h {
-                            for x in 0..w {
+                        let x_size = (ro.x_range.end - ro.x_range.start) as usize;
+                        for y in ro.y_range.clone() {
+                            for x in ro.x_range.clone() {
                                 let p = path.get_value(time);
                                 let d = (p.0 - x as f64).hypot(p.1 - y as f64);
                                 let v = (3.0 - d).min(1.0).max(0.0);
-                                buffer[y * w + x] = Rgba(v, 0.0, 0.0, v);
+                                buffer[(y - ro.y_range.start) as usize * x_size + (x - ro.x_range.start) as usize] = Rgba(v, 0.0, 0.0, v);
                             }
                         }
                     }))),
-                    CompositeMode::Normal(Rc::new(1.0))
+                    CompositeMode::Normal(Arc::new(1.0))
                 )
             ]
     });
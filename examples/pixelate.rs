@@ -11,10 +11,10 @@ use kantera::util::noise;
 
 fn main() {
     render_to_mp4(
-        5.0, 320, 240, 30, 1,
+        5.0, 320, 240, 30, 8,
         "pixelate.mp4",
-        &Map::<Rgba, Box<dyn Render<Rgba>>> {
-            render: Box::new(Sample::new(Box::new(|u: f64, v: f64, time: f64, (w, h): (usize, usize)| {
+        &Map::<Rgba, Box<dyn Render<Rgba>>>::new(
+            Box::new(Sample::new(Box::new(|u: f64, v: f64, time: f64, (w, h): (usize, usize)| {
                 let d = ((u - 0.5).powi(2) + (v - 0.5).powi(2)).powf(0.5);
                 let (u, v) = ((u - 0.5) * (0.5 + d * 2.0) + 0.5, (v - 0.5) * (0.5 + d * 2.0) + 0.5);
                 let x = u / 20.0 * w as f64;
@@ -25,7 +25,7 @@ fn main() {
                     noise(x + time * 1.0, y, 2.0) * 0.5 + 0.5,
                     1.0)
             }))),
-            map: Box::new(|w, h, buffer| {
+            Box::new(|w, h, buffer| {
                 let size = 7;
                 for y in 0..(h - 1) / size + 1 {
                     for x in 0..(w - 1) / size + 1 {
@@ -55,7 +55,7 @@ fn main() {
                     }
                 }
             })
-        });
+        ));
 
     println!("done!");
 }
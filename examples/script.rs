@@ -2,7 +2,7 @@ use kantera::pixel::Rgba;
 use kantera::render::Render;
 use kantera::export::render_to_mp4;
 use kantera::script::Runtime;
-use std::rc::Rc;
+use std::sync::Arc;
 
 fn main() {
     let mut rt = Runtime::new();
@@ -14,7 +14,7 @@ fn main() {
             (vec 2.0 true (plain (rgba 0.0 0.0 1.0 1.0)))
         )"
     ).unwrap();
-    let render = res.downcast_ref::<Rc<dyn Render<Rgba>>>().unwrap().clone();
+    let render = res.downcast_ref::<Arc<dyn Render<Rgba>>>().unwrap().clone();
     render_to_mp4(
         5.0, 320, 240, 30, 1,
         "script.mp4",
@@ -1,6 +1,6 @@
 extern crate kantera;
 
-use std::rc::Rc;
+use std::sync::Arc;
 use kantera::pixel::Rgba;
 use kantera::render::Render;
 use kantera::export::render_to_mp4;
@@ -15,7 +15,7 @@ use kantera::v::Vec2;
 fn main() {
     let (width, height) = (320, 240);
 
-    let image = Rc::new(kantera::cairo::render_image(width, height, &|ctx| {
+    let image = Arc::new(kantera::cairo::render_image(width, height, &|ctx| {
         ctx.set_source_rgb(0.9, 0.9, 0.9);
         ctx.paint();
 
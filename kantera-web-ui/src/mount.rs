@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::sync::Arc;
 use std::ops::Range;
 use serde::Serialize;
 use kantera::{
@@ -16,7 +16,7 @@ pub fn mount(src: &str, position: i32, mut rt: Runtime) -> Option<Sequencer> {
             let evaled = rt.e(vec).ok()?;
             let clips = evaled.ref_as::<Vec<Val>>()?;
             for i in 0..s.clips.len() {
-                s.clips[i].duration = clips[i].ref_as::<Vec<Val>>()?[2].ref_as::<Rc<dyn Render<Rgba>>>()?.duration();
+                s.clips[i].duration = clips[i].ref_as::<Vec<Val>>()?[2].ref_as::<Arc<dyn Render<Rgba>>>()?.duration();
             }
             return Some(s);
         }
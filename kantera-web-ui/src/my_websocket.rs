@@ -8,11 +8,13 @@ use kantera::{
     pixel::Rgba,
     buffer::Buffer,
     render::{Render, RenderOpt},
+    framerate::Framerate,
     audio_render::AudioRender,
-    export::render_to_buffer_parallel,
+    export::{try_render_to_buffer_parallel, try_render_to_mp4_parallel},
+    parallel::ParallelOpt,
     script::{Runtime, r, Val, ValInterface}
 };
-use std::rc::Rc;
+use std::sync::Arc;
 use std::cell::RefCell;
 use std::collections::HashMap;
 
@@ -25,14 +27,15 @@ const SAMPLERATE_DEFAULT: usize = 16000;
 pub struct MyWebSocket {
     hb: Instant,
     current_frame: Option<i32>,
-    render: Option<Rc<dyn Render<Rgba>>>,
-    audio_render: Option<Rc<dyn AudioRender>>,
-    framerate: usize,
+    render: Option<Arc<dyn Render<Rgba>>>,
+    audio_render: Option<Arc<dyn AudioRender>>,
+    framerate: Framerate,
     samplerate: usize,
     start_frame: i32,
     end_frame: Option<i32>,
     loop_: bool,
     size: (usize, usize),
+    pixel_center: bool,
     render_at: Instant,
     rt_cache: Val
 }
@@ -94,21 +97,32 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for MyWebSocket {
                             ctx.text(format!(r#"{{"type":"renderFailed","error":"Video duration must be finite"}}"#));
                             return;
                         }
-                        kantera::export::render_to_mp4(
+                        if let Err(e) = try_render_to_mp4_parallel(
                             duration,
                             self.size.0,
                             self.size.1,
                             self.framerate,
                             10,
                             &file_path,
-                            render.as_ref()
-                        );
+                            render.as_ref(),
+                            self.pixel_center,
+                            &ParallelOpt::default()
+                        ) {
+                            ctx.text(format!(r#"{{"type":"renderFailed","error":{:?}}}"#, format!("{}", e)));
+                            return;
+                        }
                         if let Some(ref audio_render) = self.audio_render {
                             if audio_render.duration().is_infinite() {
                                 ctx.text(format!(r#"{{"type":"renderFailed","error":"Audio duration must be finite"}}"#));
                                 return;
                             }
-                            let buffer = kantera::audio_render::render_to_buffer(audio_render.as_ref(), self.samplerate);
+                            let buffer = match kantera::audio_render::try_render_to_buffer(audio_render.as_ref(), self.samplerate) {
+                                Ok(buffer) => buffer,
+                                Err(e) => {
+                                    ctx.text(format!(r#"{{"type":"renderFailed","error":{:?}}}"#, format!("{}", e)));
+                                    return;
+                                }
+                            };
                             kantera::ffmpeg::export_audio(&(&buffer).into(), "/tmp/kantera_audio.mp3", true);
                             if std::fs::rename(&file_path, "/tmp/kantera_video.mp4").is_err() {
                                 ctx.text(format!(r#"{{"type":"renderFailed","error":"Internal error"}}"#));
@@ -137,12 +151,13 @@ impl MyWebSocket {
             current_frame: None,
             render: None,
             audio_render: None,
-            framerate: FRAMERATE_DEFAULT,
+            framerate: Framerate::from(FRAMERATE_DEFAULT),
             samplerate: SAMPLERATE_DEFAULT,
             start_frame: 0,
             end_frame: None,
             loop_: false,
             size: (600, 400),
+            pixel_center: false,
             render_at: Instant::now(),
             rt_cache: r(RefCell::new(HashMap::<String, Val>::new()))
         }
@@ -163,16 +178,22 @@ impl MyWebSocket {
         if let Some(frame) = self.current_frame {
             if let Some(ref render) = self.render {
                 let (width, height) = self.size;
-                let buffer = {
-                    let render = unsafe { std::mem::transmute::<&dyn Render<Rgba>, &'static (dyn Render<Rgba> + Send + Sync)>(render) };
-                    render_to_buffer_parallel(&RenderOpt {
-                        x_range: 0..width as i32,
-                        y_range: 0..height as i32,
-                        res_x: width,
-                        res_y: height,
-                        frame_range: frame..frame+1,
-                        framerate: self.framerate
-                    }, render) as Buffer<Rgba>
+                let result: Result<Buffer<Rgba>, _> = try_render_to_buffer_parallel(&RenderOpt {
+                    x_range: 0..width as i32,
+                    y_range: 0..height as i32,
+                    res_x: width,
+                    res_y: height,
+                    frame_range: frame..frame+1,
+                    framerate: self.framerate,
+                    pixel_center: self.pixel_center
+                }, &ParallelOpt::default(), render.as_ref());
+                let buffer = match result {
+                    Ok(buffer) => buffer,
+                    Err(e) => {
+                        ctx.text(format!(r#"{{"type":"error","error":{:?}}}"#, format!("{}", e)));
+                        self.render = None;
+                        return self.schedule_render(ctx);
+                    }
                 };
                 let mut buf: Vec<u8> = vec![0; buffer.vec.len() * 4];
                 for i in 0..buffer.vec.len() {
@@ -192,9 +213,16 @@ impl MyWebSocket {
                 let sample_rate = self.samplerate;
                 let ro = kantera::audio_render::AudioRenderOpt {
                     sample_rate: sample_rate,
-                    sample_range: frame as i64 * sample_rate as i64 / self.framerate as i64..(frame as i64 + 1) * sample_rate as i64 / self.framerate as i64
+                    sample_range: self.sample_index(frame as i64)..self.sample_index(frame as i64 + 1)
+                };
+                let vec = match audio_render.try_render(&ro) {
+                    Ok(vec) => vec,
+                    Err(e) => {
+                        ctx.text(format!(r#"{{"type":"error","error":{:?}}}"#, format!("{}", e)));
+                        self.audio_render = None;
+                        return self.schedule_render(ctx);
+                    }
                 };
-                let vec = audio_render.render(&ro);
                 let mut bin = Vec::new();
                 use std::io::Write;
                 for v in vec.iter() {
@@ -219,8 +247,16 @@ impl MyWebSocket {
             }
         }
 
-        // Schedule next render
-        let desire_duration = Duration::from_millis(1000 / self.framerate as u64);
+        self.schedule_render(ctx);
+    }
+
+    // Computed from the frame index rather than accumulated, so that audio doesn't drift at 29.97fps.
+    fn sample_index(&self, frame: i64) -> i64 {
+        (frame as i128 * self.samplerate as i128 * self.framerate.den as i128 / self.framerate.num as i128) as i64
+    }
+
+    fn schedule_render(&mut self, ctx: &mut <Self as Actor>::Context) {
+        let desire_duration = Duration::from_secs_f64(self.framerate.frame_to_time(1));
         self.render_at = (self.render_at + desire_duration).max(Instant::now() - desire_duration);
         let duration = self.render_at.checked_duration_since(Instant::now()).unwrap_or(Duration::from_millis(1));
         ctx.run_later(duration, Self::render_loop);
@@ -234,6 +270,7 @@ impl MyWebSocket {
         rt.insert("start_frame", r(0i32));
         rt.insert("end_frame", r(false));
         rt.insert("loop", r(false));
+        rt.insert("pixel_center", r(false));
         rt.insert("frame_height", r(400 as i32));
         rt.insert("__rt_cache", self.rt_cache.clone());
         rt
@@ -243,11 +280,18 @@ impl MyWebSocket {
         let mut rt = self.make_runtime();
         match rt.re(&src) {
             Ok(_) => {
-                self.render = rt.get("video").and_then(|val| val.ref_as::<Rc<dyn Render<Rgba>>>().cloned());
-                self.audio_render = rt.get("audio").and_then(|val| val.ref_as::<Rc<dyn AudioRender>>().cloned());
+                self.render = rt.get("video").and_then(|val| val.ref_as::<Arc<dyn Render<Rgba>>>().cloned());
+                self.audio_render = rt.get("audio").and_then(|val| val.ref_as::<Arc<dyn AudioRender>>().cloned());
                 if let Some(val) = rt.get("framerate") {
-                    let framerate = *val.ref_as::<i32>().unwrap();
-                    self.framerate = framerate.min(120).max(1) as usize;
+                    // Either an integer or a string such as "30000/1001".
+                    let framerate = if let Some(framerate) = val.ref_as::<i32>() {
+                        Some(Framerate::from((*framerate).min(120).max(1) as usize))
+                    } else {
+                        val.ref_as::<String>().and_then(|s| s.parse::<Framerate>().ok())
+                    };
+                    if let Some(framerate) = framerate.filter(|f| (1.0..=120.0).contains(&f.as_f64())) {
+                        self.framerate = framerate;
+                    }
                 }
                 if let Some(val) = rt.get("samplerate") {
                     let samplerate = *val.ref_as::<i32>().unwrap();
@@ -263,9 +307,10 @@ impl MyWebSocket {
                 self.start_frame = rt.get("start_frame").and_then(|val| val.ref_as::<i32>().copied()).unwrap_or(0);
                 self.end_frame = rt.get("end_frame").and_then(|val| val.ref_as::<i32>().copied()).map(|val| val.max(1));
                 self.loop_ = rt.get("loop").and_then(|val| val.ref_as::<bool>().copied()).unwrap_or(false);
+                self.pixel_center = rt.get("pixel_center").and_then(|val| val.ref_as::<bool>().copied()).unwrap_or(false);
                 self.current_frame = Some(self.start_frame);
                 let channel_num = self.audio_render.as_ref().map(|r| r.channel_num()).unwrap_or(0);
-                ctx.text(format!(r#"{{"type":"streamInfo","framerate":{:?},"samplerate":{:?},"channelNum":{:?}}}"#, self.framerate, self.samplerate, channel_num));
+                ctx.text(format!(r#"{{"type":"streamInfo","framerate":{:?},"samplerate":{:?},"channelNum":{:?}}}"#, self.framerate.as_f64(), self.samplerate, channel_num));
             },
             Err(mes) => ctx.text(format!(r#"{{"type":"parseFailed","error":{:?}}}"#, format!("{}", mes)))
         }
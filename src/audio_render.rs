@@ -1,4 +1,5 @@
 use crate::audio_buffer::AudioBuffer;
+use crate::error::{KanteraError, catch_panic};
 
 #[derive(Debug)]
 pub struct AudioRenderOpt {
@@ -6,9 +7,13 @@ pub struct AudioRenderOpt {
     pub sample_rate: usize,
 }
 
-pub trait AudioRender {
+pub trait AudioRender: Send + Sync {
     fn render(&self, ro: &AudioRenderOpt) -> Vec<f64>;
 
+    fn try_render(&self, ro: &AudioRenderOpt) -> Result<Vec<f64>, KanteraError> {
+        catch_panic(|| self.render(ro))
+    }
+
     fn channel_num(&self) -> usize;
 
     fn duration(&self) -> f64;
@@ -20,6 +25,11 @@ impl AudioRender for Box<dyn AudioRender> {
         self.as_ref().render(ro)
     }
 
+    #[inline(always)]
+    fn try_render(&self, ro: &AudioRenderOpt) -> Result<Vec<f64>, KanteraError> {
+        self.as_ref().try_render(ro)
+    }
+
     fn channel_num(&self) -> usize {
         self.as_ref().channel_num()
     }
@@ -28,12 +38,17 @@ impl AudioRender for Box<dyn AudioRender> {
         self.as_ref().duration()
     }
 }
-impl AudioRender for std::rc::Rc<dyn AudioRender> {
+impl AudioRender for std::sync::Arc<dyn AudioRender> {
     #[inline(always)]
     fn render(&self, ro: &AudioRenderOpt) -> Vec<f64> {
         self.as_ref().render(ro)
     }
 
+    #[inline(always)]
+    fn try_render(&self, ro: &AudioRenderOpt) -> Result<Vec<f64>, KanteraError> {
+        self.as_ref().try_render(ro)
+    }
+
     fn channel_num(&self) -> usize {
         self.as_ref().channel_num()
     }
@@ -70,24 +85,30 @@ impl AudioRender for Dummy {
 }
 
 pub fn render_to_buffer(render: &dyn AudioRender, sample_rate: usize) -> AudioBuffer<f64> {
-    assert!(render.duration().is_finite());
+    try_render_to_buffer(render, sample_rate).unwrap_or_else(|e| panic!("{}", e))
+}
+
+pub fn try_render_to_buffer(render: &dyn AudioRender, sample_rate: usize) -> Result<AudioBuffer<f64>, KanteraError> {
+    if render.duration().is_infinite() {
+        return Err(KanteraError::InfiniteDuration);
+    }
     // TODO: support step_sample_size for rendering large buffer
     let channel_num = render.channel_num();
     let size = (render.duration() * sample_rate as f64).floor() as usize;
-    let raw_vec = render.render(&AudioRenderOpt {
+    let raw_vec = render.try_render(&AudioRenderOpt {
         sample_range: 0..size as i64,
         sample_rate: sample_rate
-    });
+    })?;
     let mut vec = Vec::new();
     for c in 0..channel_num {
         vec.push(Vec::from(&raw_vec[c * size..(c + 1) * size]));
     }
-    AudioBuffer {
+    Ok(AudioBuffer {
         channel_num: channel_num,
         sample_num: size,
         sample_rate: sample_rate,
         vec: vec
-    }
+    })
 }
 
 #[test]
@@ -1,14 +1,14 @@
-use std::rc::Rc;
+use std::sync::Arc;
 use crate::audio_render::{AudioRenderOpt, AudioRender};
 use crate::audio_buffer::AudioBuffer;
 use crate::interpolation::AudioInterpolation;
 
 pub struct AudioBufferRender<T, U: AudioInterpolation<T>> {
-    pub audio_buffer: Rc<AudioBuffer<T>>,
+    pub audio_buffer: Arc<AudioBuffer<T>>,
     pub interpolation: U
 }
 
-impl<U: AudioInterpolation<f64>> AudioRender for AudioBufferRender<f64, U> {
+impl<U: AudioInterpolation<f64> + Send + Sync> AudioRender for AudioBufferRender<f64, U> {
     fn render(&self, ro: &AudioRenderOpt) -> Vec<f64> {
         let channel_num = self.channel_num();
         let size = (ro.sample_range.end - ro.sample_range.start) as usize;
@@ -33,7 +33,7 @@ impl<U: AudioInterpolation<f64>> AudioRender for AudioBufferRender<f64, U> {
     }
 }
 
-impl<U: AudioInterpolation<u16>> AudioRender for AudioBufferRender<u16, U> {
+impl<U: AudioInterpolation<u16> + Send + Sync> AudioRender for AudioBufferRender<u16, U> {
     fn render(&self, ro: &AudioRenderOpt) -> Vec<f64> {
         let channel_num = self.channel_num();
         let size = (ro.sample_range.end - ro.sample_range.start) as usize;
@@ -1,5 +1,6 @@
 use crate::audio_buffer::{pan, pan_mono};
 use crate::audio_render::{AudioRenderOpt, AudioRender};
+use crate::error::KanteraError;
 
 pub struct AudioClip<T: AudioRender> {
     pub audio_render: T,
@@ -14,6 +15,10 @@ pub struct AudioClip<T: AudioRender> {
 
 impl<T: AudioRender> AudioRender for AudioClip<T> {
     fn render(&self, ro: &AudioRenderOpt) -> Vec<f64> {
+        self.try_render(ro).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &AudioRenderOpt) -> Result<Vec<f64>, KanteraError> {
         let channel_num = self.channel_num();
         let size = (ro.sample_range.end - ro.sample_range.start) as usize;
         let start = ro.sample_range.start as f64 / ro.sample_rate as f64;
@@ -27,10 +32,10 @@ impl<T: AudioRender> AudioRender for AudioClip<T> {
             // assert_eq!(size, (sample_range_end - sample_range_start) as usize);
             // FIXME: (x_x) keisan ga awanai
             let sample_range_end = sample_range_start as usize + size;
-            self.audio_render.render(&AudioRenderOpt {
+            self.audio_render.try_render(&AudioRenderOpt {
                 sample_range: sample_range_start as i64..sample_range_end as i64,
                 sample_rate: sample_rate
-            })
+            })?
         };
         let mut vec = vec![0.0; channel_num * size];
         match self.audio_render.channel_num() {
@@ -46,7 +51,7 @@ impl<T: AudioRender> AudioRender for AudioClip<T> {
                     vec[i] = l * self.gain;
                     vec[size + i] = r * self.gain;
                 }
-            channel_num => panic!("AudioClip not support channel_num: {:?}", channel_num)
+            channel_num => return Err(KanteraError::UnsupportedChannelNum(channel_num))
         }
         // fadein
         {
@@ -82,7 +87,7 @@ impl<T: AudioRender> AudioRender for AudioClip<T> {
             vec[i] = 0.0;
             vec[size + i] = 0.0;
         }
-        vec
+        Ok(vec)
     }
 
     fn channel_num(&self) -> usize {
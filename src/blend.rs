@@ -0,0 +1,178 @@
+use crate::pixel::Rgba;
+
+// Blend modes as defined in the W3C Compositing and Blending spec.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BlendMode {
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    SoftLight,
+    HardLight,
+    Add,
+    Subtract,
+    Difference,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    Hue,
+    Saturation,
+    Color,
+    Luminosity
+}
+
+impl BlendMode {
+    pub fn from_name(name: &str) -> Option<BlendMode> {
+        Some(match name {
+            "normal" => BlendMode::Normal,
+            "multiply" => BlendMode::Multiply,
+            "screen" => BlendMode::Screen,
+            "overlay" => BlendMode::Overlay,
+            "soft_light" => BlendMode::SoftLight,
+            "hard_light" => BlendMode::HardLight,
+            "add" => BlendMode::Add,
+            "subtract" => BlendMode::Subtract,
+            "difference" => BlendMode::Difference,
+            "darken" => BlendMode::Darken,
+            "lighten" => BlendMode::Lighten,
+            "color_dodge" => BlendMode::ColorDodge,
+            "color_burn" => BlendMode::ColorBurn,
+            "hue" => BlendMode::Hue,
+            "saturation" => BlendMode::Saturation,
+            "color" => BlendMode::Color,
+            "luminosity" => BlendMode::Luminosity,
+            _ => return None
+        })
+    }
+}
+
+fn hard_light(b: f64, s: f64) -> f64 {
+    if s <= 0.5 {
+        b * s * 2.0
+    } else {
+        let s = s * 2.0 - 1.0;
+        b + s - b * s
+    }
+}
+
+fn soft_light(b: f64, s: f64) -> f64 {
+    if s <= 0.5 {
+        b - (1.0 - 2.0 * s) * b * (1.0 - b)
+    } else {
+        let d = if b <= 0.25 { ((16.0 * b - 12.0) * b + 4.0) * b } else { b.sqrt() };
+        b + (2.0 * s - 1.0) * (d - b)
+    }
+}
+
+fn color_dodge(b: f64, s: f64) -> f64 {
+    if b <= 0.0 {
+        0.0
+    } else if s >= 1.0 {
+        1.0
+    } else {
+        (b / (1.0 - s)).min(1.0)
+    }
+}
+
+fn color_burn(b: f64, s: f64) -> f64 {
+    if b >= 1.0 {
+        1.0
+    } else if s <= 0.0 {
+        0.0
+    } else {
+        1.0 - ((1.0 - b) / s).min(1.0)
+    }
+}
+
+fn separable(mode: BlendMode, b: f64, s: f64) -> f64 {
+    match mode {
+        BlendMode::Multiply => b * s,
+        BlendMode::Screen => b + s - b * s,
+        BlendMode::Overlay => hard_light(s, b),
+        BlendMode::SoftLight => soft_light(b, s),
+        BlendMode::HardLight => hard_light(b, s),
+        BlendMode::Add => b + s,
+        BlendMode::Subtract => (b - s).max(0.0),
+        BlendMode::Difference => (b - s).abs(),
+        BlendMode::Darken => b.min(s),
+        BlendMode::Lighten => b.max(s),
+        BlendMode::ColorDodge => color_dodge(b, s),
+        BlendMode::ColorBurn => color_burn(b, s),
+        _ => s
+    }
+}
+
+type Rgb = (f64, f64, f64);
+
+fn lum(c: Rgb) -> f64 {
+    0.3 * c.0 + 0.59 * c.1 + 0.11 * c.2
+}
+
+fn clip_color(c: Rgb) -> Rgb {
+    let l = lum(c);
+    let n = c.0.min(c.1).min(c.2);
+    let x = c.0.max(c.1).max(c.2);
+    let f = |v: f64| {
+        let v = if n < 0.0 { l + (v - l) * l / (l - n) } else { v };
+        if x > 1.0 { l + (v - l) * (1.0 - l) / (x - l) } else { v }
+    };
+    (f(c.0), f(c.1), f(c.2))
+}
+
+fn set_lum(c: Rgb, l: f64) -> Rgb {
+    let d = l - lum(c);
+    clip_color((c.0 + d, c.1 + d, c.2 + d))
+}
+
+fn sat(c: Rgb) -> f64 {
+    c.0.max(c.1).max(c.2) - c.0.min(c.1).min(c.2)
+}
+
+fn set_sat(c: Rgb, s: f64) -> Rgb {
+    let max = c.0.max(c.1).max(c.2);
+    let min = c.0.min(c.1).min(c.2);
+    let f = |v: f64| if max > min { (v - min) * s / (max - min) } else { 0.0 };
+    (f(c.0), f(c.1), f(c.2))
+}
+
+fn non_separable(mode: BlendMode, b: Rgb, s: Rgb) -> Rgb {
+    match mode {
+        BlendMode::Hue => set_lum(set_sat(s, sat(b)), lum(b)),
+        BlendMode::Saturation => set_lum(set_sat(b, sat(s)), lum(b)),
+        BlendMode::Color => set_lum(s, lum(b)),
+        BlendMode::Luminosity => set_lum(b, lum(s)),
+        _ => (separable(mode, b.0, s.0), separable(mode, b.1, s.1), separable(mode, b.2, s.2))
+    }
+}
+
+// Composites `source` over `backdrop` with its alpha multiplied by `opacity`. Both are straight alpha.
+pub fn blend(backdrop: &Rgba, source: &Rgba, mode: BlendMode, opacity: f64) -> Rgba {
+    let ab = backdrop.3;
+    let a_s = source.3 * opacity;
+    let ao = a_s + ab * (1.0 - a_s);
+    if ao <= 0.0 {
+        return Rgba(0.0, 0.0, 0.0, 0.0);
+    }
+    let b = (backdrop.0, backdrop.1, backdrop.2);
+    let s = (source.0, source.1, source.2);
+    let mixed = non_separable(mode, b, s);
+    // Where the backdrop is transparent the source is shown as is.
+    let f = |b: f64, s: f64, m: f64| (a_s * ((1.0 - ab) * s + ab * m) + (1.0 - a_s) * ab * b) / ao;
+    Rgba(f(b.0, s.0, mixed.0), f(b.1, s.1, mixed.1), f(b.2, s.2, mixed.2), ao)
+}
+
+#[test]
+fn test() {
+    let b = Rgba(0.2, 0.4, 0.8, 1.0);
+    let s = Rgba(0.5, 0.5, 0.5, 1.0);
+    assert_eq!(blend(&b, &s, BlendMode::Normal, 1.0), s);
+    assert_eq!(blend(&b, &s, BlendMode::Multiply, 1.0), Rgba(0.1, 0.2, 0.4, 1.0));
+    assert_eq!(blend(&b, &s, BlendMode::Multiply, 0.0), b);
+    assert_eq!(blend(&b, &Rgba(0.5, 0.5, 0.5, 0.0), BlendMode::Screen, 1.0), b);
+    // A transparent backdrop shows the source regardless of the mode.
+    assert_eq!(blend(&Rgba(0.0, 0.0, 0.0, 0.0), &s, BlendMode::Difference, 1.0), s);
+    // Luminosity keeps the hue of the backdrop.
+    let c = blend(&b, &s, BlendMode::Luminosity, 1.0);
+    assert!((lum((c.0, c.1, c.2)) - 0.5).abs() < 1e-9);
+}
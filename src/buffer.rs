@@ -1,8 +1,10 @@
+use crate::framerate::Framerate;
+
 #[derive(Debug)]
 pub struct Buffer<T> {
     pub width: usize,
     pub height: usize,
     pub frame_num: usize,
-    pub framerate: usize,
+    pub framerate: Framerate,
     pub vec: Vec<T>
 }
@@ -4,6 +4,7 @@ pub use cairo::*;
 use crate::pixel::Rgba;
 use crate::image::Image;
 use crate::buffer::Buffer;
+use crate::framerate::Framerate;
 
 pub fn render_image(width: usize, height: usize, builder: &dyn Fn(Context)) -> Image<Rgba> {
     let mut surface =
@@ -15,7 +16,7 @@ pub fn render_image(width: usize, height: usize, builder: &dyn Fn(Context)) -> I
 pub fn render_buffer(
     width: usize,
     height: usize,
-    framerate: usize,
+    framerate: impl Into<Framerate>,
     builder: &dyn Fn(WrapedContext)) -> Buffer<Rgba> {
     let surface = ImageSurface::create(Format::ARgb32, width as i32, height as i32).unwrap();
     let mut images: Vec<Image<Rgba>> = vec![];
@@ -32,7 +33,7 @@ pub fn render_buffer(
         width: width,
         height: height,
         frame_num: images.len(),
-        framerate: framerate,
+        framerate: framerate.into(),
         vec: vec
     }
 }
@@ -0,0 +1,41 @@
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub enum KanteraError {
+    SampleNotSupported(&'static str),
+    OutOfRange(String),
+    UnsupportedChannelNum(usize),
+    InfiniteDuration,
+    InvalidKernel(usize, usize),
+    Parse(String),
+    Panic(String)
+}
+
+impl fmt::Display for KanteraError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KanteraError::SampleNotSupported(name) => write!(f, "{} does not support sample", name),
+            KanteraError::OutOfRange(mes) => write!(f, "out of range: {}", mes),
+            KanteraError::UnsupportedChannelNum(n) => write!(f, "unsupported channel_num: {}", n),
+            KanteraError::InfiniteDuration => write!(f, "duration must be finite"),
+            KanteraError::InvalidKernel(w, h) => write!(f, "kernel must be odd sized and as large as its values: {}x{}", w, h),
+            KanteraError::Parse(mes) => write!(f, "parse error: {}", mes),
+            KanteraError::Panic(mes) => write!(f, "panicked: {}", mes)
+        }
+    }
+}
+
+impl std::error::Error for KanteraError {}
+
+// Turns a panic raised by `f` into an error, for renders that don't report errors by themselves.
+pub fn catch_panic<T>(f: impl FnOnce() -> T) -> Result<T, KanteraError> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).map_err(|payload| {
+        if let Some(mes) = payload.downcast_ref::<&str>() {
+            KanteraError::Panic(mes.to_string())
+        } else if let Some(mes) = payload.downcast_ref::<String>() {
+            KanteraError::Panic(mes.clone())
+        } else {
+            KanteraError::Panic("unknown".to_owned())
+        }
+    })
+}
@@ -1,6 +1,9 @@
 use crate::buffer::Buffer;
 use crate::pixel::Rgba;
 use crate::render::{Render, RenderOpt};
+use crate::framerate::Framerate;
+use crate::parallel::{ParallelOpt, try_render_parallel};
+use crate::error::KanteraError;
 
 pub fn rgbas_to_u8s(block: &[Rgba], u8s: &mut [u8]) {
     for i in 0..block.len() {
@@ -16,43 +19,91 @@ pub fn render_to_mp4(
     sec: f64,
     width: usize,
     height: usize,
-    framerate: usize,
+    framerate: impl Into<Framerate>,
     buffer_frame_num: usize,
     file_name: &str,
     render: &dyn Render<Rgba>) {
-    assert!(sec.is_finite());
-    let frames: usize = (framerate as f64 * sec).floor() as usize;
+    render_to_mp4_parallel(sec, width, height, framerate, buffer_frame_num, file_name, render, false, &ParallelOpt::default());
+}
+
+#[cfg(feature = "ffmpeg")]
+pub fn try_render_to_mp4(
+    sec: f64,
+    width: usize,
+    height: usize,
+    framerate: impl Into<Framerate>,
+    buffer_frame_num: usize,
+    file_name: &str,
+    render: &dyn Render<Rgba>) -> Result<(), KanteraError> {
+    try_render_to_mp4_parallel(sec, width, height, framerate, buffer_frame_num, file_name, render, false, &ParallelOpt::default())
+}
+
+#[cfg(feature = "ffmpeg")]
+#[allow(clippy::too_many_arguments)]
+pub fn render_to_mp4_parallel(
+    sec: f64,
+    width: usize,
+    height: usize,
+    framerate: impl Into<Framerate>,
+    buffer_frame_num: usize,
+    file_name: &str,
+    render: &dyn Render<Rgba>,
+    pixel_center: bool,
+    po: &ParallelOpt) {
+    try_render_to_mp4_parallel(sec, width, height, framerate, buffer_frame_num, file_name, render, pixel_center, po)
+        .unwrap_or_else(|e| panic!("{}", e))
+}
+
+#[cfg(feature = "ffmpeg")]
+#[allow(clippy::too_many_arguments)]
+pub fn try_render_to_mp4_parallel(
+    sec: f64,
+    width: usize,
+    height: usize,
+    framerate: impl Into<Framerate>,
+    buffer_frame_num: usize,
+    file_name: &str,
+    render: &dyn Render<Rgba>,
+    pixel_center: bool,
+    po: &ParallelOpt) -> Result<(), KanteraError> {
+    if !sec.is_finite() {
+        return Err(KanteraError::InfiniteDuration);
+    }
+    let framerate = framerate.into();
+    let frames: usize = framerate.frame_at(sec).max(0) as usize;
     let mut buffer = vec![Rgba::default(); width * height * buffer_frame_num];
     let mut exporter = crate::ffmpeg::Exporter::new(width, height, framerate, file_name, true);
-    for f in 0..frames / buffer_frame_num {
-        render.render(&RenderOpt {
-            x_range: 0..width as i32,
-            y_range: 0..height as i32,
-            res_x: width,
-            res_y: height,
-            frame_range: (f * buffer_frame_num) as i32..((f + 1) * buffer_frame_num) as i32,
-            framerate: framerate
-        }, buffer.as_mut_slice());
-        exporter.push(&buffer);
-    }
-    {
-        let start = (frames / buffer_frame_num) * buffer_frame_num;
-        render.render(&RenderOpt {
+    for start in (0..frames).step_by(buffer_frame_num) {
+        let end = (start + buffer_frame_num).min(frames);
+        let buffer = &mut buffer[..(end - start) * width * height];
+        let result = try_render_parallel(&RenderOpt {
             x_range: 0..width as i32,
             y_range: 0..height as i32,
             res_x: width,
             res_y: height,
-            frame_range: start as i32..frames as i32,
-            framerate: framerate
-        }, buffer.as_mut_slice());
-        exporter.push(&buffer);
+            frame_range: start as i32..end as i32,
+            framerate: framerate,
+            pixel_center
+        }, po, render, buffer);
+        if let Err(e) = result {
+            // Don't leave a truncated video behind.
+            exporter.close();
+            let _ = std::fs::remove_file(file_name);
+            return Err(e);
+        }
+        exporter.push(buffer);
     }
     exporter.close();
+    Ok(())
 }
 
 pub static mut DEBUG_PRINT: bool = true;
 
 pub fn render_to_buffer<T: Default + Clone>(ro: &RenderOpt, render: &dyn Render<T>) -> Buffer<T> {
+    try_render_to_buffer(ro, render).unwrap_or_else(|e| panic!("{}", e))
+}
+
+pub fn try_render_to_buffer<T: Default + Clone>(ro: &RenderOpt, render: &dyn Render<T>) -> Result<Buffer<T>, KanteraError> {
     if unsafe {DEBUG_PRINT} {
         println!("render start: {:#?}", ro);
     }
@@ -62,7 +113,7 @@ pub fn render_to_buffer<T: Default + Clone>(ro: &RenderOpt, render: &dyn Render<
     let y_size = (ro.y_range.end - ro.y_range.start) as usize;
     let frame_num = (ro.frame_range.end - ro.frame_range.start) as usize;
     let mut vec = vec![T::default(); y_size * x_size * frame_num];
-    render.render(ro, vec.as_mut_slice());
+    render.try_render(ro, vec.as_mut_slice())?;
 
     let duration = start.elapsed();
     if unsafe {DEBUG_PRINT} {
@@ -70,52 +121,31 @@ pub fn render_to_buffer<T: Default + Clone>(ro: &RenderOpt, render: &dyn Render<
                 duration.as_secs(), duration.subsec_nanos() / 1_000_000);
     }
 
-    Buffer {
+    Ok(Buffer {
         width: x_size,
         height: y_size,
         frame_num: frame_num,
         framerate: ro.framerate,
         vec: vec
-    }
+    })
 }
 
-use std::thread;
+pub fn render_to_buffer_parallel<T: Default + Clone + Send, U: From<T>>(ro: &RenderOpt, po: &ParallelOpt, render: &dyn Render<T>) -> Buffer<U> {
+    try_render_to_buffer_parallel(ro, po, render).unwrap_or_else(|e| panic!("{}", e))
+}
 
-pub fn render_to_buffer_parallel<T: Default + Clone + Send, U: From<T>>(ro: &RenderOpt, render: &'static (dyn Render<T> + Send + Sync)) -> Buffer<U> {
+pub fn try_render_to_buffer_parallel<T: Default + Clone + Send, U: From<T>>(ro: &RenderOpt, po: &ParallelOpt, render: &dyn Render<T>) -> Result<Buffer<U>, KanteraError> {
     let frame_num = (ro.frame_range.end - ro.frame_range.start) as usize;
     let x_size = (ro.x_range.end - ro.x_range.start) as usize;
     let y_size = (ro.y_range.end - ro.y_range.start) as usize;
-    let n = 4i32; // TODO
-    let handles: Vec<_> = (0..n).map(|i| {
-        let x_range = (ro.x_range.end - ro.x_range.start) * i / n + ro.x_range.start..(ro.x_range.end - ro.x_range.start) * (i + 1) / n + ro.x_range.start;
-        let ro = RenderOpt {
-            x_range,
-            ..ro.clone()
-        };
-        thread::spawn(move || {
-            let mut vec = vec![T::default(); (ro.x_range.end - ro.x_range.start) as usize * y_size * frame_num];
-            render.render(&ro, vec.as_mut_slice());
-            vec
-        })
-    }).collect::<Vec<_>>();
-    let vecs = handles.into_iter().map(|handle| handle.join().unwrap()).collect::<Vec<Vec<T>>>();
-
-    let mut vec = Vec::with_capacity(y_size * x_size * frame_num);
-    for f in 0..frame_num {
-        for y in ro.y_range.clone() {
-            for x in ro.x_range.clone() {
-                let i = ((x - ro.x_range.start) * n) as usize / x_size;
-                let x_size_ = (i + 1) * x_size / n as usize - i * x_size / n as usize;
-                vec.push(vecs[i][f * x_size_ * y_size + (y - ro.y_range.start) as usize * x_size_ + ((x - ro.x_range.start) as usize - i * x_size / n as usize)].clone().into());
-            }
-        }
-    }
+    let mut vec = vec![T::default(); y_size * x_size * frame_num];
+    try_render_parallel(ro, po, render, vec.as_mut_slice())?;
 
-    Buffer {
+    Ok(Buffer {
         width: x_size,
         height: y_size,
         frame_num: frame_num,
         framerate: ro.framerate,
-        vec: vec
-    }
+        vec: vec.into_iter().map(|x| x.into()).collect()
+    })
 }
@@ -1,6 +1,7 @@
 use std::io::Write;
 use std::process::{Command, Child, Stdio};
 use crate::pixel::{Rgba, RgbU8};
+use crate::framerate::Framerate;
 
 pub struct Exporter {
     size: (usize, usize),
@@ -9,7 +10,7 @@ pub struct Exporter {
 }
 
 impl Exporter {
-    pub fn new(width: usize, height: usize, framerate: usize, file_name: &str, debug: bool) -> Self {
+    pub fn new(width: usize, height: usize, framerate: impl Into<Framerate>, file_name: &str, debug: bool) -> Self {
         let child = Command::new("/bin/sh")
             .args(&[
                 "-c",
@@ -17,7 +18,7 @@ impl Exporter {
                     "ffmpeg -hide_banner -f rawvideo -pix_fmt bgra -s {width}x{height} -r {framerate} -i - -pix_fmt yuv420p -y {output}",
                     width = width,
                     height = height,
-                    framerate = framerate,
+                    framerate = framerate.into(),
                     output = file_name).as_str()])
             .stdin(Stdio::piped())
             .stdout(if debug { Stdio::inherit() } else { Stdio::null() })
@@ -130,7 +131,7 @@ impl VideoInfo {
         None
     }
 
-    pub fn get_video_info(&self) -> Option<(usize, usize, usize, usize)> {
+    pub fn get_video_info(&self) -> Option<(usize, usize, usize, Framerate)> {
         match self.get_video_stream() {
             Some(StreamInfo::Video {width, height, frame_num, framerate}) =>
                 Some((*width, *height, *frame_num, *framerate)),
@@ -145,7 +146,7 @@ pub enum StreamInfo {
         width: usize,
         height: usize,
         frame_num: usize,
-        framerate: usize,
+        framerate: Framerate,
     },
     Audio {
         channel_num: usize,
@@ -178,7 +179,7 @@ pub fn probe(file_path: &str) -> VideoInfo {
     let mut width: Option<usize> = None;
     let mut height: Option<usize> = None;
     let mut frame_num: Option<usize> = None;
-    let mut framerate: Option<usize> = None;
+    let mut framerate: Option<Framerate> = None;
     let mut channel_num: Option<usize> = None;
     let mut sample_rate: Option<usize> = None;
     for line in reader.lines() {
@@ -194,8 +195,12 @@ pub fn probe(file_path: &str) -> VideoInfo {
         }
         if line.starts_with("r_frame_rate=") {
             let r_frame_rate = &line[13..];
-            framerate = Regex::new(r"(\d+)/1").unwrap().captures(r_frame_rate).map(
-                |caps| caps.get(1).unwrap().as_str().parse().unwrap());
+            framerate = Regex::new(r"^(\d+)/(\d+)$").unwrap().captures(r_frame_rate).and_then(|caps| {
+                let num: usize = caps.get(1).unwrap().as_str().parse().ok()?;
+                let den: usize = caps.get(2).unwrap().as_str().parse().ok()?;
+                // ffprobe reports 0/0 for streams without a frame rate, e.g. still images.
+                if num == 0 || den == 0 { None } else { Some(Framerate::new(num, den)) }
+            });
         }
         if line.starts_with("nb_frames=") {
             // NOTE: N/A is considered 1.
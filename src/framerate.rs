@@ -0,0 +1,109 @@
+use std::fmt;
+use std::str::FromStr;
+
+// Frames per second as a reduced fraction, e.g. 30000/1001 for 29.97fps.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Framerate {
+    pub num: usize,
+    pub den: usize
+}
+
+pub(crate) fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+impl Framerate {
+    pub fn new(num: usize, den: usize) -> Self {
+        assert!(num != 0 && den != 0, "framerate must be positive: {}/{}", num, den);
+        let g = gcd(num, den);
+        Framerate {
+            num: num / g,
+            den: den / g
+        }
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    // The multiplication is done in integers so that the result is rounded only once,
+    // however far the frame is from zero.
+    pub fn frame_to_time(&self, frame: i64) -> f64 {
+        (frame as i128 * self.den as i128) as f64 / self.num as f64
+    }
+
+    pub fn time_to_frame(&self, time: f64) -> f64 {
+        time * self.num as f64 / self.den as f64
+    }
+
+    // The frame shown at `time`. Times produced by `frame_to_time` map back to the same frame
+    // even if they were rounded down.
+    pub fn frame_at(&self, time: f64) -> i64 {
+        let frame = self.time_to_frame(time);
+        let rounded = frame.round();
+        if (frame - rounded).abs() < 1e-6 {
+            rounded as i64
+        } else {
+            frame.floor() as i64
+        }
+    }
+
+    pub fn mul(&self, n: usize) -> Self {
+        Framerate::new(self.num * n, self.den)
+    }
+}
+
+impl From<usize> for Framerate {
+    fn from(framerate: usize) -> Self {
+        Framerate::new(framerate, 1)
+    }
+}
+
+impl fmt::Display for Framerate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+// Accepts "30", "30000/1001" (as ffprobe's r_frame_rate) and "29.97".
+impl FromStr for Framerate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("invalid framerate: {:?}", s);
+        let (num, den) = if let Some((num, den)) = s.split_once('/') {
+            (num.trim().parse::<usize>().map_err(|_| err())?, den.trim().parse::<usize>().map_err(|_| err())?)
+        } else if let Some((int, frac)) = s.split_once('.') {
+            let den = 10usize.checked_pow(frac.len() as u32).ok_or_else(err)?;
+            let int = if int.is_empty() { 0 } else { int.parse::<usize>().map_err(|_| err())? };
+            let frac = if frac.is_empty() { 0 } else { frac.parse::<usize>().map_err(|_| err())? };
+            (int * den + frac, den)
+        } else {
+            (s.trim().parse::<usize>().map_err(|_| err())?, 1)
+        };
+        if num == 0 || den == 0 {
+            return Err(err());
+        }
+        Ok(Framerate::new(num, den))
+    }
+}
+
+#[test]
+fn test() {
+    let ntsc: Framerate = "30000/1001".parse().unwrap();
+    assert_eq!(ntsc, Framerate::new(30000, 1001));
+    assert_eq!("29.97".parse::<Framerate>().unwrap(), Framerate::new(2997, 100));
+    assert_eq!("60/2".parse::<Framerate>().unwrap(), Framerate::from(30));
+    assert!("0/1".parse::<Framerate>().is_err());
+    assert_eq!(ntsc.to_string(), "30000/1001");
+
+    assert_eq!(ntsc.frame_to_time(30000 * 3600), 1001.0 * 3600.0);
+    for frame in [0, 1, 3, 1001, 107891, 10_000_000] {
+        assert_eq!(ntsc.frame_at(ntsc.frame_to_time(frame)), frame);
+        assert_eq!(Framerate::new(24000, 1001).frame_at(Framerate::new(24000, 1001).frame_to_time(frame)), frame);
+    }
+}
@@ -0,0 +1,64 @@
+use std::hash::Hasher;
+
+// Values that take part in structural hashes, such as render parameters and pixels. The default returns
+// None, meaning the value can't be hashed and neither can whatever contains it.
+pub trait StructuralHash {
+    fn hash_value(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl StructuralHash for f64 {
+    fn hash_value(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("f64");
+        hasher.write_f64(*self);
+        Some(hasher.finish())
+    }
+}
+
+// FNV-1a. Unlike `DefaultHasher`, it is stable across Rust releases, so hashes can be persisted.
+#[derive(Debug, Clone)]
+pub struct StructuralHasher(u64);
+
+impl StructuralHasher {
+    pub fn new(tag: &str) -> Self {
+        let mut hasher = StructuralHasher(0xcbf29ce484222325);
+        hasher.write_str(tag);
+        hasher
+    }
+
+    pub fn write_f64(&mut self, v: f64) {
+        self.write_u64(v.to_bits());
+    }
+
+    pub fn write_str(&mut self, s: &str) {
+        self.write_usize(s.len());
+        self.write(s.as_bytes());
+    }
+}
+
+impl Hasher for StructuralHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    // Fixed endianness and width keep hashes identical on every platform.
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+}
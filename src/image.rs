@@ -1,3 +1,6 @@
+use std::hash::Hasher;
+use crate::hash::{StructuralHash, StructuralHasher};
+
 #[derive(Debug)]
 pub struct Image<T> {
     pub width: usize,
@@ -14,3 +17,15 @@ impl<T: Copy> Image<T> {
         }
     }
 }
+
+impl<T: StructuralHash> StructuralHash for Image<T> {
+    fn hash_value(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("image");
+        hasher.write_usize(self.width);
+        hasher.write_usize(self.height);
+        for v in self.vec.iter() {
+            hasher.write_u64(v.hash_value()?);
+        }
+        Some(hasher.finish())
+    }
+}
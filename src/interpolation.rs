@@ -1,12 +1,45 @@
+use std::hash::Hasher;
 use crate::image::Image;
 use crate::lerp::Lerp;
+use crate::hash::StructuralHasher;
+use crate::error::KanteraError;
 
 pub trait Interpolation<T: Lerp> {
     fn interpolate(&self, image: &Image<T>, x: f64, y: f64) -> T;
+
+    // A stable id of the interpolation and its parameters for structural hashes. None means it can't be
+    // hashed.
+    fn structural_hash(&self) -> Option<u64> {
+        None
+    }
+
+    fn try_interpolate(&self, image: &Image<T>, x: f64, y: f64) -> Result<T, KanteraError> {
+        if 0.0 <= x && x < image.width as f64 && 0.0 <= y && y < image.height as f64 {
+            Ok(self.interpolate(image, x, y))
+        } else {
+            Err(image_out_of_range(image, x, y))
+        }
+    }
 }
 
 pub trait AudioInterpolation<T> {
     fn interpolate(&self, audio_slice: &[T], x: f64) -> T;
+
+    fn try_interpolate(&self, audio_slice: &[T], x: f64) -> Result<T, KanteraError> {
+        if 0.0 <= x && x < audio_slice.len() as f64 {
+            Ok(self.interpolate(audio_slice, x))
+        } else {
+            Err(audio_out_of_range(audio_slice, x))
+        }
+    }
+}
+
+fn image_out_of_range<T>(image: &Image<T>, x: f64, y: f64) -> KanteraError {
+    KanteraError::OutOfRange(format!("({}, {}) on {}x{} image", x, y, image.width, image.height))
+}
+
+fn audio_out_of_range<T>(audio_slice: &[T], x: f64) -> KanteraError {
+    KanteraError::OutOfRange(format!("{} on {} samples", x, audio_slice.len()))
 }
 
 pub struct NearestNeighbor;
@@ -16,9 +49,13 @@ impl<T: Lerp> Interpolation<T> for NearestNeighbor {
         if 0.0 <= x && x < image.width as f64 && 0.0 <= y && y < image.height as f64 {
             image.vec[y as usize * image.width + x as usize].clone()
         } else {
-            panic!()
+            panic!("{}", image_out_of_range(image, x, y))
         }
     }
+
+    fn structural_hash(&self) -> Option<u64> {
+        Some(StructuralHasher::new("nearest_neighbor").finish())
+    }
 }
 
 impl<T: Lerp> AudioInterpolation<T> for NearestNeighbor {
@@ -26,7 +63,7 @@ impl<T: Lerp> AudioInterpolation<T> for NearestNeighbor {
         if 0.0 <= x && x < audio_slice.len() as f64 {
             audio_slice[x as usize].clone()
         } else {
-            panic!()
+            panic!("{}", audio_out_of_range(audio_slice, x))
         }
     }
 }
@@ -36,7 +73,7 @@ impl AudioInterpolation<u16> for NearestNeighbor {
         if 0.0 <= x && x < audio_slice.len() as f64 {
             audio_slice[x as usize].clone()
         } else {
-            panic!()
+            panic!("{}", audio_out_of_range(audio_slice, x))
         }
     }
 }
@@ -58,9 +95,13 @@ impl<T: Lerp> Interpolation<T> for Bilinear {
                         &image.vec[y_ceil * image.width + x_ceil], fx),
                     fy)
         } else {
-            panic!()
+            panic!("{}", image_out_of_range(image, x, y))
         }
     }
+
+    fn structural_hash(&self) -> Option<u64> {
+        Some(StructuralHasher::new("bilinear").finish())
+    }
 }
 
 pub struct Bicubic {
@@ -125,4 +166,12 @@ impl<T: Lerp> Interpolation<T> for Bicubic {
         + (pixel(-1, 1) * hx[0] + pixel(0, 1) * hx[1] + pixel(1, 1) * hx[2] + pixel(2, 1) * hx[3]) * hy[2]
         + (pixel(-1, 2) * hx[0] + pixel(0, 2) * hx[1] + pixel(1, 2) * hx[2] + pixel(2, 2) * hx[3]) * hy[3]
     }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("bicubic");
+        for v in [self.p, self.q, self.r, self.s, self.t, self.u, self.v, self.w] {
+            hasher.write_f64(v);
+        }
+        Some(hasher.finish())
+    }
 }
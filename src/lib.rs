@@ -7,6 +7,7 @@ pub mod timed;
 #[cfg(feature = "cairo")]
 pub mod cairo;
 pub mod pixel;
+pub mod blend;
 pub mod buffer;
 pub mod audio_buffer;
 pub mod image;
@@ -14,14 +15,19 @@ pub mod interpolation;
 pub mod path;
 #[cfg(feature = "cairo")]
 pub mod path_to_image;
+pub mod framerate;
 pub mod render;
+pub mod parallel;
 pub mod audio_render;
 #[cfg(feature = "ffmpeg")]
 pub mod ffmpeg;
 pub mod export;
 pub mod renders;
 pub mod util;
+pub mod error;
+pub mod hash;
 pub mod text;
 pub mod image_import;
+pub mod lut;
 pub mod audio_renders;
 pub mod script;
@@ -0,0 +1,212 @@
+use std::hash::Hasher;
+use crate::hash::StructuralHasher;
+use crate::error::KanteraError;
+
+type Rgb = (f64, f64, f64);
+
+#[derive(Debug, Clone)]
+pub enum LutTable {
+    OneD(Vec<Rgb>),
+    // Red changes fastest, then green, then blue.
+    ThreeD(usize, Vec<Rgb>)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LutInterpolation {
+    Trilinear,
+    Tetrahedral
+}
+
+#[derive(Debug, Clone)]
+pub struct Lut {
+    pub title: Option<String>,
+    pub domain_min: Rgb,
+    pub domain_max: Rgb,
+    pub table: LutTable
+}
+
+fn parse_rgb<'a>(mut words: impl Iterator<Item = &'a str>, line: usize) -> Result<Rgb, KanteraError> {
+    let mut next = || words.next()
+        .and_then(|w| w.parse::<f64>().ok())
+        .ok_or_else(|| KanteraError::Parse(format!("expected three numbers at line {}", line)));
+    Ok((next()?, next()?, next()?))
+}
+
+// Parses an Adobe/Resolve .cube file. Files with both a 1D shaper and a 3D table are not supported.
+pub fn parse_cube(src: &str) -> Result<Lut, KanteraError> {
+    let mut title = None;
+    let mut domain_min = (0.0, 0.0, 0.0);
+    let mut domain_max = (1.0, 1.0, 1.0);
+    let mut size_1d = None;
+    let mut size_3d = None;
+    let mut values = Vec::new();
+    for (i, line) in src.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        let size = |w: Option<&str>| w.and_then(|w| w.parse::<usize>().ok()).filter(|n| *n >= 2)
+            .ok_or_else(|| KanteraError::Parse(format!("invalid size at line {}", i + 1)));
+        match words.next().unwrap() {
+            "TITLE" => title = Some(line["TITLE".len()..].trim().trim_matches('"').to_owned()),
+            "DOMAIN_MIN" => domain_min = parse_rgb(words, i + 1)?,
+            "DOMAIN_MAX" => domain_max = parse_rgb(words, i + 1)?,
+            "LUT_1D_SIZE" => size_1d = Some(size(words.next())?),
+            "LUT_3D_SIZE" => size_3d = Some(size(words.next())?),
+            "LUT_1D_INPUT_RANGE" | "LUT_3D_INPUT_RANGE" => {
+                // Resolve's `min max`, the same for all channels.
+                let mut next = || words.next().and_then(|w| w.parse::<f64>().ok())
+                    .ok_or_else(|| KanteraError::Parse(format!("expected two numbers at line {}", i + 1)));
+                let (min, max) = (next()?, next()?);
+                domain_min = (min, min, min);
+                domain_max = (max, max, max);
+            }
+            // Other keywords, such as Resolve's LUT_IN_VIDEO_RANGE, don't change the table.
+            word if word.starts_with(|c: char| c.is_ascii_alphabetic()) => {}
+            _ => values.push(parse_rgb(line.split_whitespace(), i + 1)?)
+        }
+    }
+    let table = match (size_1d, size_3d) {
+        (Some(n), None) if values.len() == n => LutTable::OneD(values),
+        (None, Some(n)) if values.len() == n * n * n => LutTable::ThreeD(n, values),
+        (Some(_), Some(_)) => return Err(KanteraError::Parse("1D shapers with 3D tables are not supported".to_owned())),
+        (None, None) => return Err(KanteraError::Parse("missing LUT_1D_SIZE or LUT_3D_SIZE".to_owned())),
+        _ => return Err(KanteraError::Parse(format!("unexpected number of entries: {}", values.len())))
+    };
+    Ok(Lut {title, domain_min, domain_max, table})
+}
+
+pub fn load_cube(filepath: &str) -> Result<Lut, KanteraError> {
+    let src = std::fs::read_to_string(filepath).map_err(|e| KanteraError::Parse(format!("{}: {}", filepath, e)))?;
+    parse_cube(&src)
+}
+
+fn add(a: Rgb, b: Rgb) -> Rgb {
+    (a.0 + b.0, a.1 + b.1, a.2 + b.2)
+}
+
+fn sub(a: Rgb, b: Rgb) -> Rgb {
+    (a.0 - b.0, a.1 - b.1, a.2 - b.2)
+}
+
+fn scale(a: Rgb, s: f64) -> Rgb {
+    (a.0 * s, a.1 * s, a.2 * s)
+}
+
+fn lerp(a: Rgb, b: Rgb, v: f64) -> Rgb {
+    add(a, scale(sub(b, a), v))
+}
+
+impl Lut {
+    // Position of `v` in a table with `n` entries, as the lower index and the fraction to the next one.
+    fn position(&self, v: f64, min: f64, max: f64, n: usize) -> (usize, f64) {
+        let x = ((v - min) / (max - min)).clamp(0.0, 1.0) * (n - 1) as f64;
+        let i = (x.floor() as usize).min(n - 2);
+        (i, x - i as f64)
+    }
+
+    pub fn apply(&self, rgb: Rgb, interpolation: LutInterpolation) -> Rgb {
+        let (min, max) = (self.domain_min, self.domain_max);
+        match &self.table {
+            LutTable::OneD(table) => {
+                let n = table.len();
+                let (r, fr) = self.position(rgb.0, min.0, max.0, n);
+                let (g, fg) = self.position(rgb.1, min.1, max.1, n);
+                let (b, fb) = self.position(rgb.2, min.2, max.2, n);
+                (
+                    table[r].0 + (table[r + 1].0 - table[r].0) * fr,
+                    table[g].1 + (table[g + 1].1 - table[g].1) * fg,
+                    table[b].2 + (table[b + 1].2 - table[b].2) * fb
+                )
+            }
+            LutTable::ThreeD(n, table) => {
+                let n = *n;
+                let (r, fr) = self.position(rgb.0, min.0, max.0, n);
+                let (g, fg) = self.position(rgb.1, min.1, max.1, n);
+                let (b, fb) = self.position(rgb.2, min.2, max.2, n);
+                let c = |dr: usize, dg: usize, db: usize| table[(b + db) * n * n + (g + dg) * n + r + dr];
+                match interpolation {
+                    LutInterpolation::Trilinear => {
+                        let c00 = lerp(c(0, 0, 0), c(1, 0, 0), fr);
+                        let c10 = lerp(c(0, 1, 0), c(1, 1, 0), fr);
+                        let c01 = lerp(c(0, 0, 1), c(1, 0, 1), fr);
+                        let c11 = lerp(c(0, 1, 1), c(1, 1, 1), fr);
+                        lerp(lerp(c00, c10, fg), lerp(c01, c11, fg), fb)
+                    }
+                    LutInterpolation::Tetrahedral => {
+                        // Walks from the lowest corner to the highest one through the tetrahedron
+                        // containing the point, along the largest fraction first.
+                        let (c000, c111) = (c(0, 0, 0), c(1, 1, 1));
+                        let (c1, f1, c2, f2, f3) = if fr > fg {
+                            if fg > fb {
+                                (c(1, 0, 0), fr, c(1, 1, 0), fg, fb)
+                            } else if fr > fb {
+                                (c(1, 0, 0), fr, c(1, 0, 1), fb, fg)
+                            } else {
+                                (c(0, 0, 1), fb, c(1, 0, 1), fr, fg)
+                            }
+                        } else if fb > fg {
+                            (c(0, 0, 1), fb, c(0, 1, 1), fg, fr)
+                        } else if fb > fr {
+                            (c(0, 1, 0), fg, c(0, 1, 1), fb, fr)
+                        } else {
+                            (c(0, 1, 0), fg, c(1, 1, 0), fr, fb)
+                        };
+                        add(add(add(c000, scale(sub(c1, c000), f1)), scale(sub(c2, c1), f2)), scale(sub(c111, c2), f3))
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn hash_value(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("lut");
+        let (table, size) = match &self.table {
+            LutTable::OneD(table) => (table, 1),
+            LutTable::ThreeD(n, table) => (table, *n)
+        };
+        hasher.write_usize(size);
+        for v in [self.domain_min, self.domain_max].iter().chain(table.iter()) {
+            hasher.write_f64(v.0);
+            hasher.write_f64(v.1);
+            hasher.write_f64(v.2);
+        }
+        Some(hasher.finish())
+    }
+}
+
+#[test]
+fn test() {
+    let identity = parse_cube("
+        TITLE \"identity\"
+        # comment
+        LUT_3D_SIZE 2
+        0 0 0
+        1 0 0
+        0 1 0
+        1 1 0
+        0 0 1
+        1 0 1
+        0 1 1
+        1 1 1
+    ").unwrap();
+    assert_eq!(identity.title.as_deref(), Some("identity"));
+    for interpolation in [LutInterpolation::Trilinear, LutInterpolation::Tetrahedral].iter() {
+        let (r, g, b) = identity.apply((0.2, 0.5, 0.9), *interpolation);
+        assert!((r - 0.2).abs() < 1e-9 && (g - 0.5).abs() < 1e-9 && (b - 0.9).abs() < 1e-9);
+    }
+
+    let invert = parse_cube("
+        LUT_1D_SIZE 3
+        LUT_IN_VIDEO_RANGE
+        DOMAIN_MIN 0 0 0
+        DOMAIN_MAX 2 2 2
+        1 1 1
+        0.5 0.5 0.5
+        0 0 0
+    ").unwrap();
+    assert_eq!(invert.apply((0.5, 1.0, 3.0), LutInterpolation::Trilinear), (0.75, 0.5, 0.0));
+
+    assert!(parse_cube("LUT_3D_SIZE 2\n0 0 0\n").is_err());
+}
@@ -0,0 +1,189 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::sync::{Mutex, mpsc};
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::render::{Render, RenderOpt};
+use crate::error::KanteraError;
+
+#[derive(Debug, Clone)]
+pub struct ParallelOpt {
+    pub thread_num: usize,
+    // Tiles are clamped to the rendered region, so `usize::MAX` means whole frames.
+    pub tile_width: usize,
+    pub tile_height: usize
+}
+
+impl Default for ParallelOpt {
+    fn default() -> Self {
+        ParallelOpt {
+            thread_num: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            tile_width: 64,
+            tile_height: 64
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Tile {
+    frame: i32,
+    x_range: Range<i32>,
+    y_range: Range<i32>
+}
+
+fn make_tiles(ro: &RenderOpt, po: &ParallelOpt) -> Vec<Tile> {
+    let tile_width = po.tile_width.max(1).min(i32::MAX as usize) as i32;
+    let tile_height = po.tile_height.max(1).min(i32::MAX as usize) as i32;
+    let mut tiles = Vec::new();
+    for frame in ro.frame_range.clone() {
+        let mut y = ro.y_range.start;
+        while y < ro.y_range.end {
+            let y_end = y.saturating_add(tile_height).min(ro.y_range.end);
+            let mut x = ro.x_range.start;
+            while x < ro.x_range.end {
+                let x_end = x.saturating_add(tile_width).min(ro.x_range.end);
+                tiles.push(Tile {frame, x_range: x..x_end, y_range: y..y_end});
+                x = x_end;
+            }
+            y = y_end;
+        }
+    }
+    tiles
+}
+
+// Takes from the front of its own queue, otherwise steals from the back of another one.
+fn next_tile(queues: &[Mutex<VecDeque<Tile>>], i: usize) -> Option<Tile> {
+    if let Some(tile) = queues[i].lock().unwrap().pop_front() {
+        return Some(tile);
+    }
+    for j in 1..queues.len() {
+        if let Some(tile) = queues[(i + j) % queues.len()].lock().unwrap().pop_back() {
+            return Some(tile);
+        }
+    }
+    None
+}
+
+pub fn render_parallel<T: Default + Clone + Send, R: Render<T> + ?Sized>(
+    ro: &RenderOpt,
+    po: &ParallelOpt,
+    render: &R,
+    buffer: &mut [T]) {
+    try_render_parallel(ro, po, render, buffer).unwrap_or_else(|e| panic!("{}", e))
+}
+
+pub fn try_render_parallel<T: Default + Clone + Send, R: Render<T> + ?Sized>(
+    ro: &RenderOpt,
+    po: &ParallelOpt,
+    render: &R,
+    buffer: &mut [T]) -> Result<(), KanteraError> {
+    let tiles = make_tiles(ro, po);
+    let thread_num = po.thread_num.min(tiles.len());
+    if thread_num <= 1 {
+        return render.try_render(ro, buffer);
+    }
+
+    let x_size = (ro.x_range.end - ro.x_range.start) as usize;
+    let y_size = (ro.y_range.end - ro.y_range.start) as usize;
+    // Dealt round-robin so that the threads work on the same frame, which keeps per-frame caches such
+    // as `Map`'s small.
+    let mut queues: Vec<_> = (0..thread_num).map(|_| VecDeque::new()).collect();
+    for (i, tile) in tiles.into_iter().enumerate() {
+        queues[i % thread_num].push_back(tile);
+    }
+    let queues: Vec<_> = queues.into_iter().map(Mutex::new).collect();
+
+    let failed = AtomicBool::new(false);
+    std::thread::scope(|s| {
+        let (sender, receiver) = mpsc::channel::<Result<(Tile, Vec<T>), KanteraError>>();
+        for i in 0..thread_num {
+            let sender = sender.clone();
+            let queues = &queues;
+            let failed = &failed;
+            s.spawn(move || {
+                while let Some(tile) = next_tile(queues, i) {
+                    if failed.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let size = ((tile.x_range.end - tile.x_range.start) * (tile.y_range.end - tile.y_range.start)) as usize;
+                    let mut vec = vec![T::default(); size];
+                    let result = render.try_render(&RenderOpt {
+                        x_range: tile.x_range.clone(),
+                        y_range: tile.y_range.clone(),
+                        frame_range: tile.frame..tile.frame + 1,
+                        ..ro.clone()
+                    }, vec.as_mut_slice());
+                    if let Err(e) = result {
+                        failed.store(true, Ordering::Relaxed);
+                        sender.send(Err(e)).ok();
+                        break;
+                    }
+                    // Sending fails only if the receiver has already returned an error.
+                    if sender.send(Ok((tile, vec))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        for result in receiver {
+            let (tile, vec) = result?;
+            let tile_x_size = (tile.x_range.end - tile.x_range.start) as usize;
+            let offset = (tile.frame - ro.frame_range.start) as usize * x_size * y_size
+                + (tile.x_range.start - ro.x_range.start) as usize;
+            for (j, y) in tile.y_range.clone().enumerate() {
+                let start = offset + (y - ro.y_range.start) as usize * x_size;
+                buffer[start..start + tile_x_size].clone_from_slice(&vec[j * tile_x_size..(j + 1) * tile_x_size]);
+            }
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn test() {
+    use crate::pixel::Rgba;
+    use crate::render::Dummy;
+    use crate::framerate::Framerate;
+
+    let ro = RenderOpt {
+        x_range: 3..50,
+        y_range: 0..37,
+        res_x: 64,
+        res_y: 48,
+        frame_range: 2..5,
+        framerate: Framerate::from(30),
+        pixel_center: false
+    };
+    let size = 47 * 37 * 3;
+    let mut expected = vec![Rgba::default(); size];
+    Dummy().render(&ro, &mut expected);
+    let mut actual = vec![Rgba::default(); size];
+    render_parallel(&ro, &ParallelOpt {thread_num: 3, tile_width: 16, tile_height: 10}, &Dummy(), &mut actual);
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn test_error() {
+    use crate::pixel::Rgba;
+    use crate::renders::{frame::{Frame, FrameType}, sequencer::Sequencer};
+    use crate::framerate::Framerate;
+
+    let ro = RenderOpt {
+        x_range: 0..32,
+        y_range: 0..32,
+        res_x: 32,
+        res_y: 32,
+        frame_range: 0..2,
+        framerate: Framerate::from(30),
+        pixel_center: false
+    };
+    // Frame renders by sampling, which Sequencer doesn't support.
+    let render = Frame {
+        render: Sequencer::<Rgba, Box<dyn Render<Rgba>>>::new(Rgba::default()),
+        frame_type: FrameType::Extend
+    };
+    let mut buffer = vec![Rgba::default(); 32 * 32 * 2];
+    let result = try_render_parallel(&ro, &ParallelOpt {thread_num: 4, tile_width: 8, tile_height: 8}, &render, &mut buffer);
+    assert!(result.is_err());
+}
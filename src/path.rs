@@ -1,4 +1,6 @@
+use std::hash::Hasher;
 use crate::lerp::Lerp;
+use crate::hash::{StructuralHash, StructuralHasher};
 pub use crate::timed::Timed;
 
 #[derive(Debug, Clone, Copy)]
@@ -28,7 +30,7 @@ impl<T: Lerp> Path<T> {
     }
 }
 
-impl<T: Lerp> Timed<T> for Path<T> {
+impl<T: Lerp + StructuralHash + Send + Sync> Timed<T> for Path<T> {
     fn get_value(&self, time: f64) -> T {
         if time < self.points[0].0 {
             return self.points[0].1;
@@ -57,6 +59,28 @@ impl<T: Lerp> Timed<T> for Path<T> {
         }
         self.points.last().unwrap().1
     }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("path");
+        for (time, value, point) in self.points.iter() {
+            hasher.write_f64(*time);
+            hasher.write_u64(value.hash_value()?);
+            match point {
+                Point::Constant => hasher.write_u8(0),
+                Point::Linear => hasher.write_u8(1),
+                Point::Bezier2(handle) => {
+                    hasher.write_u8(2);
+                    hasher.write_u64(handle.hash_value()?);
+                }
+                Point::Bezier3(handle_1, handle_2) => {
+                    hasher.write_u8(3);
+                    hasher.write_u64(handle_1.hash_value()?);
+                    hasher.write_u64(handle_2.hash_value()?);
+                }
+            }
+        }
+        Some(hasher.finish())
+    }
 }
 
 #[test]
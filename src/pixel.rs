@@ -1,4 +1,6 @@
 use crate::lerp::Lerp;
+use crate::hash::{StructuralHash, StructuralHasher};
+use std::hash::Hasher;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Rgba(pub f64, pub f64, pub f64, pub f64);
@@ -18,6 +20,24 @@ impl Rgba {
             self.2 * (1.0 - alpha) + rhs.2 * alpha,
             1.0 - (1.0 - self.3) * (1.0 - alpha))
     }
+
+    pub fn premultiply(&self) -> Rgba {
+        Rgba(self.0 * self.3, self.1 * self.3, self.2 * self.3, self.3)
+    }
+
+    pub fn unpremultiply(&self) -> Rgba {
+        if self.3 > 0.0 {
+            Rgba(self.0 / self.3, self.1 / self.3, self.2 / self.3, self.3)
+        } else {
+            Rgba(0.0, 0.0, 0.0, 0.0)
+        }
+    }
+
+    // The Rec. 709 luma of the colour channels as they are, whatever the alpha. For the luma over black
+    // of a straight colour, take `premultiply().luma()`.
+    pub fn luma(&self) -> f64 {
+        0.2126 * self.0 + 0.7152 * self.1 + 0.0722 * self.2
+    }
 }
 
 impl std::ops::Add for Rgba {
@@ -36,6 +56,17 @@ impl std::ops::Mul<f64> for Rgba {
 
 impl Lerp for Rgba {}
 
+impl StructuralHash for Rgba {
+    fn hash_value(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("rgba");
+        hasher.write_f64(self.0);
+        hasher.write_f64(self.1);
+        hasher.write_f64(self.2);
+        hasher.write_f64(self.3);
+        Some(hasher.finish())
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct RgbU8(pub u8, pub u8, pub u8);
 
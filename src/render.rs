@@ -1,5 +1,9 @@
+use std::hash::Hasher;
 use crate::pixel::Rgba;
+use crate::framerate::Framerate;
 use crate::util::hsl_to_rgb;
+use crate::hash::StructuralHasher;
+use crate::error::{KanteraError, catch_panic};
 
 pub type Res = (usize, usize);
 
@@ -10,10 +14,18 @@ pub struct RenderOpt {
     pub res_x: usize,
     pub res_y: usize,
     pub frame_range: std::ops::Range<i32>,
-    pub framerate: usize,
+    pub framerate: Framerate,
+    pub pixel_center: bool, // Sample at (x + 0.5, y + 0.5) instead of the pixel corner
 }
 
-pub trait Render<T> {
+impl RenderOpt {
+    pub fn uv(&self, x: i32, y: i32) -> (f64, f64) {
+        let offset = if self.pixel_center { 0.5 } else { 0.0 };
+        ((x as f64 + offset) / self.res_x as f64, (y as f64 + offset) / self.res_y as f64)
+    }
+}
+
+pub trait Render<T>: Send + Sync {
     fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> T;
 
     fn render(&self, ro: &RenderOpt, buffer: &mut [T]) {
@@ -22,11 +34,10 @@ pub trait Render<T> {
         let y_size = (y_range.end - y_range.start) as usize;
         let res = (*res_x, *res_y);
         for f in frame_range.start..frame_range.end {
-            let time = f as f64 / *framerate as f64;
+            let time = framerate.frame_to_time(f as i64);
             for y in y_range.clone() {
-                let v = y as f64 / *res_y as f64;
                 for x in x_range.clone() {
-                    let u = x as f64 / *res_x as f64;
+                    let (u, v) = ro.uv(x, y);
                     buffer[(f - frame_range.start) as usize * x_size * y_size + (y - y_range.start) as usize * x_size + (x - x_range.start) as usize] =
                         self.sample(u, v, time, res);
                 }
@@ -34,9 +45,23 @@ pub trait Render<T> {
         }
     }
 
+    fn try_sample(&self, u: f64, v: f64, time: f64, res: Res) -> Result<T, KanteraError> {
+        catch_panic(|| self.sample(u, v, time, res))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [T]) -> Result<(), KanteraError> {
+        catch_panic(|| self.render(ro, buffer))
+    }
+
     fn duration(&self) -> f64 {
         std::f64::INFINITY
     }
+
+    // A hash of the node type, its parameters and its children's hashes. Renders with equal hashes
+    // must produce equal output. None means the render can't be hashed, e.g. it holds a closure.
+    fn structural_hash(&self) -> Option<u64> {
+        None
+    }
 }
 
 impl<T> Render<T> for Box<dyn Render<T>> {
@@ -50,12 +75,27 @@ impl<T> Render<T> for Box<dyn Render<T>> {
         self.as_ref().render(ro, buffer);
     }
 
+    #[inline(always)]
+    fn try_sample(&self, u: f64, v: f64, time: f64, res: Res) -> Result<T, KanteraError> {
+        self.as_ref().try_sample(u, v, time, res)
+    }
+
+    #[inline(always)]
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [T]) -> Result<(), KanteraError> {
+        self.as_ref().try_render(ro, buffer)
+    }
+
     #[inline(always)]
     fn duration(&self) -> f64 {
         self.as_ref().duration()
     }
+
+    #[inline(always)]
+    fn structural_hash(&self) -> Option<u64> {
+        self.as_ref().structural_hash()
+    }
 }
-impl<T> Render<T> for std::rc::Rc<dyn Render<T>> {
+impl<T> Render<T> for std::sync::Arc<dyn Render<T>> {
     #[inline(always)]
     fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> T {
         self.as_ref().sample(u, v, time, res)
@@ -66,10 +106,25 @@ impl<T> Render<T> for std::rc::Rc<dyn Render<T>> {
         self.as_ref().render(ro, buffer);
     }
 
+    #[inline(always)]
+    fn try_sample(&self, u: f64, v: f64, time: f64, res: Res) -> Result<T, KanteraError> {
+        self.as_ref().try_sample(u, v, time, res)
+    }
+
+    #[inline(always)]
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [T]) -> Result<(), KanteraError> {
+        self.as_ref().try_render(ro, buffer)
+    }
+
     #[inline(always)]
     fn duration(&self) -> f64 {
         self.as_ref().duration()
     }
+
+    #[inline(always)]
+    fn structural_hash(&self) -> Option<u64> {
+        self.as_ref().structural_hash()
+    }
 }
 
 pub struct Dummy();
@@ -81,13 +136,14 @@ impl Render<Rgba> for Dummy {
     }
 
     fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
-        let RenderOpt {x_range, y_range, res_x, res_y, frame_range, framerate, ..} = ro;
+        let RenderOpt {x_range, y_range, frame_range, framerate, ..} = ro;
         let x_size = (x_range.end - x_range.start) as usize;
         let y_size = (y_range.end - y_range.start) as usize;
         for f in frame_range.clone() {
             for y in y_range.clone() {
                 for x in x_range.clone() {
-                    let (r, g, b) = hsl_to_rgb(f as f64 * 0.3 / *framerate as f64, x as f64 / *res_x as f64, y as f64 / *res_y as f64);
+                    let (u, v) = ro.uv(x, y);
+                    let (r, g, b) = hsl_to_rgb(framerate.frame_to_time(f as i64) * 0.3, u, v);
                     buffer[(f - frame_range.start) as usize * x_size * y_size + (y - y_range.start) as usize * x_size + (x - x_range.start) as usize] =
                         Rgba(r, g, b, 1.0);
                 }
@@ -98,6 +154,10 @@ impl Render<Rgba> for Dummy {
     fn duration(&self) -> f64 {
         std::f64::INFINITY
     }
+
+    fn structural_hash(&self) -> Option<u64> {
+        Some(StructuralHasher::new("dummy").finish())
+    }
 }
 
 impl Clone for RenderOpt {
@@ -108,7 +168,8 @@ impl Clone for RenderOpt {
             res_x: self.res_x,
             res_y: self.res_y,
             frame_range: self.frame_range.clone(),
-            framerate: self.framerate
+            framerate: self.framerate,
+            pixel_center: self.pixel_center
         }
     }
 }
@@ -0,0 +1,173 @@
+use std::hash::Hasher;
+use crate::pixel::Rgba;
+use crate::render::{Res, Render};
+use crate::hash::StructuralHasher;
+use crate::util::u32_noise;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AntialiasPattern {
+    Grid,
+    RotatedGrid,
+    Jittered
+}
+
+// Averages `samples * samples` samples over the area of each pixel.
+pub struct Antialias<R: Render<Rgba>> {
+    pub render: R,
+    pub pattern: AntialiasPattern,
+    pub samples: usize
+}
+
+impl<R: Render<Rgba>> Antialias<R> {
+    pub fn new(render: R, pattern: AntialiasPattern, samples: usize) -> Self {
+        Antialias {
+            render,
+            pattern,
+            samples
+        }
+    }
+
+    // Sample positions in the pixel, in [0, 1).
+    fn offsets(&self, x: i64, y: i64, time: f64) -> Vec<(f64, f64)> {
+        let n = self.samples.max(1);
+        let mut offsets = Vec::with_capacity(n * n);
+        match self.pattern {
+            AntialiasPattern::Grid => {
+                for j in 0..n {
+                    for i in 0..n {
+                        offsets.push(((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / n as f64));
+                    }
+                }
+            }
+            AntialiasPattern::RotatedGrid => {
+                // atan(1/2) gives every sample its own row and column, as in RGSS.
+                let (s, c) = 0.5f64.atan().sin_cos();
+                for j in 0..n {
+                    for i in 0..n {
+                        let (dx, dy) = ((i as f64 + 0.5) / n as f64 - 0.5, (j as f64 + 0.5) / n as f64 - 0.5);
+                        offsets.push(((dx * c - dy * s + 0.5).rem_euclid(1.0), (dx * s + dy * c + 0.5).rem_euclid(1.0)));
+                    }
+                }
+            }
+            AntialiasPattern::Jittered => {
+                let seed = u32_noise(x as u32, y as u32, (time * 1000.0).round() as i64 as u32);
+                for j in 0..n {
+                    for i in 0..n {
+                        let k = (j * n + i) as u32 * 2;
+                        let rx = u32_noise(seed, k, 0) as f64 / (u32::MAX as f64 + 1.0);
+                        let ry = u32_noise(seed, k + 1, 0) as f64 / (u32::MAX as f64 + 1.0);
+                        offsets.push(((i as f64 + rx) / n as f64, (j as f64 + ry) / n as f64));
+                    }
+                }
+            }
+        }
+        offsets
+    }
+}
+
+impl<R: Render<Rgba>> Render<Rgba> for Antialias<R> {
+    // The samples cover a pixel-sized area centred on (u, v), the point the pixel would be sampled at,
+    // so with `pixel_center` unset pixels stand for the area around their corner.
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        let (px, py) = (u * res.0 as f64, v * res.1 as f64);
+        let (x, y) = (px - 0.5, py - 0.5);
+        let offsets = self.offsets(px.floor() as i64, py.floor() as i64, time);
+        let mut acc = Rgba(0.0, 0.0, 0.0, 0.0);
+        for (dx, dy) in offsets.iter() {
+            acc = acc + self.render.sample((x + dx) / res.0 as f64, (y + dy) / res.1 as f64, time, res).premultiply();
+        }
+        (acc * (1.0 / offsets.len() as f64)).unpremultiply()
+    }
+
+    fn duration(&self) -> f64 {
+        self.render.duration()
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("antialias");
+        hasher.write_u64(self.render.structural_hash()?);
+        hasher.write_u8(self.pattern as u8);
+        hasher.write_usize(self.samples);
+        Some(hasher.finish())
+    }
+}
+
+#[test]
+fn test() {
+    use crate::render::{Dummy, RenderOpt};
+    use crate::renders::sample::Sample;
+    use crate::framerate::Framerate;
+
+    let ro = RenderOpt {
+        x_range: 0..4,
+        y_range: 0..3,
+        res_x: 4,
+        res_y: 3,
+        frame_range: 0..1,
+        framerate: Framerate::from(30),
+        pixel_center: true
+    };
+    let mut expected = vec![Rgba::default(); 4 * 3];
+    for pixel_center in [true, false] {
+        let ro = RenderOpt {pixel_center, ..ro.clone()};
+        Dummy().render(&ro, &mut expected);
+        for pattern in [AntialiasPattern::Grid, AntialiasPattern::RotatedGrid, AntialiasPattern::Jittered] {
+            let mut actual = vec![Rgba::default(); 4 * 3];
+            Antialias::new(Dummy(), pattern, 1).render(&ro, &mut actual);
+            if pattern != AntialiasPattern::Jittered {
+                assert_eq!(actual, expected);
+            } else {
+                // A jittered sample is repeatable and stays within half a pixel of the point sample.
+                let mut again = vec![Rgba::default(); 4 * 3];
+                Antialias::new(Dummy(), pattern, 1).render(&ro, &mut again);
+                assert_eq!(actual, again);
+                assert_ne!(actual, expected);
+                for (a, e) in actual.iter().zip(expected.iter()) {
+                    assert!((a.0 - e.0).abs() < 0.5 && (a.1 - e.1).abs() < 0.5 && (a.2 - e.2).abs() < 0.5);
+                }
+            }
+        }
+    }
+
+    // Jittered offsets stay in their own cell of the pixel, and vary between pixels and frames.
+    let jittered = Antialias::new(Dummy(), AntialiasPattern::Jittered, 3);
+    for (x, y, time) in [(0, 0, 0.0), (1, 0, 0.0), (0, 1, 0.0), (0, 0, 0.5)] {
+        let offsets = jittered.offsets(x, y, time);
+        assert_eq!(offsets, jittered.offsets(x, y, time));
+        for (k, &(dx, dy)) in offsets.iter().enumerate() {
+            let (i, j) = ((k % 3) as f64, (k / 3) as f64);
+            assert!(i / 3.0 <= dx && dx < (i + 1.0) / 3.0 && j / 3.0 <= dy && dy < (j + 1.0) / 3.0);
+        }
+        if (x, y, time) != (0, 0, 0.0) {
+            assert_ne!(offsets, jittered.offsets(0, 0, 0.0));
+        }
+    }
+
+    // A 4x4 grid is a 4x supersampled render averaged down.
+    let mut reference = vec![Rgba::default(); 16 * 12];
+    Dummy().render(&RenderOpt {x_range: 0..16, y_range: 0..12, res_x: 16, res_y: 12, ..ro.clone()}, &mut reference);
+    let mut actual = vec![Rgba::default(); 4 * 3];
+    Antialias::new(Dummy(), AntialiasPattern::Grid, 4).render(&ro, &mut actual);
+    for (i, p) in actual.iter().enumerate() {
+        let (x, y) = (i % 4, i / 4);
+        let mut sum = Rgba(0.0, 0.0, 0.0, 0.0);
+        for j in 0..16 {
+            sum = sum + reference[(y * 4 + j / 4) * 16 + x * 4 + j % 4];
+        }
+        let e = sum * (1.0 / 16.0);
+        assert!((p.0 - e.0).abs() < 1e-9 && (p.1 - e.1).abs() < 1e-9 && (p.2 - e.2).abs() < 1e-9 && (p.3 - e.3).abs() < 1e-9);
+    }
+
+    // An edge through the middle of the second column covers half of its pixels, whatever the pattern.
+    let edge = || Sample::new(Box::new(|u, _, _, res: Res| {
+        if u * (res.0 as f64) < 1.5 { Rgba(1.0, 1.0, 1.0, 1.0) } else { Rgba(0.0, 0.0, 0.0, 0.0) }
+    }));
+    for pattern in [AntialiasPattern::Grid, AntialiasPattern::RotatedGrid, AntialiasPattern::Jittered] {
+        Antialias::new(edge(), pattern, 4).render(&ro, &mut actual);
+        for y in 0..3 {
+            assert_eq!(actual[y * 4], Rgba(1.0, 1.0, 1.0, 1.0));
+            assert_eq!(actual[y * 4 + 1], Rgba(1.0, 1.0, 1.0, 0.5));
+            assert_eq!(actual[y * 4 + 2].3, 0.0);
+        }
+    }
+}
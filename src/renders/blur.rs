@@ -0,0 +1,337 @@
+use std::hash::Hasher;
+use std::ops::Range;
+use std::sync::Arc;
+use crate::pixel::Rgba;
+use crate::render::{Res, Render, RenderOpt};
+use crate::renders::frame::FrameType;
+use crate::timed::Timed;
+use crate::hash::{StructuralHash, StructuralHasher};
+use crate::error::KanteraError;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BlurType {
+    // Approximated by three box blurs, the radius is 3 sigma.
+    Gaussian,
+    Box
+}
+
+#[derive(Clone)]
+pub enum BlurDirection {
+    Both,
+    Horizontal,
+    Vertical,
+    // Along a line at the angle in radians, clockwise from the x axis.
+    Angle(Arc<dyn Timed<f64>>)
+}
+
+// Blurs `render` by `radius` pixels. Pixels outside of the frame are taken from `frame_type`, or from
+// `render` itself if it's None.
+pub struct Blur<R: Render<Rgba>> {
+    pub render: R,
+    pub blur_type: BlurType,
+    pub radius: Arc<dyn Timed<f64>>,
+    pub direction: BlurDirection,
+    pub frame_type: Option<FrameType<Rgba>>
+}
+
+impl<R: Render<Rgba>> Blur<R> {
+    pub fn new(render: R, blur_type: BlurType, radius: Arc<dyn Timed<f64>>) -> Self {
+        Blur {
+            render,
+            blur_type,
+            radius,
+            direction: BlurDirection::Both,
+            frame_type: Some(FrameType::Extend)
+        }
+    }
+}
+
+// Radius of each box pass and the number of passes.
+fn box_passes(blur_type: BlurType, radius: f64) -> (f64, usize) {
+    match blur_type {
+        BlurType::Box => (radius, 1),
+        BlurType::Gaussian => {
+            // Three boxes of width w have the variance of a Gaussian with sigma^2 = (w^2 - 1) / 4.
+            let sigma = radius / 3.0;
+            (((4.0 * sigma * sigma + 1.0).sqrt() - 1.0) / 2.0, 3)
+        }
+    }
+}
+
+// Pixels needed around the frame to blur by `radius`.
+pub(crate) fn blur_margin(blur_type: BlurType, radius: f64) -> usize {
+    let (box_radius, passes) = box_passes(blur_type, radius.max(0.0));
+    (passes * (box_radius.ceil() as usize + 1)).max(radius.max(0.0).ceil() as usize + 1)
+}
+
+// A box blur of a fractional radius: the pixels at the ends of the window are weighted by the fraction.
+fn box_blur_line(line: &[Rgba], out: &mut [Rgba], radius: f64) {
+    let len = line.len() as i64;
+    let n = radius.floor() as i64;
+    let fraction = radius - n as f64;
+    let at = |i: i64| line[i.clamp(0, len - 1) as usize];
+    let norm = 1.0 / (2.0 * radius + 1.0);
+    let mut acc = (-n..=n).map(at).fold(Rgba(0.0, 0.0, 0.0, 0.0), |a, b| a + b);
+    for i in 0..len {
+        out[i as usize] = (acc + (at(i - n - 1) + at(i + n + 1)) * fraction) * norm;
+        acc = acc + at(i + n + 1) + at(i - n) * -1.0;
+    }
+}
+
+fn gaussian_weight(x: f64, sigma: f64) -> f64 {
+    (-x * x / (2.0 * sigma * sigma)).exp()
+}
+
+fn bilinear(buffer: &[Rgba], width: usize, height: usize, x: f64, y: f64) -> Rgba {
+    let (x, y) = (x.clamp(0.0, (width - 1) as f64), y.clamp(0.0, (height - 1) as f64));
+    let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+    let at = |x: usize, y: usize| buffer[y * width + x];
+    (at(x0, y0) * (1.0 - fx) + at(x1, y0) * fx) * (1.0 - fy) + (at(x0, y1) * (1.0 - fx) + at(x1, y1) * fx) * fy
+}
+
+// Blurs a premultiplied `width` by `height` buffer in place. `angle` blurs along a line instead of both
+// axes. Pixels are clamped at the buffer edges, so the buffer should have `blur_margin` pixels of margin.
+pub(crate) fn blur_buffer(buffer: &mut [Rgba], width: usize, height: usize, blur_type: BlurType, radius: f64, axes: (bool, bool), angle: Option<f64>) {
+    if radius <= 0.0 {
+        return;
+    }
+    if let Some(angle) = angle {
+        let (dy, dx) = angle.sin_cos();
+        let reach = radius.ceil() as i64 + 1;
+        let weights: Vec<(f64, f64)> = (-reach..=reach).map(|k| {
+            let k = k as f64;
+            let w = match blur_type {
+                BlurType::Box => (radius + 1.0 - k.abs()).clamp(0.0, 1.0),
+                BlurType::Gaussian => gaussian_weight(k, radius / 3.0)
+            };
+            (k, w)
+        }).filter(|(_, w)| *w > 0.0).collect();
+        let total: f64 = weights.iter().map(|(_, w)| w).sum();
+        let src = buffer.to_vec();
+        for y in 0..height {
+            for x in 0..width {
+                buffer[y * width + x] = weights.iter().fold(Rgba(0.0, 0.0, 0.0, 0.0), |acc, (k, w)| {
+                    acc + bilinear(&src, width, height, x as f64 + dx * k, y as f64 + dy * k) * (w / total)
+                });
+            }
+        }
+        return;
+    }
+    let (box_radius, passes) = box_passes(blur_type, radius);
+    let mut line = Vec::new();
+    let mut out = Vec::new();
+    if axes.0 {
+        out.resize(width, Rgba::default());
+        for y in 0..height {
+            for _ in 0..passes {
+                box_blur_line(&buffer[y * width..(y + 1) * width], &mut out, box_radius);
+                buffer[y * width..(y + 1) * width].copy_from_slice(&out);
+            }
+        }
+    }
+    if axes.1 {
+        out.resize(height, Rgba::default());
+        for x in 0..width {
+            line.clear();
+            line.extend((0..height).map(|y| buffer[y * width + x]));
+            for _ in 0..passes {
+                box_blur_line(&line, &mut out, box_radius);
+                line.copy_from_slice(&out);
+            }
+            for (y, p) in line.iter().enumerate() {
+                buffer[y * width + x] = *p;
+            }
+        }
+    }
+}
+
+// The coordinate in [0, size) that `x` takes its pixel from, or None for a constant.
+fn frame_coord<T: Copy>(x: i32, size: i32, frame_type: &FrameType<T>) -> Option<i32> {
+    if (0..size).contains(&x) {
+        return Some(x);
+    }
+    match frame_type {
+        FrameType::Constant(_) => None,
+        FrameType::Extend => Some(x.clamp(0, size - 1)),
+        FrameType::Repeat => Some(x.rem_euclid(size)),
+        FrameType::Reflect => {
+            let m = x.rem_euclid(size * 2);
+            Some(if m < size { m } else { size * 2 - 1 - m })
+        }
+    }
+}
+
+// The range of the frame that `range` takes its pixels from.
+fn frame_range<T: Copy>(range: &Range<i32>, size: i32, frame_type: &FrameType<T>) -> Range<i32> {
+    match frame_type {
+        FrameType::Repeat | FrameType::Reflect if range.start < 0 || size < range.end => 0..size,
+        _ => {
+            let start = range.start.clamp(0, size - 1);
+            start..range.end.clamp(start + 1, size)
+        }
+    }
+}
+
+// Renders frame `f` with `margin` pixels around `ro`'s ranges, taking the pixels outside of the frame
+// from `frame_type`.
+pub(crate) fn render_with_margin<R: Render<Rgba>>(
+    render: &R,
+    ro: &RenderOpt,
+    f: i32,
+    margin: usize,
+    frame_type: &Option<FrameType<Rgba>>,
+    buffer: &mut Vec<Rgba>) -> Result<(), KanteraError> {
+    let margin = margin as i32;
+    let x_range = ro.x_range.start - margin..ro.x_range.end + margin;
+    let y_range = ro.y_range.start - margin..ro.y_range.end + margin;
+    let (width, height) = ((x_range.end - x_range.start) as usize, (y_range.end - y_range.start) as usize);
+    buffer.clear();
+    buffer.resize(width * height, Rgba::default());
+    let frame_type = match frame_type {
+        Some(frame_type) => frame_type,
+        None => {
+            return render.try_render(&RenderOpt {
+                x_range,
+                y_range,
+                frame_range: f..f + 1,
+                ..ro.clone()
+            }, buffer);
+        }
+    };
+    let (res_x, res_y) = (ro.res_x as i32, ro.res_y as i32);
+    let source_x = frame_range(&x_range, res_x, frame_type);
+    let source_y = frame_range(&y_range, res_y, frame_type);
+    let source_width = (source_x.end - source_x.start) as usize;
+    let mut source = vec![Rgba::default(); source_width * (source_y.end - source_y.start) as usize];
+    render.try_render(&RenderOpt {
+        x_range: source_x.clone(),
+        y_range: source_y.clone(),
+        frame_range: f..f + 1,
+        ..ro.clone()
+    }, &mut source)?;
+    for (j, y) in y_range.enumerate() {
+        let sy = frame_coord(y, res_y, frame_type);
+        for (i, x) in x_range.clone().enumerate() {
+            buffer[j * width + i] = match (frame_coord(x, res_x, frame_type), sy, frame_type) {
+                (Some(sx), Some(sy), _) => source[(sy - source_y.start) as usize * source_width + (sx - source_x.start) as usize],
+                (_, _, FrameType::Constant(t)) => *t,
+                _ => unreachable!()
+            };
+        }
+    }
+    Ok(())
+}
+
+impl<R: Render<Rgba>> Render<Rgba> for Blur<R> {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        self.try_sample(u, v, time, res).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_sample(&self, _u: f64, _v: f64, _time: f64, _res: Res) -> Result<Rgba, KanteraError> {
+        Err(KanteraError::SampleNotSupported("Blur"))
+    }
+
+    fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) -> Result<(), KanteraError> {
+        let x_size = (ro.x_range.end - ro.x_range.start) as usize;
+        let y_size = (ro.y_range.end - ro.y_range.start) as usize;
+        let mut sub_buffer = Vec::new();
+        for f in ro.frame_range.clone() {
+            let time = ro.framerate.frame_to_time(f as i64);
+            let radius = self.radius.get_value(time).max(0.0);
+            let margin = blur_margin(self.blur_type, radius);
+            render_with_margin(&self.render, ro, f, margin, &self.frame_type, &mut sub_buffer)?;
+            let (width, height) = (x_size + margin * 2, y_size + margin * 2);
+            for p in sub_buffer.iter_mut() {
+                *p = p.premultiply();
+            }
+            let (axes, angle) = match &self.direction {
+                BlurDirection::Both => ((true, true), None),
+                BlurDirection::Horizontal => ((true, false), None),
+                BlurDirection::Vertical => ((false, true), None),
+                BlurDirection::Angle(angle) => ((false, false), Some(angle.get_value(time)))
+            };
+            blur_buffer(&mut sub_buffer, width, height, self.blur_type, radius, axes, angle);
+            let frame = &mut buffer[(f - ro.frame_range.start) as usize * x_size * y_size..][..x_size * y_size];
+            for y in 0..y_size {
+                for x in 0..x_size {
+                    frame[y * x_size + x] = sub_buffer[(y + margin) * width + x + margin].unpremultiply();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn duration(&self) -> f64 {
+        self.render.duration()
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("blur");
+        hasher.write_u64(self.render.structural_hash()?);
+        hasher.write_u8(self.blur_type as u8);
+        hasher.write_u64(self.radius.structural_hash()?);
+        match &self.direction {
+            BlurDirection::Both => hasher.write_u8(0),
+            BlurDirection::Horizontal => hasher.write_u8(1),
+            BlurDirection::Vertical => hasher.write_u8(2),
+            BlurDirection::Angle(angle) => {
+                hasher.write_u8(3);
+                hasher.write_u64(angle.structural_hash()?);
+            }
+        }
+        match &self.frame_type {
+            None => hasher.write_u8(0),
+            Some(FrameType::Constant(t)) => {
+                hasher.write_u8(1);
+                hasher.write_u64(t.hash_value()?);
+            }
+            Some(FrameType::Extend) => hasher.write_u8(2),
+            Some(FrameType::Repeat) => hasher.write_u8(3),
+            Some(FrameType::Reflect) => hasher.write_u8(4)
+        }
+        Some(hasher.finish())
+    }
+}
+
+#[test]
+fn test() {
+    use crate::renders::functional_render::pixels;
+    use crate::framerate::Framerate;
+
+    let ro = RenderOpt {
+        x_range: 0..8,
+        y_range: 0..1,
+        res_x: 8,
+        res_y: 1,
+        frame_range: 0..1,
+        framerate: Framerate::from(30),
+        pixel_center: false
+    };
+    // White on the left half and black on the right half.
+    let halves = || pixels(|x, _| if x < 4 { Rgba(1.0, 1.0, 1.0, 1.0) } else { Rgba(0.0, 0.0, 0.0, 1.0) });
+    let mut buffer = vec![Rgba::default(); 8];
+
+    let blur = Blur::new(halves(), BlurType::Box, Arc::new(1.0));
+    blur.render(&ro, &mut buffer);
+    let values: Vec<f64> = buffer.iter().map(|p| (p.0 * 3.0).round() / 3.0).collect();
+    assert_eq!(values, vec![1.0, 1.0, 1.0, 2.0 / 3.0, 1.0 / 3.0, 0.0, 0.0, 0.0]);
+
+    // With Repeat the right edge takes the white pixels from the left.
+    let mut blur = Blur::new(halves(), BlurType::Gaussian, Arc::new(3.0));
+    blur.frame_type = Some(FrameType::Repeat);
+    blur.render(&ro, &mut buffer);
+    assert!(buffer[7].0 > 0.1 && buffer[0].0 < 0.9);
+    assert!((buffer[3].0 + buffer[7].0 - 1.0).abs() < 1e-9);
+
+    // A vertical blur leaves rows alone.
+    let mut blur = Blur::new(halves(), BlurType::Gaussian, Arc::new(3.0));
+    blur.direction = BlurDirection::Vertical;
+    blur.render(&ro, &mut buffer);
+    assert!((buffer[3].0 - 1.0).abs() < 1e-9 && buffer[4].0.abs() < 1e-9);
+}
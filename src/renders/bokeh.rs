@@ -1,17 +1,24 @@
+use std::hash::Hasher;
 use crate::pixel::Rgba;
 use crate::render::{Res, Render, RenderOpt};
+use crate::hash::StructuralHasher;
+use crate::error::KanteraError;
 use crate::path::Timed;
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub struct Bokeh<R: Render<Rgba>> {
     pub render: R,
     pub max_size: usize,
-    pub size: Rc<dyn Timed<f64>> // TODO: make pixel size
+    pub size: Arc<dyn Timed<f64>> // TODO: make pixel size
 }
 
 impl<R: Render<Rgba>> Render<Rgba> for Bokeh<R> {
-    fn sample(&self, _u: f64, _v: f64, _time: f64, _res: Res) -> Rgba {
-        panic!("Bokeh cannot sample");
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        self.try_sample(u, v, time, res).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_sample(&self, _u: f64, _v: f64, _time: f64, _res: Res) -> Result<Rgba, KanteraError> {
+        Err(KanteraError::SampleNotSupported("Bokeh"))
     }
 
     fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
@@ -22,15 +29,13 @@ impl<R: Render<Rgba>> Render<Rgba> for Bokeh<R> {
         let mut sub_buffer = vec![Rgba::default(); (res_x + size * 2 + 1) * (res_y + size * 2 + 1)];
 
         for f in frame_range.start..frame_range.end {
-            let time = f as f64 / *framerate as f64;
+            let time = framerate.frame_to_time(f as i64);
             self.render.render(
                 &RenderOpt {
                     x_range: x_range.start - size as i32..x_range.end + size as i32,
                     y_range: y_range.start - size as i32..y_range.end + size as i32,
-                    res_x: *res_x,
-                    res_y: *res_y,
                     frame_range: f..f + 1,
-                    framerate: *framerate
+                    ..ro.clone()
                 },
                 sub_buffer.as_mut_slice());
 
@@ -71,7 +76,7 @@ impl<R: Render<Rgba>> Render<Rgba> for Bokeh<R> {
                     acc.2 += rgba.2;
                     acc.3 += rgba.3;
                 }
-                for y in size..res_y + size {
+                for y in size..y_size + size {
                     let left = sub_buffer[(y - real_size) * (x_size + size * 2) + x];
 
                     let rgba = &mut sub_buffer[(y - size) * (x_size + size * 2) + x];
@@ -100,4 +105,12 @@ impl<R: Render<Rgba>> Render<Rgba> for Bokeh<R> {
     fn duration(&self) -> f64 {
         self.render.duration()
     }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("bokeh");
+        hasher.write_u64(self.render.structural_hash()?);
+        hasher.write_usize(self.max_size);
+        hasher.write_u64(self.size.structural_hash()?);
+        Some(hasher.finish())
+    }
 }
@@ -0,0 +1,190 @@
+use std::collections::{HashMap, VecDeque};
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, OnceLock};
+use crate::render::{Res, Render, RenderOpt};
+use crate::framerate::Framerate;
+use crate::error::KanteraError;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Key {
+    frame: i32,
+    framerate: Framerate,
+    res: Res,
+    x_range: (i32, i32),
+    y_range: (i32, i32),
+    pixel_center: bool
+}
+
+struct Entry<T> {
+    vec: Vec<T>,
+    last_used: u64
+}
+
+struct CacheState<T> {
+    entries: HashMap<Key, Entry<T>>,
+    bytes: usize,
+    clock: u64
+}
+
+pub struct Cache<T, R: Render<T>> {
+    pub render: R,
+    pub max_bytes: usize,
+    state: Mutex<CacheState<T>>,
+    t: PhantomData<T>
+}
+
+impl<T, R: Render<T>> Cache<T, R> {
+    pub fn new(render: R, max_bytes: usize) -> Self {
+        Cache {
+            render,
+            max_bytes,
+            state: Mutex::new(CacheState {
+                entries: HashMap::new(),
+                bytes: 0,
+                clock: 0
+            }),
+            t: PhantomData
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.entries.clear();
+        state.bytes = 0;
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.state.lock().unwrap().bytes
+    }
+}
+
+impl<T> CacheState<T> {
+    fn get(&mut self, key: &Key) -> Option<&Vec<T>> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.entries.get_mut(key).map(|entry| {
+            entry.last_used = clock;
+            &entry.vec
+        })
+    }
+
+    fn insert(&mut self, key: Key, vec: Vec<T>, max_bytes: usize) {
+        let bytes = vec.len() * std::mem::size_of::<T>();
+        if bytes > max_bytes {
+            return;
+        }
+        while self.bytes + bytes > max_bytes {
+            let oldest = self.entries.iter().min_by_key(|(_, entry)| entry.last_used).map(|(key, _)| key.clone()).unwrap();
+            let entry = self.entries.remove(&oldest).unwrap();
+            self.bytes -= entry.vec.len() * std::mem::size_of::<T>();
+        }
+        self.clock += 1;
+        self.bytes += bytes;
+        if let Some(old) = self.entries.insert(key, Entry {vec, last_used: self.clock}) {
+            self.bytes -= old.vec.len() * std::mem::size_of::<T>();
+        }
+    }
+}
+
+impl<T: Default + Clone + Send + Sync, R: Render<T>> Render<T> for Cache<T, R> {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> T {
+        self.render.sample(u, v, time, res)
+    }
+
+    fn render(&self, ro: &RenderOpt, buffer: &mut [T]) {
+        let frame_size = ((ro.x_range.end - ro.x_range.start) * (ro.y_range.end - ro.y_range.start)) as usize;
+        for f in ro.frame_range.clone() {
+            let key = Key {
+                frame: f,
+                framerate: ro.framerate,
+                res: (ro.res_x, ro.res_y),
+                x_range: (ro.x_range.start, ro.x_range.end),
+                y_range: (ro.y_range.start, ro.y_range.end),
+                pixel_center: ro.pixel_center
+            };
+            let buffer = &mut buffer[(f - ro.frame_range.start) as usize * frame_size..(f - ro.frame_range.start + 1) as usize * frame_size];
+            if let Some(vec) = self.state.lock().unwrap().get(&key) {
+                buffer.clone_from_slice(vec);
+                continue;
+            }
+            // Render without holding the lock so that other threads can use the cache meanwhile.
+            self.render.render(&RenderOpt {
+                frame_range: f..f + 1,
+                ..ro.clone()
+            }, buffer);
+            self.state.lock().unwrap().insert(key, buffer.to_vec(), self.max_bytes);
+        }
+    }
+
+    fn duration(&self) -> f64 {
+        self.render.duration()
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        self.render.structural_hash()
+    }
+}
+
+type Slot<V> = Arc<OnceLock<Result<V, KanteraError>>>;
+
+// Keeps the last `capacity` values by key. Callers asking for a key that is being computed wait for it
+// instead of computing it again, so tiles of the same frame share one render.
+pub(crate) struct Memo<K, V> {
+    capacity: usize,
+    slots: Mutex<VecDeque<(K, Slot<V>)>>
+}
+
+impl<K: PartialEq, V: Clone> Memo<K, V> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Memo {capacity, slots: Mutex::new(VecDeque::new())}
+    }
+
+    pub(crate) fn get_or_try_init(&self, key: K, f: impl FnOnce() -> Result<V, KanteraError>) -> Result<V, KanteraError> {
+        let slot = {
+            let mut slots = self.slots.lock().unwrap();
+            match slots.iter().find(|(k, _)| *k == key) {
+                Some((_, slot)) => slot.clone(),
+                None => {
+                    if slots.len() >= self.capacity.max(1) {
+                        slots.pop_front();
+                    }
+                    let slot = Arc::new(OnceLock::new());
+                    slots.push_back((key, slot.clone()));
+                    slot
+                }
+            }
+        };
+        slot.get_or_init(f).clone()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.slots.get_mut().unwrap().clear();
+    }
+}
+
+#[test]
+fn test() {
+    use crate::pixel::Rgba;
+    use crate::render::Dummy;
+
+    let frame_bytes = 4 * 3 * std::mem::size_of::<Rgba>();
+    let cache = Cache::new(Dummy(), frame_bytes * 2);
+    let ro = RenderOpt {
+        x_range: 0..4,
+        y_range: 0..3,
+        res_x: 4,
+        res_y: 3,
+        frame_range: 0..3,
+        framerate: Framerate::from(30),
+        pixel_center: false
+    };
+    let mut expected = vec![Rgba::default(); 4 * 3 * 3];
+    Dummy().render(&ro, &mut expected);
+    let mut actual = vec![Rgba::default(); 4 * 3 * 3];
+    cache.render(&ro, &mut actual);
+    assert_eq!(actual, expected);
+    assert_eq!(cache.bytes(), frame_bytes * 2);
+    let mut actual = vec![Rgba::default(); 4 * 3 * 3];
+    cache.render(&ro, &mut actual);
+    assert_eq!(actual, expected);
+}
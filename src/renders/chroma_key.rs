@@ -0,0 +1,258 @@
+use std::hash::Hasher;
+use std::sync::Arc;
+use crate::pixel::Rgba;
+use crate::render::{Res, Render, RenderOpt};
+use crate::timed::Timed;
+use crate::hash::StructuralHasher;
+use crate::error::KanteraError;
+
+// Removes `key_color` by the distance of each pixel to the key in the YCbCr chroma plane.
+// `tolerance` and `softness` are fractions of the key's own chroma: pixels closer than `tolerance` are
+// fully transparent and the matte ramps up to opaque over `softness`. `spill` in [0, 1] removes the key
+// colour from what's left. `choke` shrinks (or grows, if negative) the matte and `feather` blurs it,
+// both in pixels and limited to `max_size`.
+pub struct ChromaKey<R: Render<Rgba>> {
+    pub render: R,
+    pub key_color: Arc<dyn Timed<Rgba>>,
+    pub tolerance: Arc<dyn Timed<f64>>,
+    pub softness: Arc<dyn Timed<f64>>,
+    pub spill: Arc<dyn Timed<f64>>,
+    pub choke: Arc<dyn Timed<f64>>,
+    pub feather: Arc<dyn Timed<f64>>,
+    pub max_size: usize
+}
+
+// Rec. 709 YCbCr.
+fn to_ycbcr(p: &Rgba) -> (f64, f64, f64) {
+    let y = p.luma();
+    (y, (p.2 - y) / 1.8556, (p.0 - y) / 1.5748)
+}
+
+fn from_ycbcr(y: f64, cb: f64, cr: f64) -> (f64, f64, f64) {
+    let r = y + 1.5748 * cr;
+    let b = y + 1.8556 * cb;
+    let g = (y - 0.2126 * r - 0.0722 * b) / 0.7152;
+    (r, g, b)
+}
+
+struct Key {
+    cb: f64,
+    cr: f64,
+    norm: f64,
+    tolerance: f64,
+    softness: f64,
+    spill: f64
+}
+
+impl Key {
+    // Returns the despilled pixel and its matte.
+    fn apply(&self, p: &Rgba) -> (Rgba, f64) {
+        let (y, cb, cr) = to_ycbcr(p);
+        let d = ((cb - self.cb).powi(2) + (cr - self.cr).powi(2)).sqrt() / self.norm;
+        let matte = if self.softness <= 0.0 {
+            if d > self.tolerance { 1.0 } else { 0.0 }
+        } else {
+            ((d - self.tolerance) / self.softness).clamp(0.0, 1.0)
+        };
+        // Only the chroma towards the key is removed, the luma is kept.
+        let s = ((cb * self.cb + cr * self.cr) / self.norm).max(0.0) * self.spill;
+        let (r, g, b) = from_ycbcr(y, cb - self.cb / self.norm * s, cr - self.cr / self.norm * s);
+        (Rgba(r.clamp(0.0, 1.0), g.clamp(0.0, 1.0), b.clamp(0.0, 1.0), p.3), matte)
+    }
+}
+
+impl<R: Render<Rgba>> ChromaKey<R> {
+    pub fn new(render: R, key_color: Arc<dyn Timed<Rgba>>) -> Self {
+        ChromaKey {
+            render,
+            key_color,
+            tolerance: Arc::new(0.3),
+            softness: Arc::new(0.2),
+            spill: Arc::new(1.0),
+            choke: Arc::new(0.0),
+            feather: Arc::new(0.0),
+            max_size: 8
+        }
+    }
+
+    fn key(&self, time: f64) -> Key {
+        let (_, cb, cr) = to_ycbcr(&self.key_color.get_value(time));
+        Key {
+            cb,
+            cr,
+            norm: (cb * cb + cr * cr).sqrt().max(1e-6),
+            tolerance: self.tolerance.get_value(time),
+            softness: self.softness.get_value(time),
+            spill: self.spill.get_value(time).clamp(0.0, 1.0)
+        }
+    }
+}
+
+// (length, number of lines, step and line step) to walk a w by h buffer along rows or columns.
+fn lines(w: usize, h: usize, horizontal: bool) -> (usize, usize, usize, usize) {
+    if horizontal { (w, h, 1, w) } else { (h, w, w, 1) }
+}
+
+// Minimum (or maximum) of `radius` neighbours on each side.
+fn extremum(src: &[f64], dst: &mut [f64], (w, h): (usize, usize), horizontal: bool, radius: usize, max: bool) {
+    let (len, lines, step, line_step) = lines(w, h, horizontal);
+    for l in 0..lines {
+        for i in 0..len {
+            let range = i.saturating_sub(radius)..(i + radius + 1).min(len);
+            let values = range.map(|j| src[l * line_step + j * step]);
+            dst[l * line_step + i * step] = if max { values.fold(0.0, f64::max) } else { values.fold(1.0, f64::min) };
+        }
+    }
+}
+
+// Box blur with a running sum, the edges are averaged over the available neighbours.
+fn box_blur(src: &[f64], dst: &mut [f64], (w, h): (usize, usize), horizontal: bool, radius: usize) {
+    let (len, lines, step, line_step) = lines(w, h, horizontal);
+    for l in 0..lines {
+        let at = |j: usize| src[l * line_step + j * step];
+        let mut acc: f64 = (0..radius.min(len)).map(at).sum();
+        for i in 0..len {
+            if i + radius < len {
+                acc += at(i + radius);
+            }
+            if i > radius {
+                acc -= at(i - radius - 1);
+            }
+            let n = (i + radius + 1).min(len) - i.saturating_sub(radius);
+            dst[l * line_step + i * step] = acc / n as f64;
+        }
+    }
+}
+
+impl<R: Render<Rgba>> Render<Rgba> for ChromaKey<R> {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        self.try_sample(u, v, time, res).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    // Sampling works only while the matte is neither choked nor feathered.
+    fn try_sample(&self, u: f64, v: f64, time: f64, res: Res) -> Result<Rgba, KanteraError> {
+        if self.choke.get_value(time).round() != 0.0 || self.feather.get_value(time).round() > 0.0 {
+            return Err(KanteraError::SampleNotSupported("ChromaKey"));
+        }
+        let (p, matte) = self.key(time).apply(&self.render.try_sample(u, v, time, res)?);
+        Ok(Rgba(p.0, p.1, p.2, p.3 * matte))
+    }
+
+    fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) -> Result<(), KanteraError> {
+        let RenderOpt {x_range, y_range, frame_range, framerate, ..} = ro;
+        let x_size = (x_range.end - x_range.start) as usize;
+        let y_size = (y_range.end - y_range.start) as usize;
+        let mut sub_buffer = Vec::new();
+        let mut matte = Vec::new();
+        let mut tmp = Vec::new();
+
+        for f in frame_range.clone() {
+            let time = framerate.frame_to_time(f as i64);
+            let choke = self.choke.get_value(time).round();
+            let choke_size = (choke.abs() as usize).min(self.max_size);
+            let feather_size = (self.feather.get_value(time).round().max(0.0) as usize).min(self.max_size);
+            // The choke and the feather each need their size in pixels around the frame.
+            let margin = choke_size + feather_size;
+            let (w, h) = (x_size + margin * 2, y_size + margin * 2);
+            sub_buffer.resize(w * h, Rgba::default());
+            matte.resize(w * h, 0.0);
+            tmp.resize(w * h, 0.0);
+            self.render.try_render(&RenderOpt {
+                x_range: x_range.start - margin as i32..x_range.end + margin as i32,
+                y_range: y_range.start - margin as i32..y_range.end + margin as i32,
+                frame_range: f..f + 1,
+                ..ro.clone()
+            }, &mut sub_buffer)?;
+
+            let key = self.key(time);
+            for (p, m) in sub_buffer.iter_mut().zip(matte.iter_mut()) {
+                let (q, n) = key.apply(p);
+                *p = q;
+                *m = n;
+            }
+
+            if choke_size > 0 {
+                extremum(&matte, &mut tmp, (w, h), true, choke_size, choke < 0.0);
+                extremum(&tmp, &mut matte, (w, h), false, choke_size, choke < 0.0);
+            }
+            if feather_size > 0 {
+                box_blur(&matte, &mut tmp, (w, h), true, feather_size);
+                box_blur(&tmp, &mut matte, (w, h), false, feather_size);
+            }
+
+            let frame = &mut buffer[(f - frame_range.start) as usize * x_size * y_size..][..x_size * y_size];
+            for y in 0..y_size {
+                for x in 0..x_size {
+                    let i = (y + margin) * w + x + margin;
+                    let p = sub_buffer[i];
+                    frame[y * x_size + x] = Rgba(p.0, p.1, p.2, p.3 * matte[i]);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn duration(&self) -> f64 {
+        self.render.duration()
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("chroma_key");
+        hasher.write_u64(self.render.structural_hash()?);
+        hasher.write_u64(self.key_color.structural_hash()?);
+        hasher.write_u64(self.tolerance.structural_hash()?);
+        hasher.write_u64(self.softness.structural_hash()?);
+        hasher.write_u64(self.spill.structural_hash()?);
+        hasher.write_u64(self.choke.structural_hash()?);
+        hasher.write_u64(self.feather.structural_hash()?);
+        hasher.write_usize(self.max_size);
+        Some(hasher.finish())
+    }
+}
+
+#[test]
+fn test() {
+    use crate::renders::{plain::Plain, functional_render::{FunctionalRender, pixels}};
+    use crate::framerate::Framerate;
+
+    let green = Rgba(0.1, 0.8, 0.2, 1.0);
+    let key = |p: Rgba| ChromaKey::new(Plain::new(p), Arc::new(green)).sample(0.0, 0.0, 0.0, (1, 1));
+    assert_eq!(key(green).3, 0.0);
+    assert_eq!(key(Rgba(0.15, 0.75, 0.25, 1.0)).3, 0.0);
+    assert_eq!(key(Rgba(0.9, 0.6, 0.5, 1.0)).3, 1.0);
+    // Grey is left as is and green spill on it is removed.
+    let grey = key(Rgba(0.5, 0.5, 0.5, 1.0));
+    assert!((grey.0 - 0.5).abs() < 1e-9 && (grey.1 - 0.5).abs() < 1e-9);
+    let spill = key(Rgba(0.5, 0.6, 0.5, 1.0));
+    assert!(spill.3 == 1.0 && spill.1 - spill.0 < 0.1 - 1e-3);
+
+    // The left half is the screen. Choking by a pixel removes the foreground next to it.
+    let ro = RenderOpt {
+        x_range: 0..4,
+        y_range: 0..1,
+        res_x: 4,
+        res_y: 1,
+        frame_range: 0..1,
+        framerate: Framerate::from(30),
+        pixel_center: false
+    };
+    let source = pixels(move |x, _| if x < 2 { green } else { Rgba(0.9, 0.6, 0.5, 1.0) });
+    let mut chroma_key = ChromaKey::new(source, Arc::new(green));
+    chroma_key.choke = Arc::new(1.0);
+    chroma_key.max_size = 1;
+    let mut buffer = vec![Rgba::default(); 4];
+    chroma_key.render(&ro, &mut buffer);
+    assert_eq!(buffer.iter().map(|p| p.3).collect::<Vec<_>>(), vec![0.0, 0.0, 0.0, 1.0]);
+
+    // Without a choke or a feather there is no margin to render.
+    let source = FunctionalRender(Box::new(move |ro: &RenderOpt, _time: f64, buffer: &mut [Rgba]| {
+        assert_eq!((ro.x_range.clone(), ro.y_range.clone()), (0..4, 0..1));
+        buffer.fill(green);
+    }));
+    ChromaKey::new(source, Arc::new(green)).render(&ro, &mut buffer);
+    assert!(buffer.iter().all(|p| p.3 == 0.0));
+}
@@ -1,4 +1,6 @@
+use std::hash::Hasher;
 use crate::render::{Res, Render, RenderOpt};
+use crate::hash::StructuralHasher;
 use std::marker::PhantomData;
 
 pub struct Clip<T, R: Render<T>> {
@@ -8,14 +10,14 @@ pub struct Clip<T, R: Render<T>> {
     pub t: PhantomData<T>
 }
 
-impl <T, R: Render<T>> Render<T> for Clip<T, R> {
+impl <T: Send + Sync, R: Render<T>> Render<T> for Clip<T, R> {
     fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> T {
         let time = time + self.start;
         self.render.sample(u, v, time, res)
     }
 
     fn render(&self, ro: &RenderOpt, buffer: &mut [T]) {
-        let dframe = (ro.framerate as f64 * self.start) as i32;
+        let dframe = ro.framerate.frame_at(self.start) as i32;
         let ro = RenderOpt {
             frame_range: ro.frame_range.start + dframe..ro.frame_range.end + dframe,
             ..ro.clone()
@@ -26,6 +28,14 @@ impl <T, R: Render<T>> Render<T> for Clip<T, R> {
     fn duration(&self) -> f64 {
         self.end - self.start
     }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("clip");
+        hasher.write_u64(self.render.structural_hash()?);
+        hasher.write_f64(self.start);
+        hasher.write_f64(self.end);
+        Some(hasher.finish())
+    }
 }
 
 impl<T, R: Render<T>> Clip<T, R> {
@@ -1,6 +1,9 @@
+use std::hash::Hasher;
 use crate::pixel::Rgba;
 use crate::render::{Res, Render, RenderOpt};
+use crate::hash::StructuralHasher;
 
+#[derive(Debug, Copy, Clone)]
 pub enum ColorSamplingType {
     T444, // 1x1
     T422, // 2x1
@@ -21,10 +24,10 @@ impl <R: Render<Rgba>> Render<Rgba> for ColorSampling<R> {
     fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
         self.render.render(ro, buffer);
 
-        let RenderOpt {res_x, res_y, frame_range, x_range, y_range, ..} = ro;
-        let frame_size = *res_x * *res_y;
+        let RenderOpt {frame_range, x_range, y_range, ..} = ro;
         let x_size = (x_range.end - x_range.start) as usize;
         let y_size = (y_range.end - y_range.start) as usize;
+        let frame_size = x_size * y_size;
 
         use ColorSamplingType::*;
         match self.r#type {
@@ -110,6 +113,13 @@ impl <R: Render<Rgba>> Render<Rgba> for ColorSampling<R> {
     fn duration(&self) -> f64 {
         self.render.duration()
     }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("color_sampling");
+        hasher.write_u64(self.render.structural_hash()?);
+        hasher.write_u8(self.r#type as u8);
+        Some(hasher.finish())
+    }
 }
 
 fn rgb_to_ypbpr(r: f64, g: f64, b: f64) -> (f64, f64, f64) {
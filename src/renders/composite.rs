@@ -1,11 +1,36 @@
+use std::hash::Hasher;
 use crate::pixel::Rgba;
+use crate::blend::{BlendMode, blend};
+use crate::hash::StructuralHasher;
+use crate::error::KanteraError;
 use crate::render::{Res, Render, RenderOpt};
 use crate::path::Timed;
-use std::rc::Rc;
+use std::sync::Arc;
 
+#[derive(Clone)]
 pub enum CompositeMode {
     None,
-    Normal(Rc<dyn Timed<f64>>)
+    Normal(Arc<dyn Timed<f64>>),
+    Blend(BlendMode, Arc<dyn Timed<f64>>) // with opacity
+}
+
+impl CompositeMode {
+    pub fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("composite_mode");
+        match self {
+            CompositeMode::None => hasher.write_u8(0),
+            CompositeMode::Normal(alpha) => {
+                hasher.write_u8(1);
+                hasher.write_u64(alpha.structural_hash()?);
+            }
+            CompositeMode::Blend(mode, opacity) => {
+                hasher.write_u8(2);
+                hasher.write_u8(*mode as u8);
+                hasher.write_u64(opacity.structural_hash()?);
+            }
+        }
+        Some(hasher.finish())
+    }
 }
 
 pub struct Composite<R: Render<Rgba>> {
@@ -22,29 +47,43 @@ impl<R: Render<Rgba>> Render<Rgba> for Composite<R> {
     }
 
     fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) -> Result<(), KanteraError> {
         let RenderOpt {x_range, y_range, frame_range, framerate, ..} = ro;
         let mut sub_buffer = vec![Rgba::default(); buffer.len()];
         let frame_size = ((x_range.end - x_range.start) * (y_range.end - y_range.start)) as usize;
 
         for (render, cm) in &self.layers {
-            render.render(ro, sub_buffer.as_mut_slice());
+            render.try_render(ro, sub_buffer.as_mut_slice())?;
             for f in frame_range.start..frame_range.end {
                 let start = frame_size * (f - frame_range.start) as usize;
                 composite(
                     &mut buffer[start..start+frame_size],
                     &sub_buffer[start..start+frame_size],
-                    f as f64 / *framerate as f64, cm);
+                    framerate.frame_to_time(f as i64), cm);
             }
         }
+        Ok(())
     }
 
     fn duration(&self) -> f64 {
         self.layers.iter().map(|x| x.0.duration()).fold(std::f64::INFINITY, |x, y| x.min(y))
     }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("composite");
+        for (render, cm) in self.layers.iter() {
+            hasher.write_u64(render.structural_hash()?);
+            hasher.write_u64(cm.structural_hash()?);
+        }
+        Some(hasher.finish())
+    }
 }
 
 #[inline(always)]
-fn composite(base: &mut [Rgba], value: &[Rgba], time: f64, cm: &CompositeMode) {
+pub(crate) fn composite(base: &mut [Rgba], value: &[Rgba], time: f64, cm: &CompositeMode) {
     match cm {
         CompositeMode::None => {
             for i in 0..base.len() {
@@ -57,5 +96,11 @@ fn composite(base: &mut [Rgba], value: &[Rgba], time: f64, cm: &CompositeMode) {
                 base[i] = base[i].normal_blend(&value[i], alpha);
             }
         }
+        CompositeMode::Blend(mode, opacity) => {
+            let opacity = opacity.get_value(time);
+            for i in 0..base.len() {
+                base[i] = blend(&base[i], &value[i], *mode, opacity);
+            }
+        }
     };
 }
@@ -0,0 +1,263 @@
+use std::hash::Hasher;
+use std::sync::Arc;
+use crate::pixel::Rgba;
+use crate::image::Image;
+use crate::render::{Res, Render, RenderOpt};
+use crate::renders::frame::FrameType;
+use crate::renders::filter::{check_kernel, make_laplacian_kernel, make_sobel_kernels};
+use crate::renders::blur::{BlurType, blur_margin, blur_buffer, render_with_margin};
+use crate::timed::Timed;
+use crate::hash::{StructuralHash, StructuralHasher};
+use crate::error::KanteraError;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EdgeOperator {
+    Sobel,
+    Laplacian
+}
+
+// The magnitude of the luma gradient (Sobel) or of the Laplacian, as grey.
+pub struct EdgeDetect<R: Render<Rgba>> {
+    pub render: R,
+    pub operator: EdgeOperator
+}
+
+fn convolve_3x3(values: &[f64], width: usize, x: usize, y: usize, kernel: &Image<f64>) -> f64 {
+    let mut acc = 0.0;
+    for ky in 0..3 {
+        for kx in 0..3 {
+            acc += values[(y + ky) * width + x + kx] * kernel.vec[ky * 3 + kx];
+        }
+    }
+    acc
+}
+
+impl<R: Render<Rgba>> Render<Rgba> for EdgeDetect<R> {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        self.try_sample(u, v, time, res).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_sample(&self, _u: f64, _v: f64, _time: f64, _res: Res) -> Result<Rgba, KanteraError> {
+        Err(KanteraError::SampleNotSupported("EdgeDetect"))
+    }
+
+    fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) -> Result<(), KanteraError> {
+        let x_size = (ro.x_range.end - ro.x_range.start) as usize;
+        let y_size = (ro.y_range.end - ro.y_range.start) as usize;
+        let width = x_size + 2;
+        let (sobel_x, sobel_y) = make_sobel_kernels();
+        let laplacian = make_laplacian_kernel();
+        let mut sub_buffer = Vec::new();
+        for f in ro.frame_range.clone() {
+            render_with_margin(&self.render, ro, f, 1, &Some(FrameType::Extend), &mut sub_buffer)?;
+            let values: Vec<f64> = sub_buffer.iter().map(|p| p.premultiply().luma()).collect();
+            let frame = &mut buffer[(f - ro.frame_range.start) as usize * x_size * y_size..][..x_size * y_size];
+            for y in 0..y_size {
+                for x in 0..x_size {
+                    let m = match self.operator {
+                        EdgeOperator::Sobel =>
+                            convolve_3x3(&values, width, x, y, &sobel_x).hypot(convolve_3x3(&values, width, x, y, &sobel_y)),
+                        EdgeOperator::Laplacian => convolve_3x3(&values, width, x, y, &laplacian).abs()
+                    }.min(1.0);
+                    frame[y * x_size + x] = Rgba(m, m, m, sub_buffer[(y + 1) * width + x + 1].3);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn duration(&self) -> f64 {
+        self.render.duration()
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("edge_detect");
+        hasher.write_u64(self.render.structural_hash()?);
+        hasher.write_u8(self.operator as u8);
+        Some(hasher.finish())
+    }
+}
+
+// Applies an odd sized `kernel` to the premultiplied red, green and blue, leaving alpha as is.
+pub struct Convolve<R: Render<Rgba>> {
+    pub render: R,
+    pub kernel: Image<f64>
+}
+
+impl<R: Render<Rgba>> Render<Rgba> for Convolve<R> {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        self.try_sample(u, v, time, res).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_sample(&self, _u: f64, _v: f64, _time: f64, _res: Res) -> Result<Rgba, KanteraError> {
+        Err(KanteraError::SampleNotSupported("Convolve"))
+    }
+
+    fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) -> Result<(), KanteraError> {
+        check_kernel(&self.kernel)?;
+        let Image {width: kw, height: kh, vec: ref kernel} = self.kernel;
+        let x_size = (ro.x_range.end - ro.x_range.start) as usize;
+        let y_size = (ro.y_range.end - ro.y_range.start) as usize;
+        let margin = kw.max(kh) / 2;
+        let width = x_size + margin * 2;
+        let (ox, oy) = (margin - kw / 2, margin - kh / 2);
+        let mut sub_buffer = Vec::new();
+        for f in ro.frame_range.clone() {
+            render_with_margin(&self.render, ro, f, margin, &Some(FrameType::Extend), &mut sub_buffer)?;
+            for p in sub_buffer.iter_mut() {
+                *p = p.premultiply();
+            }
+            let frame = &mut buffer[(f - ro.frame_range.start) as usize * x_size * y_size..][..x_size * y_size];
+            for y in 0..y_size {
+                for x in 0..x_size {
+                    let mut acc = (0.0, 0.0, 0.0);
+                    for ky in 0..kh {
+                        for kx in 0..kw {
+                            let p = sub_buffer[(y + oy + ky) * width + x + ox + kx];
+                            let k = kernel[ky * kw + kx];
+                            acc.0 += p.0 * k;
+                            acc.1 += p.1 * k;
+                            acc.2 += p.2 * k;
+                        }
+                    }
+                    // Clamped to the premultiplied range.
+                    let a = sub_buffer[(y + margin) * width + x + margin].3;
+                    frame[y * x_size + x] = Rgba(acc.0.clamp(0.0, a), acc.1.clamp(0.0, a), acc.2.clamp(0.0, a), a).unpremultiply();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn duration(&self) -> f64 {
+        self.render.duration()
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("convolve");
+        hasher.write_u64(self.render.structural_hash()?);
+        hasher.write_u64(self.kernel.hash_value()?);
+        Some(hasher.finish())
+    }
+}
+
+// Sharpens by adding `amount` times the difference to a Gaussian blur of `radius` pixels, where the
+// difference in luma is above `threshold`.
+pub struct UnsharpMask<R: Render<Rgba>> {
+    pub render: R,
+    pub amount: Arc<dyn Timed<f64>>,
+    pub radius: Arc<dyn Timed<f64>>,
+    pub threshold: Arc<dyn Timed<f64>>
+}
+
+impl<R: Render<Rgba>> Render<Rgba> for UnsharpMask<R> {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        self.try_sample(u, v, time, res).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_sample(&self, _u: f64, _v: f64, _time: f64, _res: Res) -> Result<Rgba, KanteraError> {
+        Err(KanteraError::SampleNotSupported("UnsharpMask"))
+    }
+
+    fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) -> Result<(), KanteraError> {
+        let x_size = (ro.x_range.end - ro.x_range.start) as usize;
+        let y_size = (ro.y_range.end - ro.y_range.start) as usize;
+        let mut sub_buffer = Vec::new();
+        for f in ro.frame_range.clone() {
+            let time = ro.framerate.frame_to_time(f as i64);
+            let radius = self.radius.get_value(time).max(0.0);
+            let margin = blur_margin(BlurType::Gaussian, radius);
+            render_with_margin(&self.render, ro, f, margin, &Some(FrameType::Extend), &mut sub_buffer)?;
+            let (width, height) = (x_size + margin * 2, y_size + margin * 2);
+            for p in sub_buffer.iter_mut() {
+                *p = p.premultiply();
+            }
+            let mut blurred = sub_buffer.clone();
+            blur_buffer(&mut blurred, width, height, BlurType::Gaussian, radius, (true, true), None);
+
+            let amount = self.amount.get_value(time);
+            let threshold = self.threshold.get_value(time);
+            let frame = &mut buffer[(f - ro.frame_range.start) as usize * x_size * y_size..][..x_size * y_size];
+            for y in 0..y_size {
+                for x in 0..x_size {
+                    let i = (y + margin) * width + x + margin;
+                    let (p, b) = (sub_buffer[i], blurred[i]);
+                    frame[y * x_size + x] = if (p.luma() - b.luma()).abs() > threshold {
+                        // Clamped to the premultiplied range.
+                        let sharpen = |p: f64, b: f64, a: f64| (p + (p - b) * amount).clamp(0.0, a);
+                        Rgba(sharpen(p.0, b.0, p.3), sharpen(p.1, b.1, p.3), sharpen(p.2, b.2, p.3), p.3).unpremultiply()
+                    } else {
+                        p.unpremultiply()
+                    };
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn duration(&self) -> f64 {
+        self.render.duration()
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("unsharp_mask");
+        hasher.write_u64(self.render.structural_hash()?);
+        hasher.write_u64(self.amount.structural_hash()?);
+        hasher.write_u64(self.radius.structural_hash()?);
+        hasher.write_u64(self.threshold.structural_hash()?);
+        Some(hasher.finish())
+    }
+}
+
+#[test]
+fn test() {
+    use crate::renders::{functional_render::pixels, filter::make_sharpen_kernel};
+    use crate::framerate::Framerate;
+
+    let ro = RenderOpt {
+        x_range: 0..6,
+        y_range: 0..1,
+        res_x: 6,
+        res_y: 1,
+        frame_range: 0..1,
+        framerate: Framerate::from(30),
+        pixel_center: false
+    };
+    // A step from dark to light grey.
+    let step = || pixels(|x, _| if x >= 3 { Rgba(0.75, 0.75, 0.75, 1.0) } else { Rgba(0.25, 0.25, 0.25, 1.0) });
+    let mut buffer = vec![Rgba::default(); 6];
+
+    EdgeDetect {render: step(), operator: EdgeOperator::Sobel}.render(&ro, &mut buffer);
+    let edges: Vec<f64> = buffer.iter().map(|p| (p.0 * 1e6).round() / 1e6).collect();
+    assert_eq!(edges, vec![0.0, 0.0, 1.0, 1.0, 0.0, 0.0]);
+
+    // Both sides of the step are pushed apart, flat areas are left alone.
+    UnsharpMask {render: step(), amount: Arc::new(1.0), radius: Arc::new(1.0), threshold: Arc::new(0.01)}.render(&ro, &mut buffer);
+    assert!(buffer[2].0 < 0.25 && buffer[3].0 > 0.75);
+    assert!((buffer[0].0 - 0.25).abs() < 1e-9);
+
+    Convolve {render: step(), kernel: make_sharpen_kernel(0.5)}.render(&ro, &mut buffer);
+    assert!(buffer[2].0 < 0.25 && buffer[3].0 > 0.75 && buffer[3].3 == 1.0);
+    assert!((buffer[0].0 - 0.25).abs() < 1e-9);
+
+    // Half transparent: the result stays within range instead of overshooting past the alpha.
+    let half = pixels(|x, _| if x >= 3 { Rgba(1.0, 1.0, 1.0, 0.5) } else { Rgba(0.0, 0.0, 0.0, 0.5) });
+    Convolve {render: half, kernel: make_sharpen_kernel(1.0)}.render(&ro, &mut buffer);
+    assert_eq!((buffer[2].0, buffer[3].0, buffer[3].3), (0.0, 1.0, 0.5));
+
+    // Even sized kernels are an error rather than a panic.
+    let convolve = Convolve {render: step(), kernel: Image {width: 2, height: 1, vec: vec![0.5, 0.5]}};
+    assert!(matches!(convolve.try_render(&ro, &mut buffer), Err(KanteraError::InvalidKernel(2, 1))));
+}
@@ -0,0 +1,110 @@
+use std::hash::Hasher;
+use crate::pixel::Rgba;
+use crate::render::{Res, Render, RenderOpt};
+use crate::renders::transform::Mat3;
+use crate::timed::Timed;
+use crate::v::Vec2;
+use crate::hash::StructuralHasher;
+
+// Maps `render` onto the quad of `corners` (top left, top right, bottom right, bottom left) in uv,
+// leaving the outside transparent.
+pub struct CornerPin<R: Render<Rgba>, P: Timed<Vec2<f64>>> {
+    pub render: R,
+    pub corners: [P; 4]
+}
+
+impl<R: Render<Rgba>, P: Timed<Vec2<f64>>> CornerPin<R, P> {
+    pub fn new(render: R, corners: [P; 4]) -> Self {
+        CornerPin {render, corners}
+    }
+
+    // The mapping from output pixels to the child's uv.
+    fn inverse(&self, time: f64, res: Res) -> Option<Mat3> {
+        let corner = |i: usize| {
+            let c = self.corners[i].get_value(time);
+            (c.0 * res.0 as f64, c.1 * res.1 as f64)
+        };
+        Mat3::square_to_quad([corner(0), corner(1), corner(2), corner(3)]).inverse()
+    }
+
+    fn sample_with(&self, inverse: Option<Mat3>, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        match inverse.and_then(|inv| inv.apply(u * res.0 as f64, v * res.1 as f64)) {
+            Some((u, v)) if (0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&v) =>
+                self.render.sample(u, v, time, res),
+            _ => Rgba(0.0, 0.0, 0.0, 0.0)
+        }
+    }
+}
+
+impl<R: Render<Rgba>, P: Timed<Vec2<f64>>> Render<Rgba> for CornerPin<R, P> {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        self.sample_with(self.inverse(time, res), u, v, time, res)
+    }
+
+    // The same as sampling, with the mapping computed once per frame.
+    fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
+        let RenderOpt {x_range, y_range, res_x, res_y, frame_range, framerate, ..} = ro;
+        let res = (*res_x, *res_y);
+        let mut i = 0;
+        for f in frame_range.clone() {
+            let time = framerate.frame_to_time(f as i64);
+            let inverse = self.inverse(time, res);
+            for y in y_range.clone() {
+                for x in x_range.clone() {
+                    let (u, v) = ro.uv(x, y);
+                    buffer[i] = self.sample_with(inverse, u, v, time, res);
+                    i += 1;
+                }
+            }
+        }
+    }
+
+    fn duration(&self) -> f64 {
+        self.render.duration()
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("corner_pin");
+        hasher.write_u64(self.render.structural_hash()?);
+        for corner in self.corners.iter() {
+            hasher.write_u64(corner.structural_hash()?);
+        }
+        Some(hasher.finish())
+    }
+}
+
+#[test]
+fn test() {
+    use crate::renders::sample::Sample;
+    use crate::path::Path;
+    use crate::framerate::Framerate;
+
+    let gradient = Box::new(Sample::new(Box::new(|u, v, _, _| Rgba(u, v, 0.0, 1.0)))) as Box<dyn Render<Rgba>>;
+    // Pinned to the right half of the frame.
+    let pin = CornerPin::new(gradient, [
+        Path::new(Vec2(0.5, 0.0)),
+        Path::new(Vec2(1.0, 0.0)),
+        Path::new(Vec2(1.0, 1.0)),
+        Path::new(Vec2(0.5, 1.0))
+    ]);
+    let p = pin.sample(0.75, 0.5, 0.0, (16, 16));
+    assert!((p.0 - 0.5).abs() < 1e-9 && (p.1 - 0.5).abs() < 1e-9);
+    assert_eq!(pin.sample(0.25, 0.5, 0.0, (16, 16)), Rgba(0.0, 0.0, 0.0, 0.0));
+
+    // Rendering gives the same as sampling each pixel.
+    let ro = RenderOpt {
+        x_range: 0..16,
+        y_range: 0..16,
+        res_x: 16,
+        res_y: 16,
+        frame_range: 0..1,
+        framerate: Framerate::from(30),
+        pixel_center: true
+    };
+    let mut buffer = vec![Rgba::default(); 16 * 16];
+    pin.render(&ro, &mut buffer);
+    for (i, p) in buffer.iter().enumerate() {
+        let (u, v) = ro.uv((i % 16) as i32, (i / 16) as i32);
+        assert_eq!(*p, pin.sample(u, v, 0.0, (16, 16)));
+    }
+}
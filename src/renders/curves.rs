@@ -0,0 +1,212 @@
+use std::hash::Hasher;
+use std::sync::Arc;
+use crate::pixel::Rgba;
+use crate::render::{Res, Render, RenderOpt};
+use crate::renders::cache::Memo;
+use crate::timed::Timed;
+use crate::v::Vec2;
+use crate::hash::StructuralHasher;
+use crate::error::KanteraError;
+
+// The number of times whose splines are kept.
+const MAX_SPLINES: usize = 4;
+
+// Control points of a curve from input to output, both in [0, 1]. No points leave the values as is.
+pub type Curve = Vec<Arc<dyn Timed<Vec2<f64>>>>;
+
+// A monotone cubic spline through the points, so that the curve doesn't overshoot between them. Outside
+// of the points the curve is flat.
+pub struct Spline {
+    points: Vec<(f64, f64)>,
+    tangents: Vec<f64>
+}
+
+impl Spline {
+    pub fn new(mut points: Vec<(f64, f64)>) -> Self {
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        points.dedup_by(|a, b| a.0 == b.0);
+        let n = points.len();
+        let slopes: Vec<f64> = points.windows(2).map(|w| (w[1].1 - w[0].1) / (w[1].0 - w[0].0)).collect();
+        let mut tangents = vec![0.0; n];
+        if n >= 2 {
+            tangents[0] = slopes[0];
+            tangents[n - 1] = slopes[n - 2];
+        }
+        for i in 1..n.saturating_sub(1) {
+            tangents[i] = if slopes[i - 1] * slopes[i] <= 0.0 { 0.0 } else { (slopes[i - 1] + slopes[i]) / 2.0 };
+        }
+        // Fritsch-Carlson: limit the tangents to keep each segment monotone.
+        for (i, s) in slopes.iter().enumerate() {
+            if *s == 0.0 {
+                tangents[i] = 0.0;
+                tangents[i + 1] = 0.0;
+            } else {
+                let (a, b) = (tangents[i] / s, tangents[i + 1] / s);
+                let h = a * a + b * b;
+                if h > 9.0 {
+                    let t = 3.0 / h.sqrt();
+                    tangents[i] = t * a * s;
+                    tangents[i + 1] = t * b * s;
+                }
+            }
+        }
+        Spline {points, tangents}
+    }
+
+    pub fn get_value(&self, x: f64) -> f64 {
+        let points = &self.points;
+        match points.len() {
+            0 => return x,
+            1 => return points[0].1,
+            _ => {}
+        }
+        if x <= points[0].0 {
+            return points[0].1;
+        }
+        if points[points.len() - 1].0 <= x {
+            return points[points.len() - 1].1;
+        }
+        let i = points.partition_point(|p| p.0 <= x) - 1;
+        let ((x0, y0), (x1, y1)) = (points[i], points[i + 1]);
+        let h = x1 - x0;
+        let t = (x - x0) / h;
+        let (t2, t3) = (t * t, t * t * t);
+        (2.0 * t3 - 3.0 * t2 + 1.0) * y0 + (t3 - 2.0 * t2 + t) * h * self.tangents[i]
+            + (-2.0 * t3 + 3.0 * t2) * y1 + (t3 - t2) * h * self.tangents[i + 1]
+    }
+}
+
+// The channel curves are applied first, then `rgb` to every channel and finally `luma`, which shifts the
+// colour by the change of its Rec. 709 luma.
+pub struct Curves<R: Render<Rgba>> {
+    pub render: R,
+    rgb: Curve,
+    red: Curve,
+    green: Curve,
+    blue: Curve,
+    luma: Curve,
+    // The splines by time, built once for all the pixels sampled at that time and dropped by the setters.
+    splines: Memo<u64, Arc<[Spline; 5]>>
+}
+
+fn spline(curve: &Curve, time: f64) -> Spline {
+    Spline::new(curve.iter().map(|p| {
+        let Vec2(x, y) = p.get_value(time);
+        (x, y)
+    }).collect())
+}
+
+fn hash_curve(hasher: &mut StructuralHasher, curve: &Curve) -> Option<()> {
+    hasher.write_usize(curve.len());
+    for p in curve.iter() {
+        hasher.write_u64(p.structural_hash()?);
+    }
+    Some(())
+}
+
+impl<R: Render<Rgba>> Curves<R> {
+    pub fn new(render: R) -> Self {
+        Curves {
+            render,
+            rgb: vec![],
+            red: vec![],
+            green: vec![],
+            blue: vec![],
+            luma: vec![],
+            splines: Memo::new(MAX_SPLINES)
+        }
+    }
+
+    pub fn set_rgb(&mut self, curve: Curve) {
+        self.rgb = curve;
+        self.splines.clear();
+    }
+
+    pub fn set_red(&mut self, curve: Curve) {
+        self.red = curve;
+        self.splines.clear();
+    }
+
+    pub fn set_green(&mut self, curve: Curve) {
+        self.green = curve;
+        self.splines.clear();
+    }
+
+    pub fn set_blue(&mut self, curve: Curve) {
+        self.blue = curve;
+        self.splines.clear();
+    }
+
+    pub fn set_luma(&mut self, curve: Curve) {
+        self.luma = curve;
+        self.splines.clear();
+    }
+
+    fn curves(&self, time: f64) -> impl Fn(&Rgba) -> Rgba {
+        let splines = self.splines.get_or_try_init(time.to_bits(), || Ok(Arc::new([
+            spline(&self.rgb, time), spline(&self.red, time), spline(&self.green, time),
+            spline(&self.blue, time), spline(&self.luma, time)
+        ]))).unwrap();
+        move |p: &Rgba| {
+            let [rgb, red, green, blue, luma] = splines.as_ref();
+            let (r, g, b) = (rgb.get_value(red.get_value(p.0)), rgb.get_value(green.get_value(p.1)), rgb.get_value(blue.get_value(p.2)));
+            let y = Rgba(r, g, b, p.3).luma();
+            let d = luma.get_value(y) - y;
+            Rgba(r + d, g + d, b + d, p.3)
+        }
+    }
+}
+
+impl<R: Render<Rgba>> Render<Rgba> for Curves<R> {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        self.curves(time)(&self.render.sample(u, v, time, res))
+    }
+
+    fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) -> Result<(), KanteraError> {
+        self.render.try_render(ro, buffer)?;
+        let frame_size = ((ro.x_range.end - ro.x_range.start) * (ro.y_range.end - ro.y_range.start)) as usize;
+        for (f, frame) in ro.frame_range.clone().zip(buffer.chunks_mut(frame_size)) {
+            let curves = self.curves(ro.framerate.frame_to_time(f as i64));
+            for p in frame.iter_mut() {
+                *p = curves(p);
+            }
+        }
+        Ok(())
+    }
+
+    fn duration(&self) -> f64 {
+        self.render.duration()
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("curves");
+        hasher.write_u64(self.render.structural_hash()?);
+        for curve in [&self.rgb, &self.red, &self.green, &self.blue, &self.luma].iter() {
+            hash_curve(&mut hasher, curve)?;
+        }
+        Some(hasher.finish())
+    }
+}
+
+#[test]
+fn test() {
+    use crate::renders::plain::Plain;
+
+    let spline = Spline::new(vec![(1.0, 1.0), (0.0, 0.0), (0.5, 0.8)]);
+    assert_eq!(spline.get_value(0.5), 0.8);
+    assert_eq!(spline.get_value(-1.0), 0.0);
+    // Monotone between the points.
+    let values: Vec<f64> = (0..=100).map(|i| spline.get_value(i as f64 / 100.0)).collect();
+    assert!(values.windows(2).all(|w| w[0] <= w[1] && w[1] <= 1.0));
+    Spline::new(vec![(f64::NAN, 0.0), (0.0, 0.0)]);
+
+    let mut curves = Curves::new(Plain::new(Rgba(0.5, 0.5, 0.5, 1.0)));
+    curves.set_red(vec![Arc::new(Vec2(0.0, 1.0)), Arc::new(Vec2(1.0, 0.0))]);
+    assert_eq!(curves.sample(0.0, 0.0, 0.0, (1, 1)), Rgba(0.5, 0.5, 0.5, 1.0));
+    curves.set_rgb(vec![Arc::new(Vec2(0.0, 0.0)), Arc::new(Vec2(0.5, 0.25)), Arc::new(Vec2(1.0, 1.0))]);
+    assert_eq!(curves.sample(0.0, 0.0, 0.0, (1, 1)), Rgba(0.25, 0.25, 0.25, 1.0));
+}
@@ -0,0 +1,203 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::pixel::Rgba;
+use crate::render::{Res, Render, RenderOpt};
+use crate::renders::cache::Memo;
+use crate::framerate::Framerate;
+use crate::error::KanteraError;
+
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// The number of whole frames kept in memory for the tiles still to be rendered.
+const MAX_FRAMES: usize = 4;
+
+// Files start with MAGIC, the format VERSION and the frame's width and height, each as a u32.
+const MAGIC: &[u8; 8] = b"KNTRFRM\0";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 8 + 4 * 3;
+
+// Stores whole rendered frames on disk keyed by the structural hash of the child, so they survive
+// between runs, and crops tiles out of them. Renders that can't be hashed are rendered as is. The
+// child is fixed at construction so that the hash always matches it.
+pub struct DiskCache<R: Render<Rgba>> {
+    render: R,
+    pub dir: PathBuf,
+    hash: Option<u64>,
+    frames: Memo<(i32, Framerate, Res, bool), Arc<Vec<Rgba>>>
+}
+
+impl<R: Render<Rgba>> DiskCache<R> {
+    pub fn new(render: R, dir: impl Into<PathBuf>) -> Self {
+        let hash = render.structural_hash();
+        DiskCache {
+            render,
+            dir: dir.into(),
+            hash,
+            frames: Memo::new(MAX_FRAMES)
+        }
+    }
+
+    pub fn hash(&self) -> Option<u64> {
+        self.hash
+    }
+
+    fn frame_path(&self, hash: u64, ro: &RenderOpt, frame: i32) -> PathBuf {
+        self.dir.join(format!(
+            "{:016x}_{}-{}_{}x{}_{}{}.bin",
+            hash, ro.framerate.num, ro.framerate.den, ro.res_x, ro.res_y, frame,
+            if ro.pixel_center { "c" } else { "" }))
+    }
+
+    // The whole frame `f`, read from disk or rendered and stored.
+    fn frame(&self, hash: u64, ro: &RenderOpt, f: i32) -> Result<Arc<Vec<Rgba>>, KanteraError> {
+        let (width, height) = (ro.res_x, ro.res_y);
+        self.frames.get_or_try_init((f, ro.framerate, (width, height), ro.pixel_center), || {
+            let path = self.frame_path(hash, ro, f);
+            let mut vec = vec![Rgba::default(); width * height];
+            if read_frame(&path, (width, height), &mut vec) {
+                return Ok(Arc::new(vec));
+            }
+            self.render.try_render(&RenderOpt {
+                x_range: 0..width as i32,
+                y_range: 0..height as i32,
+                frame_range: f..f + 1,
+                ..ro.clone()
+            }, &mut vec)?;
+            // The cache is best-effort; failing to store a frame only costs a re-render later.
+            write_frame(&self.dir, &path, (width, height), &vec).ok();
+            Ok(Arc::new(vec))
+        })
+    }
+}
+
+fn read_frame(path: &Path, (width, height): Res, buffer: &mut [Rgba]) -> bool {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(_) => return false
+    };
+    if bytes.len() != HEADER_SIZE + buffer.len() * 32 || &bytes[..8] != MAGIC {
+        return false;
+    }
+    let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+    if u32_at(8) != VERSION || u32_at(12) as usize != width || u32_at(16) as usize != height {
+        return false;
+    }
+    for (i, chunk) in bytes[HEADER_SIZE..].chunks_exact(32).enumerate() {
+        let f = |j: usize| f64::from_le_bytes(chunk[j * 8..j * 8 + 8].try_into().unwrap());
+        buffer[i] = Rgba(f(0), f(1), f(2), f(3));
+    }
+    true
+}
+
+fn write_frame(dir: &Path, path: &Path, (width, height): Res, buffer: &[Rgba]) -> std::io::Result<()> {
+    let mut bytes = Vec::with_capacity(HEADER_SIZE + buffer.len() * 32);
+    bytes.extend_from_slice(MAGIC);
+    for v in [VERSION, width as u32, height as u32] {
+        bytes.extend_from_slice(&v.to_le_bytes());
+    }
+    for p in buffer {
+        for v in [p.0, p.1, p.2, p.3] {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+    std::fs::create_dir_all(dir)?;
+    // Write to a temporary file first so that a partially written frame is never read.
+    let tmp_path = path.with_extension(format!("{}_{}.tmp", std::process::id(), TMP_COUNTER.fetch_add(1, Ordering::Relaxed)));
+    std::fs::write(&tmp_path, bytes)?;
+    std::fs::rename(&tmp_path, path)
+}
+
+impl<R: Render<Rgba>> Render<Rgba> for DiskCache<R> {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        self.render.sample(u, v, time, res)
+    }
+
+    fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) -> Result<(), KanteraError> {
+        let hash = match self.hash {
+            Some(hash) => hash,
+            None => return self.render.try_render(ro, buffer)
+        };
+        let RenderOpt {x_range, y_range, res_x, res_y, frame_range, ..} = ro;
+        let x_size = (x_range.end - x_range.start) as usize;
+        let frame_size = x_size * (y_range.end - y_range.start) as usize;
+        // Pixels outside the frame, as in margins, aren't stored.
+        let (xs, ys) = (x_range.start.max(0), y_range.start.max(0));
+        let (xe, ye) = (x_range.end.min(*res_x as i32), y_range.end.min(*res_y as i32));
+        if xs > x_range.start || ys > y_range.start || xe < x_range.end || ye < y_range.end {
+            self.render.try_render(ro, buffer)?;
+        }
+        if xs >= xe || ys >= ye {
+            return Ok(());
+        }
+        for f in frame_range.clone() {
+            let frame = self.frame(hash, ro, f)?;
+            let buffer = &mut buffer[(f - frame_range.start) as usize * frame_size..][..frame_size];
+            for y in ys..ye {
+                let start = (y - y_range.start) as usize * x_size + (xs - x_range.start) as usize;
+                let src = y as usize * res_x + xs as usize;
+                buffer[start..start + (xe - xs) as usize].copy_from_slice(&frame[src..src + (xe - xs) as usize]);
+            }
+        }
+        Ok(())
+    }
+
+    fn duration(&self) -> f64 {
+        self.render.duration()
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        self.hash
+    }
+}
+
+#[test]
+fn test() {
+    use crate::renders::{plain::Plain, clip::Clip};
+    use crate::framerate::Framerate;
+
+    let a = Clip::new(Plain::new(Rgba(1.0, 0.0, 0.0, 1.0)), 0.0, 1.0);
+    let b = Clip::new(Plain::new(Rgba(1.0, 0.0, 0.0, 1.0)), 0.0, 1.0);
+    let c = Clip::new(Plain::new(Rgba(1.0, 0.0, 0.0, 1.0)), 0.0, 2.0);
+    assert_eq!(a.structural_hash(), b.structural_hash());
+    assert_ne!(a.structural_hash(), c.structural_hash());
+
+    let dir = std::env::temp_dir().join(format!("kantera_disk_cache_test_{}", std::process::id()));
+    let ro = RenderOpt {
+        x_range: 0..4,
+        y_range: 0..3,
+        res_x: 4,
+        res_y: 3,
+        frame_range: 0..2,
+        framerate: Framerate::from(30),
+        pixel_center: false
+    };
+    let mut expected = vec![Rgba::default(); 4 * 3 * 2];
+    a.render(&ro, &mut expected);
+    for _ in 0..2 {
+        let mut actual = vec![Rgba::default(); 4 * 3 * 2];
+        DiskCache::new(Clip::new(Plain::new(Rgba(1.0, 0.0, 0.0, 1.0)), 0.0, 1.0), &dir).render(&ro, &mut actual);
+        assert_eq!(actual, expected);
+    }
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+    // Tiles are cropped out of the stored frames.
+    let mut tile = vec![Rgba::default(); 2 * 2 * 2];
+    let cache = DiskCache::new(Clip::new(Plain::new(Rgba(1.0, 0.0, 0.0, 1.0)), 0.0, 1.0), &dir);
+    cache.render(&RenderOpt {x_range: 1..3, y_range: 1..3, ..ro.clone()}, &mut tile);
+    assert!(tile.iter().all(|p| *p == expected[0]));
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+    // Files of another format are ignored.
+    for entry in std::fs::read_dir(&dir).unwrap() {
+        std::fs::write(entry.unwrap().path(), vec![0u8; HEADER_SIZE + 4 * 3 * 32]).unwrap();
+    }
+    let mut actual = vec![Rgba::default(); 4 * 3 * 2];
+    DiskCache::new(Clip::new(Plain::new(Rgba(1.0, 0.0, 0.0, 1.0)), 0.0, 1.0), &dir).render(&ro, &mut actual);
+    assert_eq!(actual, expected);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
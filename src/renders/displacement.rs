@@ -0,0 +1,129 @@
+use std::hash::Hasher;
+use std::sync::Arc;
+use crate::pixel::Rgba;
+use crate::render::{Res, Render};
+use crate::timed::Timed;
+use crate::hash::StructuralHasher;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+    Alpha,
+    Luma,
+    // Leaves the axis as is.
+    Off
+}
+
+impl Channel {
+    pub fn from_name(name: &str) -> Option<Channel> {
+        Some(match name {
+            "red" => Channel::Red,
+            "green" => Channel::Green,
+            "blue" => Channel::Blue,
+            "alpha" => Channel::Alpha,
+            "luma" => Channel::Luma,
+            "off" => Channel::Off,
+            _ => return None
+        })
+    }
+
+    fn value(&self, p: &Rgba) -> f64 {
+        match self {
+            Channel::Red => p.0,
+            Channel::Green => p.1,
+            Channel::Blue => p.2,
+            Channel::Alpha => p.3,
+            Channel::Luma => p.luma(),
+            Channel::Off => 0.5
+        }
+    }
+}
+
+// Samples `render` offset by `map`. A channel value of 0.5 doesn't move, 0.0 and 1.0 move by `scale_x`
+// (or `scale_y`) pixels in either direction.
+pub struct Displacement<R: Render<Rgba>, M: Render<Rgba>> {
+    pub render: R,
+    pub map: M,
+    pub x_channel: Channel,
+    pub y_channel: Channel,
+    pub scale_x: Arc<dyn Timed<f64>>,
+    pub scale_y: Arc<dyn Timed<f64>>
+}
+
+impl<R: Render<Rgba>, M: Render<Rgba>> Displacement<R, M> {
+    pub fn new(render: R, map: M, scale: Arc<dyn Timed<f64>>) -> Self {
+        Displacement {
+            render,
+            map,
+            x_channel: Channel::Red,
+            y_channel: Channel::Green,
+            scale_x: scale.clone(),
+            scale_y: scale
+        }
+    }
+}
+
+impl<R: Render<Rgba>, M: Render<Rgba>> Render<Rgba> for Displacement<R, M> {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        let m = self.map.sample(u, v, time, res);
+        let dx = (self.x_channel.value(&m) - 0.5) * 2.0 * self.scale_x.get_value(time);
+        let dy = (self.y_channel.value(&m) - 0.5) * 2.0 * self.scale_y.get_value(time);
+        self.render.sample(u + dx / res.0 as f64, v + dy / res.1 as f64, time, res)
+    }
+
+    fn duration(&self) -> f64 {
+        self.render.duration()
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("displacement");
+        hasher.write_u64(self.render.structural_hash()?);
+        hasher.write_u64(self.map.structural_hash()?);
+        hasher.write_u8(self.x_channel as u8);
+        hasher.write_u8(self.y_channel as u8);
+        hasher.write_u64(self.scale_x.structural_hash()?);
+        hasher.write_u64(self.scale_y.structural_hash()?);
+        Some(hasher.finish())
+    }
+}
+
+#[test]
+fn test() {
+    use crate::renders::{plain::Plain, sample::Sample};
+
+    // Encodes the position, and is transparent outside the frame.
+    let gradient = || Sample::new(Box::new(|u, v, _, _| {
+        if (0.0..1.0).contains(&u) && (0.0..1.0).contains(&v) { Rgba(u, v, 0.0, 1.0) } else { Rgba(0.0, 0.0, 0.0, 0.0) }
+    }));
+    // Red at 1.0 moves the sample 4 pixels to the right, green at 0.5 doesn't move it.
+    let map = Plain::new(Rgba(1.0, 0.5, 0.0, 1.0));
+    let mut displacement = Displacement::new(gradient(), map, Arc::new(4.0));
+    assert_eq!(displacement.sample(0.25, 0.5, 0.0, (16, 16)), Rgba(0.5, 0.5, 0.0, 1.0));
+
+    // Each axis has its own scale, and green at 0.0 moves up.
+    displacement.map = Plain::new(Rgba(1.0, 0.0, 0.0, 1.0));
+    displacement.scale_x = Arc::new(2.0);
+    displacement.scale_y = Arc::new(8.0);
+    assert_eq!(displacement.sample(0.25, 0.75, 0.0, (16, 16)), Rgba(0.375, 0.25, 0.0, 1.0));
+
+    // Any channel can drive either axis, and Off leaves the axis alone.
+    displacement.map = Plain::new(Rgba(0.5, 0.5, 0.0, 0.75));
+    displacement.x_channel = Channel::Alpha;
+    displacement.y_channel = Channel::Blue;
+    assert_eq!(displacement.sample(0.25, 0.75, 0.0, (16, 16)), Rgba(0.3125, 0.25, 0.0, 1.0));
+    displacement.x_channel = Channel::Off;
+    displacement.y_channel = Channel::Luma;
+    let p = displacement.sample(0.25, 0.75, 0.0, (16, 16));
+    assert_eq!(p.0, 0.25);
+    assert!((p.1 - (0.75 + Rgba(0.5, 0.5, 0.0, 0.75).luma() - 0.5)).abs() < 1e-9);
+
+    // Lookups beyond the edge are left to the displaced render.
+    displacement.x_channel = Channel::Red;
+    displacement.y_channel = Channel::Off;
+    displacement.map = Plain::new(Rgba(1.0, 0.5, 0.5, 1.0));
+    assert_eq!(displacement.sample(0.9, 0.5, 0.0, (16, 16)), Rgba(0.0, 0.0, 0.0, 0.0));
+    displacement.map = Plain::new(Rgba(0.0, 0.5, 0.5, 1.0));
+    assert_eq!(displacement.sample(0.05, 0.5, 0.0, (16, 16)), Rgba(0.0, 0.0, 0.0, 0.0));
+}
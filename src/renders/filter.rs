@@ -1,36 +1,54 @@
+use std::hash::Hasher;
 use crate::pixel::Rgba;
+use crate::hash::{StructuralHash, StructuralHasher};
+use crate::error::KanteraError;
 use crate::image::Image;
 use crate::render::{Res, Render, RenderOpt};
 
+// Kernels are centred on their middle value, so both sides must be odd.
+pub(crate) fn check_kernel<T>(kernel: &Image<T>) -> Result<(), KanteraError> {
+    let Image {width, height, ref vec} = *kernel;
+    if width % 2 == 0 || height % 2 == 0 || vec.len() != width * height {
+        return Err(KanteraError::InvalidKernel(width, height));
+    }
+    Ok(())
+}
+
 pub struct Filter<R: Render<Rgba>> {
     pub render: R,
     pub filter: Image<Rgba>
 }
 
 impl<R: Render<Rgba>> Render<Rgba> for Filter<R> {
-    fn sample(&self, _u: f64, _v: f64, _time: f64, _res: Res) -> Rgba {
-        unimplemented!();
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        self.try_sample(u, v, time, res).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_sample(&self, _u: f64, _v: f64, _time: f64, _res: Res) -> Result<Rgba, KanteraError> {
+        Err(KanteraError::SampleNotSupported("Filter"))
     }
 
     fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
-        let RenderOpt {x_range, y_range, res_x, res_y, frame_range, framerate, ..} = ro;
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) -> Result<(), KanteraError> {
+        check_kernel(&self.filter)?;
+        let RenderOpt {x_range, y_range, res_x, res_y, frame_range, ..} = ro;
         let x_size = (ro.x_range.end - ro.x_range.start) as usize;
         let y_size = (ro.y_range.end - ro.y_range.start) as usize;
         let Image {width, height, ref vec} = self.filter;
-        assert!(width % 2 == 1 && height % 2 == 1);
         let mut sub_buffer = vec![Rgba::default(); (res_x + width) * (res_y + height)];
 
         for f in frame_range.clone() {
-            self.render.render(
+            self.render.try_render(
                 &RenderOpt {
                     x_range: x_range.start - (width / 2) as i32..x_range.end + (width / 2) as i32,
                     y_range: y_range.start - (height / 2) as i32..y_range.end + (height / 2) as i32,
-                    res_x: *res_x,
-                    res_y: *res_y,
                     frame_range: f..f + 1,
-                    framerate: *framerate
+                    ..ro.clone()
                 },
-                sub_buffer.as_mut_slice());
+                sub_buffer.as_mut_slice())?;
 
             for y in 0..y_size {
                 for x in 0..x_size {
@@ -51,11 +69,19 @@ impl<R: Render<Rgba>> Render<Rgba> for Filter<R> {
                 }
             }
         }
+        Ok(())
     }
 
     fn duration(&self) -> f64 {
         self.render.duration()
     }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("filter");
+        hasher.write_u64(self.render.structural_hash()?);
+        hasher.write_u64(self.filter.hash_value()?);
+        Some(hasher.finish())
+    }
 }
 
 pub fn make_gaussian_filter(w: usize, h: usize, d: f64) -> Image<Rgba> {
@@ -74,3 +100,52 @@ pub fn make_gaussian_filter(w: usize, h: usize, d: f64) -> Image<Rgba> {
     }
     Image {width: dw, height: dh, vec}
 }
+
+fn kernel_3x3(vec: [f64; 9]) -> Image<f64> {
+    Image {width: 3, height: 3, vec: vec.to_vec()}
+}
+
+// Adds `amount` times the difference to the 4 neighbours.
+pub fn make_sharpen_kernel(amount: f64) -> Image<f64> {
+    kernel_3x3([
+        0.0, -amount, 0.0,
+        -amount, 1.0 + 4.0 * amount, -amount,
+        0.0, -amount, 0.0])
+}
+
+pub fn make_laplacian_kernel() -> Image<f64> {
+    kernel_3x3([
+        0.0, 1.0, 0.0,
+        1.0, -4.0, 1.0,
+        0.0, 1.0, 0.0])
+}
+
+// Horizontal and vertical gradients.
+pub fn make_sobel_kernels() -> (Image<f64>, Image<f64>) {
+    (
+        kernel_3x3([
+            -1.0, 0.0, 1.0,
+            -2.0, 0.0, 2.0,
+            -1.0, 0.0, 1.0]),
+        kernel_3x3([
+            -1.0, -2.0, -1.0,
+            0.0, 0.0, 0.0,
+            1.0, 2.0, 1.0])
+    )
+}
+
+// Lights the image from `angle` in radians, clockwise from the x axis. The kernel sums to 1, so flat
+// areas keep their colour.
+pub fn make_emboss_kernel(angle: f64, strength: f64) -> Image<f64> {
+    let (sin, cos) = angle.sin_cos();
+    let mut vec = [0.0; 9];
+    for y in 0..3 {
+        for x in 0..3 {
+            let (dx, dy) = (x as f64 - 1.0, y as f64 - 1.0);
+            // Neighbours towards the light are subtracted.
+            vec[y * 3 + x] = -(dx * cos + dy * sin) * strength;
+        }
+    }
+    vec[4] = 1.0;
+    kernel_3x3(vec)
+}
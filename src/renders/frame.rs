@@ -1,4 +1,6 @@
+use std::hash::Hasher;
 use crate::render::{Res, Render};
+use crate::hash::{StructuralHash, StructuralHasher};
 
 #[derive(Debug, Copy, Clone)]
 pub enum FrameType<T: Copy> {
@@ -13,7 +15,7 @@ pub struct Frame<T: Copy, R: Render<T>> {
     pub frame_type: FrameType<T>
 }
 
-impl <T: Copy, R: Render<T>> Render<T> for Frame<T, R> {
+impl <T: Copy + StructuralHash + Send + Sync, R: Render<T>> Render<T> for Frame<T, R> {
     fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> T {
         if (0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&v) {
             self.render.sample(u, v, time, res)
@@ -42,4 +44,19 @@ impl <T: Copy, R: Render<T>> Render<T> for Frame<T, R> {
     fn duration(&self) -> f64 {
         self.render.duration()
     }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("frame");
+        hasher.write_u64(self.render.structural_hash()?);
+        match &self.frame_type {
+            FrameType::Constant(t) => {
+                hasher.write_u8(0);
+                hasher.write_u64(t.hash_value()?);
+            }
+            FrameType::Extend => hasher.write_u8(1),
+            FrameType::Repeat => hasher.write_u8(2),
+            FrameType::Reflect => hasher.write_u8(3)
+        }
+        Some(hasher.finish())
+    }
 }
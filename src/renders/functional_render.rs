@@ -1,6 +1,6 @@
 use crate::render::{Res, Render, RenderOpt};
 
-pub struct FunctionalRender<T: Copy>(pub Box<dyn Fn(&RenderOpt, f64, &mut [T])>);
+pub struct FunctionalRender<T: Copy>(pub Box<dyn Fn(&RenderOpt, f64, &mut [T]) + Send + Sync>);
 
 impl <T: Copy> Render<T> for FunctionalRender<T> {
     fn sample(&self, _u: f64, _v: f64, _time: f64, _res: Res) -> T {
@@ -8,11 +8,22 @@ impl <T: Copy> Render<T> for FunctionalRender<T> {
     }
 
     fn render(&self, ro: &RenderOpt, buffer: &mut [T]) {
-        let RenderOpt {res_x, res_y, frame_range, framerate, ..} = ro;
-        let frame_size = *res_x * *res_y;
+        let RenderOpt {x_range, y_range, frame_range, framerate, ..} = ro;
+        let frame_size = ((x_range.end - x_range.start) * (y_range.end - y_range.start)) as usize;
         for f in 0..(frame_range.end - frame_range.start) as usize {
-            let time = (frame_range.start as f64 + f as f64) / *framerate as f64;
+            let time = framerate.frame_to_time(frame_range.start as i64 + f as i64);
             (self.0)(ro, time, &mut buffer[f * frame_size..(f + 1) * frame_size]);
         }
     }
 }
+
+// Renders `f(x, y)` of each pixel, for tests of renders that look outside the tile.
+#[cfg(test)]
+pub(crate) fn pixels<T: Copy + 'static>(f: impl Fn(i32, i32) -> T + Send + Sync + 'static) -> FunctionalRender<T> {
+    FunctionalRender(Box::new(move |ro: &RenderOpt, _time: f64, buffer: &mut [T]| {
+        let x_size = (ro.x_range.end - ro.x_range.start) as usize;
+        for (i, p) in buffer.iter_mut().enumerate() {
+            *p = f(ro.x_range.start + (i % x_size) as i32, ro.y_range.start + (i / x_size) as i32);
+        }
+    }))
+}
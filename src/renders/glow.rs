@@ -0,0 +1,145 @@
+use std::hash::Hasher;
+use std::sync::Arc;
+use crate::pixel::Rgba;
+use crate::render::{Res, Render, RenderOpt};
+use crate::renders::frame::FrameType;
+use crate::renders::blur::{BlurType, blur_margin, blur_buffer, render_with_margin};
+use crate::timed::Timed;
+use crate::hash::StructuralHasher;
+use crate::error::KanteraError;
+
+// Each layer doubles the radius, so more layers would only blur with enormous margins.
+pub const MAX_LAYERS: usize = 8;
+
+// Adds the parts of `render` brighter than `threshold`, blurred at `layers` (up to `MAX_LAYERS`) radii
+// doubling from `radius`, multiplied by `tint` and `intensity`. The glow ramps up from `threshold` to full
+// luma.
+pub struct Glow<R: Render<Rgba>> {
+    pub render: R,
+    pub threshold: Arc<dyn Timed<f64>>,
+    pub radius: Arc<dyn Timed<f64>>,
+    pub layers: usize,
+    pub tint: Arc<dyn Timed<Rgba>>,
+    pub intensity: Arc<dyn Timed<f64>>
+}
+
+impl<R: Render<Rgba>> Glow<R> {
+    pub fn new(render: R, threshold: Arc<dyn Timed<f64>>, radius: Arc<dyn Timed<f64>>, intensity: Arc<dyn Timed<f64>>) -> Self {
+        Glow {
+            render,
+            threshold,
+            radius,
+            layers: 3,
+            tint: Arc::new(Rgba(1.0, 1.0, 1.0, 1.0)),
+            intensity
+        }
+    }
+}
+
+impl<R: Render<Rgba>> Render<Rgba> for Glow<R> {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        self.try_sample(u, v, time, res).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_sample(&self, _u: f64, _v: f64, _time: f64, _res: Res) -> Result<Rgba, KanteraError> {
+        Err(KanteraError::SampleNotSupported("Glow"))
+    }
+
+    fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) -> Result<(), KanteraError> {
+        let x_size = (ro.x_range.end - ro.x_range.start) as usize;
+        let y_size = (ro.y_range.end - ro.y_range.start) as usize;
+        let layers = self.layers.clamp(1, MAX_LAYERS);
+        let mut sub_buffer = Vec::new();
+        for f in ro.frame_range.clone() {
+            let time = ro.framerate.frame_to_time(f as i64);
+            let radius = self.radius.get_value(time).max(0.0);
+            let margin = blur_margin(BlurType::Gaussian, radius * 2f64.powi(layers as i32 - 1));
+            render_with_margin(&self.render, ro, f, margin, &Some(FrameType::Constant(Rgba(0.0, 0.0, 0.0, 0.0))), &mut sub_buffer)?;
+            let (width, height) = (x_size + margin * 2, y_size + margin * 2);
+
+            let threshold = self.threshold.get_value(time);
+            let bright: Vec<Rgba> = sub_buffer.iter().map(|p| {
+                let amount = if threshold < 1.0 { ((p.luma() - threshold) / (1.0 - threshold)).clamp(0.0, 1.0) } else { 0.0 };
+                p.premultiply() * amount
+            }).collect();
+            let mut glow = vec![Rgba(0.0, 0.0, 0.0, 0.0); bright.len()];
+            let mut layer = Vec::with_capacity(bright.len());
+            for i in 0..layers {
+                layer.clear();
+                layer.extend_from_slice(&bright);
+                blur_buffer(&mut layer, width, height, BlurType::Gaussian, radius * 2f64.powi(i as i32), (true, true), None);
+                for (g, p) in glow.iter_mut().zip(layer.iter()) {
+                    *g = *g + *p * (1.0 / layers as f64);
+                }
+            }
+
+            let tint = self.tint.get_value(time);
+            let intensity = self.intensity.get_value(time);
+            let frame = &mut buffer[(f - ro.frame_range.start) as usize * x_size * y_size..][..x_size * y_size];
+            for y in 0..y_size {
+                for x in 0..x_size {
+                    let i = (y + margin) * width + x + margin;
+                    let (p, g) = (sub_buffer[i].premultiply(), glow[i] * intensity);
+                    let alpha = (p.3 + g.3 * (1.0 - p.3)).min(1.0);
+                    frame[y * x_size + x] = Rgba(p.0 + g.0 * tint.0, p.1 + g.1 * tint.1, p.2 + g.2 * tint.2, alpha).unpremultiply();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn duration(&self) -> f64 {
+        self.render.duration()
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("glow");
+        hasher.write_u64(self.render.structural_hash()?);
+        hasher.write_u64(self.threshold.structural_hash()?);
+        hasher.write_u64(self.radius.structural_hash()?);
+        hasher.write_usize(self.layers);
+        hasher.write_u64(self.tint.structural_hash()?);
+        hasher.write_u64(self.intensity.structural_hash()?);
+        Some(hasher.finish())
+    }
+}
+
+#[test]
+fn test() {
+    use crate::renders::functional_render::pixels;
+    use crate::framerate::Framerate;
+
+    let ro = RenderOpt {
+        x_range: 0..9,
+        y_range: 0..1,
+        res_x: 9,
+        res_y: 1,
+        frame_range: 0..1,
+        framerate: Framerate::from(30),
+        pixel_center: false
+    };
+    // A bright and a dim pixel on black.
+    let source = || pixels(|x, _| match x {
+        1 => Rgba(1.0, 1.0, 1.0, 1.0),
+        7 => Rgba(0.4, 0.4, 0.4, 1.0),
+        _ => Rgba(0.0, 0.0, 0.0, 1.0)
+    });
+    let mut buffer = vec![Rgba::default(); 9];
+    let mut glow = Glow::new(source(), Arc::new(0.5), Arc::new(1.0), Arc::new(1.0));
+    glow.tint = Arc::new(Rgba(1.0, 0.0, 0.0, 1.0));
+    glow.render(&ro, &mut buffer);
+    // Only the bright pixel glows, in red.
+    assert!(buffer[2].0 > 0.0 && buffer[2].1 == 0.0);
+    assert!(buffer[1].0 > 1.0 && buffer[1].1 == 1.0);
+    assert!(buffer[6].0.abs() < 1e-9 && (buffer[7].0 - 0.4).abs() < 1e-9);
+
+    // Too many layers are capped instead of overflowing the radius.
+    glow.layers = 100;
+    glow.radius = Arc::new(0.1);
+    glow.render(&ro, &mut buffer);
+    assert!(buffer[2].0 > 0.0 && buffer[2].0.is_finite());
+}
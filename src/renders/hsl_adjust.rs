@@ -0,0 +1,83 @@
+use std::hash::Hasher;
+use std::sync::Arc;
+use crate::pixel::Rgba;
+use crate::render::{Res, Render, RenderOpt};
+use crate::timed::Timed;
+use crate::hash::StructuralHasher;
+use crate::error::KanteraError;
+use crate::util::{rgb_to_hsl, hsl_to_rgb};
+
+// Shifts hue (in turns), saturation and lightness. Saturation and lightness are clamped to [0, 1].
+pub struct HslAdjust<R: Render<Rgba>> {
+    pub render: R,
+    pub hue: Arc<dyn Timed<f64>>,
+    pub saturation: Arc<dyn Timed<f64>>,
+    pub lightness: Arc<dyn Timed<f64>>
+}
+
+impl<R: Render<Rgba>> HslAdjust<R> {
+    pub fn new(render: R, hue: Arc<dyn Timed<f64>>, saturation: Arc<dyn Timed<f64>>, lightness: Arc<dyn Timed<f64>>) -> Self {
+        HslAdjust {
+            render,
+            hue,
+            saturation,
+            lightness
+        }
+    }
+
+    fn adjust(&self, time: f64) -> impl Fn(&Rgba) -> Rgba {
+        let (dh, ds, dl) = (self.hue.get_value(time), self.saturation.get_value(time), self.lightness.get_value(time));
+        move |p: &Rgba| {
+            let (h, s, l) = rgb_to_hsl(p.0, p.1, p.2);
+            let (r, g, b) = hsl_to_rgb(h + dh, (s + ds).clamp(0.0, 1.0), (l + dl).clamp(0.0, 1.0));
+            Rgba(r, g, b, p.3)
+        }
+    }
+}
+
+impl<R: Render<Rgba>> Render<Rgba> for HslAdjust<R> {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        self.adjust(time)(&self.render.sample(u, v, time, res))
+    }
+
+    fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) -> Result<(), KanteraError> {
+        self.render.try_render(ro, buffer)?;
+        let frame_size = ((ro.x_range.end - ro.x_range.start) * (ro.y_range.end - ro.y_range.start)) as usize;
+        for (f, frame) in ro.frame_range.clone().zip(buffer.chunks_mut(frame_size)) {
+            let adjust = self.adjust(ro.framerate.frame_to_time(f as i64));
+            for p in frame.iter_mut() {
+                *p = adjust(p);
+            }
+        }
+        Ok(())
+    }
+
+    fn duration(&self) -> f64 {
+        self.render.duration()
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("hsl_adjust");
+        hasher.write_u64(self.render.structural_hash()?);
+        hasher.write_u64(self.hue.structural_hash()?);
+        hasher.write_u64(self.saturation.structural_hash()?);
+        hasher.write_u64(self.lightness.structural_hash()?);
+        Some(hasher.finish())
+    }
+}
+
+#[test]
+fn test() {
+    use crate::renders::plain::Plain;
+
+    let red = Plain::new(Rgba(1.0, 0.0, 0.0, 1.0));
+    let adjust = HslAdjust::new(red, Arc::new(1.0 / 3.0), Arc::new(0.0), Arc::new(0.0));
+    let p = adjust.sample(0.0, 0.0, 0.0, (1, 1));
+    assert!(p.0.abs() < 1e-9 && (p.1 - 1.0).abs() < 1e-9 && p.2.abs() < 1e-9);
+    let grey = HslAdjust::new(Plain::new(Rgba(1.0, 0.0, 0.0, 1.0)), Arc::new(0.0), Arc::new(-1.0), Arc::new(0.0));
+    assert_eq!(grey.sample(0.0, 0.0, 0.0, (1, 1)), Rgba(0.5, 0.5, 0.5, 1.0));
+}
@@ -1,5 +1,7 @@
-use std::rc::Rc;
+use std::hash::Hasher;
+use std::sync::Arc;
 use crate::render::{Res, Render};
+use crate::hash::{StructuralHash, StructuralHasher};
 use crate::image::Image;
 use crate::lerp::Lerp;
 use crate::interpolation::Interpolation;
@@ -12,18 +14,21 @@ pub enum Sizing {
     DotByDot,
 }
 
-pub struct ImageRender<T: Lerp + Copy, U: Interpolation<T>> {
-    pub image: Rc<Image<T>>,
-    pub sizing: Sizing,
-    pub default: T,
-    pub interpolation: U
-}
+impl Sizing {
+    pub fn from_name(name: &str) -> Option<Sizing> {
+        Some(match name {
+            "fit" => Sizing::Fit,
+            "contain" => Sizing::Contain,
+            "cover" => Sizing::Cover,
+            "dot_by_dot" => Sizing::DotByDot,
+            _ => return None
+        })
+    }
 
-impl <T: Lerp + Copy, U: Interpolation<T>> Render<T> for ImageRender<T, U> {
-    fn sample(&self, u: f64, v: f64, _time: f64, res: Res) -> T {
-        let width = self.image.width;
-        let height = self.image.height;
-        let (x, y) = match self.sizing {
+    // The position in an image of `size` that is shown at (u, v) of the output.
+    pub fn image_position(&self, u: f64, v: f64, size: (usize, usize), res: Res) -> (f64, f64) {
+        let (width, height) = size;
+        match self {
             Sizing::Fit => (
                 u * width as f64,
                 v * height as f64
@@ -46,7 +51,21 @@ impl <T: Lerp + Copy, U: Interpolation<T>> Render<T> for ImageRender<T, U> {
                 u * res.0 as f64,
                 v * res.1 as f64
             )
-        };
+        }
+    }
+}
+
+pub struct ImageRender<T: Lerp + Copy, U: Interpolation<T>> {
+    pub image: Arc<Image<T>>,
+    pub sizing: Sizing,
+    pub default: T,
+    pub interpolation: U
+}
+
+impl <T: Lerp + Copy + StructuralHash + Send + Sync, U: Interpolation<T> + Send + Sync> Render<T> for ImageRender<T, U> {
+    fn sample(&self, u: f64, v: f64, _time: f64, res: Res) -> T {
+        let (width, height) = (self.image.width, self.image.height);
+        let (x, y) = self.sizing.image_position(u, v, (width, height), res);
         if 0.0 <= x && x < width as f64 && 0.0 <= y && y < height as f64 {
             self.interpolation.interpolate(self.image.as_ref(), x, y)
         } else {
@@ -67,4 +86,13 @@ impl <T: Lerp + Copy, U: Interpolation<T>> Render<T> for ImageRender<T, U> {
         }
 
    /}*/
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("image_render");
+        hasher.write_u64(self.image.hash_value()?);
+        hasher.write_u8(self.sizing as u8);
+        hasher.write_u64(self.default.hash_value()?);
+        hasher.write_u64(self.interpolation.structural_hash()?);
+        Some(hasher.finish())
+    }
 }
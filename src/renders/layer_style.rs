@@ -0,0 +1,313 @@
+use std::hash::Hasher;
+use std::sync::Arc;
+use crate::pixel::Rgba;
+use crate::render::{Res, Render, RenderOpt};
+use crate::renders::frame::FrameType;
+use crate::renders::blur::{BlurType, blur_margin, blur_buffer, render_with_margin};
+use crate::timed::Timed;
+use crate::v::Vec2;
+use crate::hash::StructuralHasher;
+use crate::error::KanteraError;
+
+// Effects derived from the alpha of a layer. Offsets, radii and widths are in pixels.
+pub enum Style {
+    DropShadow {
+        offset: Arc<dyn Timed<Vec2<f64>>>,
+        blur: Arc<dyn Timed<f64>>,
+        color: Arc<dyn Timed<Rgba>>,
+        opacity: Arc<dyn Timed<f64>>
+    },
+    InnerShadow {
+        offset: Arc<dyn Timed<Vec2<f64>>>,
+        blur: Arc<dyn Timed<f64>>,
+        color: Arc<dyn Timed<Rgba>>,
+        opacity: Arc<dyn Timed<f64>>
+    },
+    OuterGlow {
+        radius: Arc<dyn Timed<f64>>,
+        color: Arc<dyn Timed<Rgba>>,
+        opacity: Arc<dyn Timed<f64>>
+    },
+    Stroke {
+        width: Arc<dyn Timed<f64>>,
+        color: Arc<dyn Timed<Rgba>>,
+        opacity: Arc<dyn Timed<f64>>
+    }
+}
+
+impl Style {
+    fn margin(&self, time: f64) -> usize {
+        let offset = |offset: &Arc<dyn Timed<Vec2<f64>>>| {
+            let o = offset.get_value(time);
+            o.0.abs().max(o.1.abs()).ceil() as usize + 1
+        };
+        match self {
+            Style::DropShadow {offset: o, blur, ..} | Style::InnerShadow {offset: o, blur, ..} =>
+                blur_margin(BlurType::Gaussian, blur.get_value(time)) + offset(o),
+            Style::OuterGlow {radius, ..} => blur_margin(BlurType::Gaussian, radius.get_value(time)),
+            Style::Stroke {width, ..} => width.get_value(time).max(0.0).ceil() as usize + 1
+        }
+    }
+
+    fn hash(&self, hasher: &mut StructuralHasher) -> Option<()> {
+        match self {
+            Style::DropShadow {offset, blur, color, opacity} | Style::InnerShadow {offset, blur, color, opacity} => {
+                hasher.write_u8(if let Style::DropShadow {..} = self { 0 } else { 1 });
+                hasher.write_u64(offset.structural_hash()?);
+                hasher.write_u64(blur.structural_hash()?);
+                hasher.write_u64(color.structural_hash()?);
+                hasher.write_u64(opacity.structural_hash()?);
+            }
+            Style::OuterGlow {radius, color, opacity} => {
+                hasher.write_u8(2);
+                hasher.write_u64(radius.structural_hash()?);
+                hasher.write_u64(color.structural_hash()?);
+                hasher.write_u64(opacity.structural_hash()?);
+            }
+            Style::Stroke {width, color, opacity} => {
+                hasher.write_u8(3);
+                hasher.write_u64(width.structural_hash()?);
+                hasher.write_u64(color.structural_hash()?);
+                hasher.write_u64(opacity.structural_hash()?);
+            }
+        }
+        Some(())
+    }
+}
+
+// Applies `styles` to `render` in order. Shadows, glows and strokes go behind everything applied
+// so far, inner shadows go on top, clipped to the layer.
+pub struct LayerStyle<R: Render<Rgba>> {
+    pub render: R,
+    pub styles: Vec<Style>
+}
+
+impl<R: Render<Rgba>> LayerStyle<R> {
+    pub fn new(render: R, style: Style) -> Self {
+        LayerStyle {render, styles: vec![style]}
+    }
+
+    pub fn append(mut self, style: Style) -> Self {
+        self.styles.push(style);
+        self
+    }
+}
+
+fn blurred_alpha(alpha: &[f64], width: usize, height: usize, radius: f64) -> Vec<f64> {
+    let mut buffer: Vec<Rgba> = alpha.iter().map(|a| Rgba(0.0, 0.0, 0.0, *a)).collect();
+    blur_buffer(&mut buffer, width, height, BlurType::Gaussian, radius, (true, true), None);
+    buffer.iter().map(|p| p.3).collect()
+}
+
+// Bilinear lookup, transparent outside.
+fn alpha_at(alpha: &[f64], width: usize, height: usize, x: f64, y: f64) -> f64 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let get = |x: f64, y: f64| {
+        if 0.0 <= x && x < width as f64 && 0.0 <= y && y < height as f64 {
+            alpha[y as usize * width + x as usize]
+        } else {
+            0.0
+        }
+    };
+    (get(x0, y0) * (1.0 - fx) + get(x0 + 1.0, y0) * fx) * (1.0 - fy)
+        + (get(x0, y0 + 1.0) * (1.0 - fx) + get(x0 + 1.0, y0 + 1.0) * fx) * fy
+}
+
+// Alpha grown by `radius` with an antialiased rim, from the Euclidean distance to the nearest covered pixel.
+// The distance transform is separable (Felzenszwalb and Huttenlocher), so the cost doesn't grow with the radius.
+fn dilated_alpha(alpha: &[f64], width: usize, height: usize, radius: f64) -> Vec<f64> {
+    // Squared distance to, and index of, the nearest covered pixel in the same column.
+    let mut column = vec![(f64::INFINITY, 0); alpha.len()];
+    for x in 0..width {
+        let mut sweep = |ys: &mut dyn Iterator<Item = usize>| {
+            let mut last = None;
+            for y in ys {
+                let i = y * width + x;
+                if alpha[i] > 0.0 {
+                    last = Some(y);
+                }
+                if let Some(l) = last {
+                    let d = y.abs_diff(l) as f64;
+                    if d * d < column[i].0 {
+                        column[i] = (d * d, l * width + x);
+                    }
+                }
+            }
+        };
+        sweep(&mut (0..height));
+        sweep(&mut (0..height).rev());
+    }
+    // Lower envelope of the parabolas `(x - q)^2 + column[q]` along each row.
+    let mut nearest = vec![(f64::INFINITY, 0); alpha.len()];
+    let mut v = vec![0; width];
+    let mut z = vec![0.0; width + 1];
+    for y in 0..height {
+        let row = &column[y * width..(y + 1) * width];
+        let parabola = |q: usize| row[q].0 + (q * q) as f64;
+        let mut k: Option<usize> = None;
+        for q in (0..width).filter(|&q| row[q].0.is_finite()) {
+            let mut s = f64::NEG_INFINITY;
+            while let Some(j) = k {
+                s = (parabola(q) - parabola(v[j])) / (2 * (q - v[j])) as f64;
+                if s > z[j] {
+                    break;
+                }
+                k = j.checked_sub(1);
+            }
+            let j = k.map_or(0, |j| j + 1);
+            v[j] = q;
+            z[j] = if j == 0 { f64::NEG_INFINITY } else { s };
+            z[j + 1] = f64::INFINITY;
+            k = Some(j);
+        }
+        let Some(_) = k else { continue };
+        let mut j = 0;
+        for x in 0..width {
+            while z[j + 1] < x as f64 {
+                j += 1;
+            }
+            let dx = x.abs_diff(v[j]) as f64;
+            nearest[y * width + x] = (dx * dx + row[v[j]].0, row[v[j]].1);
+        }
+    }
+    // A pixel's edge sits `alpha - 0.5` beyond its centre, so an opaque one reaches half a pixel closer.
+    nearest.iter().map(|&(d, i)| {
+        if d.is_finite() { (radius + alpha[i] - d.sqrt()).clamp(0.0, 1.0) } else { 0.0 }
+    }).collect()
+}
+
+impl<R: Render<Rgba>> Render<Rgba> for LayerStyle<R> {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        self.try_sample(u, v, time, res).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_sample(&self, _u: f64, _v: f64, _time: f64, _res: Res) -> Result<Rgba, KanteraError> {
+        Err(KanteraError::SampleNotSupported("LayerStyle"))
+    }
+
+    fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) -> Result<(), KanteraError> {
+        let x_size = (ro.x_range.end - ro.x_range.start) as usize;
+        let y_size = (ro.y_range.end - ro.y_range.start) as usize;
+        let mut sub_buffer = Vec::new();
+        for f in ro.frame_range.clone() {
+            let time = ro.framerate.frame_to_time(f as i64);
+            let margin = self.styles.iter().map(|s| s.margin(time)).max().unwrap_or(0);
+            render_with_margin(&self.render, ro, f, margin, &Some(FrameType::Constant(Rgba(0.0, 0.0, 0.0, 0.0))), &mut sub_buffer)?;
+            let (width, height) = (x_size + margin * 2, y_size + margin * 2);
+            let alpha: Vec<f64> = sub_buffer.iter().map(|p| p.3).collect();
+            let mut out: Vec<Rgba> = sub_buffer.iter().map(|p| p.premultiply()).collect();
+
+            for style in self.styles.iter() {
+                match style {
+                    Style::DropShadow {offset, blur, color, opacity} | Style::InnerShadow {offset, blur, color, opacity} => {
+                        let inner = matches!(style, Style::InnerShadow {..});
+                        let offset = offset.get_value(time);
+                        let color = color.get_value(time);
+                        let opacity = opacity.get_value(time) * color.3;
+                        let blurred = blurred_alpha(&alpha, width, height, blur.get_value(time).max(0.0));
+                        for y in 0..height {
+                            for x in 0..width {
+                                let i = y * width + x;
+                                let a = alpha_at(&blurred, width, height, x as f64 - offset.0, y as f64 - offset.1);
+                                let shadow = if inner { (1.0 - a) * alpha[i] } else { a } * opacity;
+                                let layer = Rgba(color.0 * shadow, color.1 * shadow, color.2 * shadow, shadow);
+                                out[i] = if inner { layer + out[i] * (1.0 - shadow) } else { out[i] + layer * (1.0 - out[i].3) };
+                            }
+                        }
+                    }
+                    Style::OuterGlow {radius, color, opacity} | Style::Stroke {width: radius, color, opacity} => {
+                        let layer_alpha = if let Style::OuterGlow {..} = style {
+                            blurred_alpha(&alpha, width, height, radius.get_value(time).max(0.0))
+                        } else {
+                            dilated_alpha(&alpha, width, height, radius.get_value(time).max(0.0))
+                        };
+                        let color = color.get_value(time);
+                        let opacity = opacity.get_value(time) * color.3;
+                        for (o, a) in out.iter_mut().zip(layer_alpha.iter()) {
+                            let a = a * opacity;
+                            *o = *o + Rgba(color.0 * a, color.1 * a, color.2 * a, a) * (1.0 - o.3);
+                        }
+                    }
+                }
+            }
+
+            let frame = &mut buffer[(f - ro.frame_range.start) as usize * x_size * y_size..][..x_size * y_size];
+            for y in 0..y_size {
+                for x in 0..x_size {
+                    frame[y * x_size + x] = out[(y + margin) * width + x + margin].unpremultiply();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn duration(&self) -> f64 {
+        self.render.duration()
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("layer_style");
+        hasher.write_u64(self.render.structural_hash()?);
+        hasher.write_usize(self.styles.len());
+        for style in self.styles.iter() {
+            style.hash(&mut hasher)?;
+        }
+        Some(hasher.finish())
+    }
+}
+
+#[test]
+fn test() {
+    use crate::renders::functional_render::pixels;
+    use crate::framerate::Framerate;
+
+    let ro = RenderOpt {
+        x_range: 0..9,
+        y_range: 0..1,
+        res_x: 9,
+        res_y: 1,
+        frame_range: 0..1,
+        framerate: Framerate::from(30),
+        pixel_center: false
+    };
+    // A white bar from x = 3 to x = 5 on transparent.
+    let bar = || pixels(|x, _| if (3..6).contains(&x) { Rgba(1.0, 1.0, 1.0, 1.0) } else { Rgba(0.0, 0.0, 0.0, 0.0) });
+    let black = || Arc::new(Rgba(0.0, 0.0, 0.0, 1.0)) as Arc<dyn Timed<Rgba>>;
+    let mut buffer = vec![Rgba::default(); 9];
+
+    LayerStyle::new(bar(), Style::DropShadow {
+        offset: Arc::new(Vec2(2.0, 0.0)), blur: Arc::new(0.0), color: black(), opacity: Arc::new(0.5)
+    }).render(&ro, &mut buffer);
+    assert_eq!(buffer[2], Rgba(0.0, 0.0, 0.0, 0.0));
+    assert_eq!(buffer[4], Rgba(1.0, 1.0, 1.0, 1.0));
+    assert_eq!(buffer[7], Rgba(0.0, 0.0, 0.0, 0.5));
+    assert_eq!(buffer[8], Rgba(0.0, 0.0, 0.0, 0.0));
+
+    LayerStyle::new(bar(), Style::Stroke {width: Arc::new(1.0), color: black(), opacity: Arc::new(1.0)})
+        .append(Style::InnerShadow {offset: Arc::new(Vec2(1.0, 0.0)), blur: Arc::new(0.0), color: black(), opacity: Arc::new(1.0)})
+        .render(&ro, &mut buffer);
+    assert_eq!(buffer[2], Rgba(0.0, 0.0, 0.0, 1.0));
+    assert_eq!(buffer[3], Rgba(0.0, 0.0, 0.0, 1.0));
+    assert_eq!(buffer[4], Rgba(1.0, 1.0, 1.0, 1.0));
+    assert_eq!(buffer[6], Rgba(0.0, 0.0, 0.0, 1.0));
+    assert_eq!(buffer[7], Rgba(0.0, 0.0, 0.0, 0.0));
+
+    LayerStyle::new(bar(), Style::OuterGlow {radius: Arc::new(2.0), color: black(), opacity: Arc::new(1.0)}).render(&ro, &mut buffer);
+    assert!(buffer[1].3 > 0.0 && buffer[1].3 < buffer[2].3 && buffer[8].3 < buffer[7].3);
+
+    // The dilation of a single opaque pixel is a disc with an antialiased rim.
+    let mut dot = vec![0.0; 25];
+    dot[12] = 1.0;
+    let disc = dilated_alpha(&dot, 5, 5, 1.5);
+    assert_eq!(disc[12], 1.0);
+    assert_eq!(disc[13], 1.0);
+    assert_eq!(disc[18], 1.0);
+    assert!((disc[19] - (2.5 - 5f64.sqrt())).abs() < 1e-9);
+    assert_eq!(disc[14], 0.5);
+    assert_eq!(disc[0], 0.0);
+}
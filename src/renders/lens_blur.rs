@@ -0,0 +1,264 @@
+use std::hash::Hasher;
+use std::sync::Arc;
+use crate::pixel::Rgba;
+use crate::image::Image;
+use crate::render::{Res, Render, RenderOpt};
+use crate::renders::frame::FrameType;
+use crate::renders::blur::render_with_margin;
+use crate::timed::Timed;
+use crate::hash::{StructuralHash, StructuralHasher};
+use crate::error::KanteraError;
+
+#[derive(Debug, Clone)]
+pub enum Aperture {
+    Disc,
+    Hexagon,
+    // Stretched over the square of the radius.
+    Custom(Arc<Image<f64>>)
+}
+
+impl Aperture {
+    // The weight at (x, y) in [-1, 1], `radius` pixels to each side, with an antialiased edge.
+    fn weight(&self, x: f64, y: f64, radius: f64) -> f64 {
+        let edge = |d: f64| ((1.0 - d) * radius + 0.5).clamp(0.0, 1.0);
+        match self {
+            Aperture::Disc => edge((x * x + y * y).sqrt()),
+            Aperture::Hexagon => {
+                // A flat topped hexagon with its corners at distance 1.
+                let s = 3.0f64.sqrt();
+                edge((y.abs() * 2.0 / s).max(x.abs() + y.abs() / s))
+            }
+            Aperture::Custom(image) => {
+                let (u, v) = ((x + 1.0) / 2.0 * image.width as f64 - 0.5, (y + 1.0) / 2.0 * image.height as f64 - 0.5);
+                if u < -0.5 || v < -0.5 || image.width as f64 - 0.5 < u || image.height as f64 - 0.5 < v {
+                    return 0.0;
+                }
+                let (u, v) = (u.clamp(0.0, (image.width - 1) as f64), v.clamp(0.0, (image.height - 1) as f64));
+                let (x0, y0) = (u.floor() as usize, v.floor() as usize);
+                let (x1, y1) = ((x0 + 1).min(image.width - 1), (y0 + 1).min(image.height - 1));
+                let (fx, fy) = (u - x0 as f64, v - y0 as f64);
+                let at = |x: usize, y: usize| image.vec[y * image.width + x];
+                (at(x0, y0) * (1.0 - fx) + at(x1, y0) * fx) * (1.0 - fy) + (at(x0, y1) * (1.0 - fx) + at(x1, y1) * fx) * fy
+            }
+        }
+    }
+}
+
+// A 2D lens blur by `radius` pixels in the shape of `aperture`. Pixels brighter than `highlight_threshold`
+// are boosted by up to `highlight_gain` times so that they bloom into the aperture shape.
+// With a `depth` map the radius is scaled by the map's luma, or by its distance to `focus` if set.
+pub struct LensBlur<R: Render<Rgba>> {
+    pub render: R,
+    pub aperture: Aperture,
+    pub radius: Arc<dyn Timed<f64>>,
+    pub highlight_threshold: Arc<dyn Timed<f64>>,
+    pub highlight_gain: Arc<dyn Timed<f64>>,
+    pub depth: Option<R>,
+    pub focus: Option<Arc<dyn Timed<f64>>>,
+    pub frame_type: Option<FrameType<Rgba>>
+}
+
+impl<R: Render<Rgba>> LensBlur<R> {
+    pub fn new(render: R, aperture: Aperture, radius: Arc<dyn Timed<f64>>) -> Self {
+        LensBlur {
+            render,
+            aperture,
+            radius,
+            highlight_threshold: Arc::new(1.0),
+            highlight_gain: Arc::new(0.0),
+            depth: None,
+            focus: None,
+            frame_type: Some(FrameType::Extend)
+        }
+    }
+}
+
+// Radii are quantized so that kernels can be shared between pixels.
+const RADIUS_STEPS: f64 = 4.0;
+
+// Weights of the kernel of the quantized radius `q` over the offsets in [-reach, reach], normalized to
+// sum to 1.
+fn kernel(aperture: &Aperture, reach: i32, q: usize) -> Vec<f64> {
+    let radius = q as f64 / RADIUS_STEPS;
+    let size = (reach * 2 + 1) as usize;
+    let mut kernel = vec![0.0; size * size];
+    if radius < 0.5 {
+        kernel[size * size / 2] = 1.0;
+        return kernel;
+    }
+    for dy in -reach..=reach {
+        for dx in -reach..=reach {
+            kernel[(dy + reach) as usize * size + (dx + reach) as usize] =
+                aperture.weight(dx as f64 / radius, dy as f64 / radius, radius);
+        }
+    }
+    let sum: f64 = kernel.iter().sum();
+    if sum > 0.0 {
+        kernel.iter_mut().for_each(|w| *w /= sum);
+    } else {
+        kernel[size * size / 2] = 1.0;
+    }
+    kernel
+}
+
+impl<R: Render<Rgba>> Render<Rgba> for LensBlur<R> {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        self.try_sample(u, v, time, res).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_sample(&self, _u: f64, _v: f64, _time: f64, _res: Res) -> Result<Rgba, KanteraError> {
+        Err(KanteraError::SampleNotSupported("LensBlur"))
+    }
+
+    fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) -> Result<(), KanteraError> {
+        let x_size = (ro.x_range.end - ro.x_range.start) as usize;
+        let y_size = (ro.y_range.end - ro.y_range.start) as usize;
+        let mut sub_buffer = Vec::new();
+        let mut depth_buffer = Vec::new();
+        for f in ro.frame_range.clone() {
+            let time = ro.framerate.frame_to_time(f as i64);
+            let radius = self.radius.get_value(time).max(0.0);
+            let reach = radius.ceil() as i32 + 1;
+            let margin = reach as usize;
+            let width = x_size + margin * 2;
+            render_with_margin(&self.render, ro, f, margin, &self.frame_type, &mut sub_buffer)?;
+
+            // The quantized radius of each source pixel.
+            let radii: Vec<usize> = if let Some(depth) = &self.depth {
+                render_with_margin(depth, ro, f, margin, &self.frame_type, &mut depth_buffer)?;
+                let focus = self.focus.as_ref().map(|focus| focus.get_value(time));
+                depth_buffer.iter().map(|p| {
+                    let d = p.luma();
+                    let amount = focus.map_or(d, |focus| (d - focus).abs()).clamp(0.0, 1.0);
+                    (radius * amount * RADIUS_STEPS).round() as usize
+                }).collect()
+            } else {
+                vec![(radius * RADIUS_STEPS).round() as usize; sub_buffer.len()]
+            };
+
+            let threshold = self.highlight_threshold.get_value(time);
+            let gain = self.highlight_gain.get_value(time);
+            for p in sub_buffer.iter_mut() {
+                let luma = p.luma();
+                let boost = if luma > threshold && threshold < 1.0 { 1.0 + gain * (luma - threshold) / (1.0 - threshold) } else { 1.0 };
+                let q = p.premultiply();
+                *p = Rgba(q.0 * boost, q.1 * boost, q.2 * boost, q.3);
+            }
+
+            // Each source pixel spreads over its own kernel, gathered here from the output's side.
+            // The kernels by quantized radius, built for the radii in use.
+            let mut kernels = vec![Vec::new(); radii.iter().max().map_or(0, |q| q + 1)];
+            for q in radii.iter() {
+                if kernels[*q].is_empty() {
+                    kernels[*q] = kernel(&self.aperture, reach, *q);
+                }
+            }
+            let size = (reach * 2 + 1) as usize;
+            let frame = &mut buffer[(f - ro.frame_range.start) as usize * x_size * y_size..][..x_size * y_size];
+            for y in 0..y_size {
+                for x in 0..x_size {
+                    let mut acc = Rgba(0.0, 0.0, 0.0, 0.0);
+                    let mut total = 0.0;
+                    for dy in -reach..=reach {
+                        for dx in -reach..=reach {
+                            let i = (y as i32 + margin as i32 + dy) as usize * width + (x as i32 + margin as i32 + dx) as usize;
+                            let w = kernels[radii[i]][(reach - dy) as usize * size + (reach - dx) as usize];
+                            if w > 0.0 {
+                                acc = acc + sub_buffer[i] * w;
+                                total += w;
+                            }
+                        }
+                    }
+                    frame[y * x_size + x] = if total > 0.0 { (acc * (1.0 / total)).unpremultiply() } else { Rgba(0.0, 0.0, 0.0, 0.0) };
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn duration(&self) -> f64 {
+        self.render.duration()
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("lens_blur");
+        hasher.write_u64(self.render.structural_hash()?);
+        match &self.aperture {
+            Aperture::Disc => hasher.write_u8(0),
+            Aperture::Hexagon => hasher.write_u8(1),
+            Aperture::Custom(image) => {
+                hasher.write_u8(2);
+                hasher.write_u64(image.hash_value()?);
+            }
+        }
+        hasher.write_u64(self.radius.structural_hash()?);
+        hasher.write_u64(self.highlight_threshold.structural_hash()?);
+        hasher.write_u64(self.highlight_gain.structural_hash()?);
+        match &self.depth {
+            Some(depth) => hasher.write_u64(depth.structural_hash()?),
+            None => hasher.write_u8(0)
+        }
+        match &self.focus {
+            Some(focus) => hasher.write_u64(focus.structural_hash()?),
+            None => hasher.write_u8(0)
+        }
+        match &self.frame_type {
+            None => hasher.write_u8(0),
+            Some(FrameType::Constant(t)) => {
+                hasher.write_u8(1);
+                hasher.write_u64(t.hash_value()?);
+            }
+            Some(FrameType::Extend) => hasher.write_u8(2),
+            Some(FrameType::Repeat) => hasher.write_u8(3),
+            Some(FrameType::Reflect) => hasher.write_u8(4)
+        }
+        Some(hasher.finish())
+    }
+}
+
+#[test]
+fn test() {
+    use crate::renders::functional_render::pixels;
+    use crate::framerate::Framerate;
+
+    let ro = RenderOpt {
+        x_range: 0..9,
+        y_range: 0..9,
+        res_x: 9,
+        res_y: 9,
+        frame_range: 0..1,
+        framerate: Framerate::from(30),
+        pixel_center: false
+    };
+    // A single bright point in the middle of a black frame.
+    let point = || Box::new(pixels(|x, y| if (x, y) == (4, 4) { Rgba(1.0, 1.0, 1.0, 1.0) } else { Rgba(0.0, 0.0, 0.0, 1.0) })) as Box<dyn Render<Rgba>>;
+    let mut buffer = vec![Rgba::default(); 81];
+
+    // The point spreads into a disc which doesn't reach the corners of its square.
+    let lens_blur = LensBlur::new(point(), Aperture::Disc, Arc::new(3.0));
+    lens_blur.render(&ro, &mut buffer);
+    let total: f64 = buffer.iter().map(|p| p.0).sum();
+    let at = |buffer: &[Rgba], x: usize, y: usize| buffer[y * 9 + x];
+    assert!((total - 1.0).abs() < 1e-9);
+    assert!(at(&buffer, 1, 4).0 > 0.0 && at(&buffer, 1, 4) == at(&buffer, 4, 1));
+    assert_eq!(at(&buffer, 1, 1).0, 0.0);
+
+    // Boosted highlights carry more energy.
+    let mut lens_blur = LensBlur::new(point(), Aperture::Hexagon, Arc::new(3.0));
+    lens_blur.highlight_threshold = Arc::new(0.5);
+    lens_blur.highlight_gain = Arc::new(2.0);
+    lens_blur.render(&ro, &mut buffer);
+    let total: f64 = buffer.iter().map(|p| p.0).sum();
+    assert!((total - 3.0).abs() < 1e-9);
+
+    // Where the depth map is in focus nothing is blurred.
+    let mut lens_blur = LensBlur::new(point(), Aperture::Disc, Arc::new(3.0));
+    lens_blur.depth = Some(Box::new(crate::renders::plain::Plain::new(Rgba(0.5, 0.5, 0.5, 1.0))));
+    lens_blur.focus = Some(Arc::new(0.5));
+    lens_blur.render(&ro, &mut buffer);
+    assert_eq!(at(&buffer, 4, 4), Rgba(1.0, 1.0, 1.0, 1.0));
+}
@@ -0,0 +1,95 @@
+use std::hash::Hasher;
+use std::sync::Arc;
+use crate::pixel::Rgba;
+use crate::render::{Res, Render, RenderOpt};
+use crate::timed::Timed;
+use crate::hash::StructuralHasher;
+use crate::error::KanteraError;
+
+// Each channel of the parameters applies to the same channel of the pixels, so channels can be leveled
+// separately. Input in [black, white] is mapped to [0, 1], raised to 1 / gamma and then mapped to
+// [output_black, output_white].
+pub struct Levels<R: Render<Rgba>> {
+    pub render: R,
+    pub black: Arc<dyn Timed<Rgba>>,
+    pub white: Arc<dyn Timed<Rgba>>,
+    pub gamma: Arc<dyn Timed<Rgba>>,
+    pub output_black: Arc<dyn Timed<Rgba>>,
+    pub output_white: Arc<dyn Timed<Rgba>>
+}
+
+impl<R: Render<Rgba>> Levels<R> {
+    // The same levels for red, green and blue, leaving alpha as is.
+    pub fn new(render: R, black: f64, white: f64, gamma: f64) -> Self {
+        Levels {
+            render,
+            black: Arc::new(Rgba(black, black, black, 0.0)),
+            white: Arc::new(Rgba(white, white, white, 1.0)),
+            gamma: Arc::new(Rgba(gamma, gamma, gamma, 1.0)),
+            output_black: Arc::new(Rgba(0.0, 0.0, 0.0, 0.0)),
+            output_white: Arc::new(Rgba(1.0, 1.0, 1.0, 1.0))
+        }
+    }
+
+    fn levels(&self, time: f64) -> impl Fn(&Rgba) -> Rgba {
+        let black = self.black.get_value(time);
+        let white = self.white.get_value(time);
+        let gamma = self.gamma.get_value(time);
+        let output_black = self.output_black.get_value(time);
+        let output_white = self.output_white.get_value(time);
+        let f = |v: f64, black: f64, white: f64, gamma: f64, output_black: f64, output_white: f64| {
+            let x = if white > black { ((v - black) / (white - black)).clamp(0.0, 1.0) } else if v < black { 0.0 } else { 1.0 };
+            output_black + (output_white - output_black) * x.powf(1.0 / gamma.max(1e-6))
+        };
+        move |p: &Rgba| Rgba(
+            f(p.0, black.0, white.0, gamma.0, output_black.0, output_white.0),
+            f(p.1, black.1, white.1, gamma.1, output_black.1, output_white.1),
+            f(p.2, black.2, white.2, gamma.2, output_black.2, output_white.2),
+            f(p.3, black.3, white.3, gamma.3, output_black.3, output_white.3))
+    }
+}
+
+impl<R: Render<Rgba>> Render<Rgba> for Levels<R> {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        self.levels(time)(&self.render.sample(u, v, time, res))
+    }
+
+    fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) -> Result<(), KanteraError> {
+        self.render.try_render(ro, buffer)?;
+        let frame_size = ((ro.x_range.end - ro.x_range.start) * (ro.y_range.end - ro.y_range.start)) as usize;
+        for (f, frame) in ro.frame_range.clone().zip(buffer.chunks_mut(frame_size)) {
+            let levels = self.levels(ro.framerate.frame_to_time(f as i64));
+            for p in frame.iter_mut() {
+                *p = levels(p);
+            }
+        }
+        Ok(())
+    }
+
+    fn duration(&self) -> f64 {
+        self.render.duration()
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("levels");
+        hasher.write_u64(self.render.structural_hash()?);
+        hasher.write_u64(self.black.structural_hash()?);
+        hasher.write_u64(self.white.structural_hash()?);
+        hasher.write_u64(self.gamma.structural_hash()?);
+        hasher.write_u64(self.output_black.structural_hash()?);
+        hasher.write_u64(self.output_white.structural_hash()?);
+        Some(hasher.finish())
+    }
+}
+
+#[test]
+fn test() {
+    use crate::renders::plain::Plain;
+
+    let levels = Levels::new(Plain::new(Rgba(0.5, 0.25, 0.0, 0.5)), 0.25, 0.75, 1.0);
+    assert_eq!(levels.sample(0.0, 0.0, 0.0, (1, 1)), Rgba(0.5, 0.0, 0.0, 0.5));
+}
@@ -0,0 +1,68 @@
+use std::hash::Hasher;
+use std::sync::Arc;
+use crate::pixel::Rgba;
+use crate::render::{Res, Render, RenderOpt};
+use crate::lut::{Lut, LutInterpolation};
+use crate::timed::Timed;
+use crate::hash::StructuralHasher;
+use crate::error::KanteraError;
+
+// Applies `lut` to the colour and leaves alpha as is. `mix` blends between the original (0.0) and the
+// graded colour (1.0).
+pub struct Lut3d<R: Render<Rgba>> {
+    pub render: R,
+    pub lut: Arc<Lut>,
+    pub interpolation: LutInterpolation,
+    pub mix: Arc<dyn Timed<f64>>
+}
+
+impl<R: Render<Rgba>> Lut3d<R> {
+    pub fn new(render: R, lut: Arc<Lut>) -> Self {
+        Lut3d {
+            render,
+            lut,
+            interpolation: LutInterpolation::Tetrahedral,
+            mix: Arc::new(1.0)
+        }
+    }
+
+    fn grade(&self, p: &Rgba, mix: f64) -> Rgba {
+        let (r, g, b) = self.lut.apply((p.0, p.1, p.2), self.interpolation);
+        Rgba(p.0 + (r - p.0) * mix, p.1 + (g - p.1) * mix, p.2 + (b - p.2) * mix, p.3)
+    }
+}
+
+impl<R: Render<Rgba>> Render<Rgba> for Lut3d<R> {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        self.grade(&self.render.sample(u, v, time, res), self.mix.get_value(time))
+    }
+
+    fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) -> Result<(), KanteraError> {
+        self.render.try_render(ro, buffer)?;
+        let frame_size = ((ro.x_range.end - ro.x_range.start) * (ro.y_range.end - ro.y_range.start)) as usize;
+        for (f, frame) in ro.frame_range.clone().zip(buffer.chunks_mut(frame_size)) {
+            let mix = self.mix.get_value(ro.framerate.frame_to_time(f as i64));
+            for p in frame.iter_mut() {
+                *p = self.grade(p, mix);
+            }
+        }
+        Ok(())
+    }
+
+    fn duration(&self) -> f64 {
+        self.render.duration()
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("lut3d");
+        hasher.write_u64(self.render.structural_hash()?);
+        hasher.write_u64(self.lut.hash_value()?);
+        hasher.write_u8(self.interpolation as u8);
+        hasher.write_u64(self.mix.structural_hash()?);
+        Some(hasher.finish())
+    }
+}
@@ -1,26 +1,123 @@
+use std::sync::Arc;
 use crate::render::{Res, Render, RenderOpt};
+use crate::renders::cache::Memo;
+use crate::framerate::Framerate;
+use crate::error::KanteraError;
 
+// The number of mapped frames kept for the tiles still to be rendered.
+const MAX_FRAMES: usize = 4;
+
+pub type MapFn<T> = dyn Fn(usize, usize, &mut [T]) + Send + Sync;
+
+// Applies `map` to whole frames of `render`, given as width, height and the pixels. When rendered in
+// tiles, each frame is mapped once and shared by its tiles.
 pub struct Map<T: Copy, R: Render<T>> {
     pub render: R,
-    pub map: Box<dyn Fn(usize, usize, &mut [T])>
+    pub map: Box<MapFn<T>>,
+    frames: Memo<(i32, Framerate, Res, bool), Arc<Vec<T>>>
 }
 
-impl <T: Copy, R: Render<T>> Render<T> for Map<T, R> {
-    fn sample(&self, _u: f64, _v: f64, _time: f64, _res: Res) -> T {
-        unimplemented!();
+impl<T: Copy + Default, R: Render<T>> Map<T, R> {
+    pub fn new(render: R, map: Box<MapFn<T>>) -> Self {
+        Map {render, map, frames: Memo::new(MAX_FRAMES)}
+    }
+
+    fn frame(&self, ro: &RenderOpt, f: i32) -> Result<Arc<Vec<T>>, KanteraError> {
+        let (width, height) = (ro.res_x, ro.res_y);
+        self.frames.get_or_try_init((f, ro.framerate, (width, height), ro.pixel_center), || {
+            let mut vec = vec![T::default(); width * height];
+            self.render.try_render(&RenderOpt {
+                x_range: 0..width as i32,
+                y_range: 0..height as i32,
+                frame_range: f..f + 1,
+                ..ro.clone()
+            }, &mut vec)?;
+            (self.map)(width, height, &mut vec);
+            Ok(Arc::new(vec))
+        })
+    }
+}
+
+impl <T: Copy + Default + Send + Sync, R: Render<T>> Render<T> for Map<T, R> {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> T {
+        self.try_sample(u, v, time, res).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_sample(&self, _u: f64, _v: f64, _time: f64, _res: Res) -> Result<T, KanteraError> {
+        Err(KanteraError::SampleNotSupported("Map"))
     }
 
     fn render(&self, ro: &RenderOpt, buffer: &mut [T]) {
-        self.render.render(ro, buffer);
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [T]) -> Result<(), KanteraError> {
+        let RenderOpt {x_range, y_range, res_x, res_y, frame_range, ..} = ro;
+        let x_size = (x_range.end - x_range.start) as usize;
+        let y_size = (y_range.end - y_range.start) as usize;
+        let frame_size = x_size * y_size;
+        if *x_range == (0..*res_x as i32) && *y_range == (0..*res_y as i32) {
+            self.render.try_render(ro, buffer)?;
+            for f in 0..(frame_range.end - frame_range.start) as usize {
+                (self.map)(x_size, y_size, &mut buffer[f * frame_size..(f + 1) * frame_size]);
+            }
+            return Ok(());
+        }
 
-        let RenderOpt {res_x, res_y, frame_range, ..} = ro;
-        let frame_size = *res_x * *res_y;
-        for f in 0..(frame_range.end - frame_range.start) as usize {
-            (self.map)(*res_x, *res_y, &mut buffer[f * frame_size..(f + 1) * frame_size]);
+        // Pixels outside the frame are left unmapped.
+        let (xs, ys) = (x_range.start.max(0), y_range.start.max(0));
+        let (xe, ye) = (x_range.end.min(*res_x as i32), y_range.end.min(*res_y as i32));
+        if xs > x_range.start || ys > y_range.start || xe < x_range.end || ye < y_range.end {
+            self.render.try_render(ro, buffer)?;
+        }
+        if xs >= xe || ys >= ye {
+            return Ok(());
+        }
+        for f in frame_range.clone() {
+            let frame = self.frame(ro, f)?;
+            let buffer = &mut buffer[(f - frame_range.start) as usize * frame_size..][..frame_size];
+            for y in ys..ye {
+                let start = (y - y_range.start) as usize * x_size + (xs - x_range.start) as usize;
+                let src = y as usize * res_x + xs as usize;
+                buffer[start..start + (xe - xs) as usize].copy_from_slice(&frame[src..src + (xe - xs) as usize]);
+            }
         }
+        Ok(())
     }
 
     fn duration(&self) -> f64 {
         self.render.duration()
     }
 }
+
+#[test]
+fn test() {
+    use crate::pixel::Rgba;
+    use crate::render::Dummy;
+    use crate::parallel::{ParallelOpt, render_parallel};
+
+    // Each pixel becomes the mean of the whole frame, which no single tile could compute.
+    let map = Map::new(Dummy(), Box::new(|w, h, buffer: &mut [Rgba]| {
+        let mut sum = Rgba(0.0, 0.0, 0.0, 0.0);
+        for p in buffer.iter() {
+            sum = Rgba(sum.0 + p.0, sum.1 + p.1, sum.2 + p.2, sum.3 + p.3);
+        }
+        let n = (w * h) as f64;
+        buffer.fill(Rgba(sum.0 / n, sum.1 / n, sum.2 / n, sum.3 / n));
+    }));
+    let ro = RenderOpt {
+        x_range: 0..20,
+        y_range: 0..10,
+        res_x: 20,
+        res_y: 10,
+        frame_range: 0..3,
+        framerate: Framerate::from(30),
+        pixel_center: false
+    };
+    let mut expected = vec![Rgba::default(); 20 * 10 * 3];
+    map.render(&ro, &mut expected);
+    assert!(expected[..200].iter().all(|p| *p == expected[0]));
+    let mut actual = vec![Rgba::default(); 20 * 10 * 3];
+    render_parallel(&ro, &ParallelOpt {thread_num: 4, tile_width: 8, tile_height: 4}, &map, &mut actual);
+    assert_eq!(actual, expected);
+}
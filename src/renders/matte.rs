@@ -0,0 +1,165 @@
+use std::hash::Hasher;
+use crate::pixel::Rgba;
+use crate::render::{Res, Render, RenderOpt};
+use crate::hash::StructuralHasher;
+use crate::error::KanteraError;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MatteType {
+    Alpha,
+    InvertedAlpha,
+    Luma,
+    InvertedLuma
+}
+
+// How a mask is combined with the masks before it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MaskMode {
+    Add,
+    Subtract,
+    Intersect
+}
+
+impl MatteType {
+    pub fn from_name(name: &str) -> Option<MatteType> {
+        Some(match name {
+            "alpha" => MatteType::Alpha,
+            "inverted_alpha" => MatteType::InvertedAlpha,
+            "luma" => MatteType::Luma,
+            "inverted_luma" => MatteType::InvertedLuma,
+            _ => return None
+        })
+    }
+
+    pub fn value(&self, p: &Rgba) -> f64 {
+        // Luma of the matte over black.
+        let luma = || p.premultiply().luma();
+        match self {
+            MatteType::Alpha => p.3,
+            MatteType::InvertedAlpha => 1.0 - p.3,
+            MatteType::Luma => luma(),
+            MatteType::InvertedLuma => 1.0 - luma()
+        }
+    }
+}
+
+impl MaskMode {
+    pub fn from_name(name: &str) -> Option<MaskMode> {
+        Some(match name {
+            "add" => MaskMode::Add,
+            "subtract" => MaskMode::Subtract,
+            "intersect" => MaskMode::Intersect,
+            _ => return None
+        })
+    }
+
+    pub fn combine(&self, acc: f64, value: f64) -> f64 {
+        let value = value.clamp(0.0, 1.0);
+        match self {
+            MaskMode::Add => acc + value - acc * value,
+            MaskMode::Subtract => acc * (1.0 - value),
+            MaskMode::Intersect => acc * value
+        }
+    }
+}
+
+// Multiplies the alpha of `render` by the combined masks. Like in most editors, the masks start from
+// nothing if the first one adds and from everything otherwise.
+pub struct Matte<R: Render<Rgba>> {
+    pub render: R,
+    pub masks: Vec<(R, MatteType, MaskMode)>
+}
+
+impl<R: Render<Rgba>> Matte<R> {
+    pub fn new(render: R, mask: R, matte_type: MatteType) -> Self {
+        Matte {
+            render,
+            masks: vec![(mask, matte_type, MaskMode::Add)]
+        }
+    }
+
+    pub fn append(mut self, mask: R, matte_type: MatteType, mask_mode: MaskMode) -> Self {
+        self.masks.push((mask, matte_type, mask_mode));
+        self
+    }
+
+    fn initial_value(&self) -> f64 {
+        match self.masks.first() {
+            Some((_, _, MaskMode::Add)) => 0.0,
+            _ => 1.0
+        }
+    }
+}
+
+impl<R: Render<Rgba>> Render<Rgba> for Matte<R> {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        let mut m = self.initial_value();
+        for (mask, matte_type, mask_mode) in self.masks.iter() {
+            m = mask_mode.combine(m, matte_type.value(&mask.sample(u, v, time, res)));
+        }
+        let p = self.render.sample(u, v, time, res);
+        Rgba(p.0, p.1, p.2, p.3 * m)
+    }
+
+    fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) -> Result<(), KanteraError> {
+        self.render.try_render(ro, buffer)?;
+        let mut m = vec![self.initial_value(); buffer.len()];
+        let mut mask_buffer = vec![Rgba::default(); buffer.len()];
+        for (mask, matte_type, mask_mode) in self.masks.iter() {
+            mask.try_render(ro, &mut mask_buffer)?;
+            for (m, p) in m.iter_mut().zip(mask_buffer.iter()) {
+                *m = mask_mode.combine(*m, matte_type.value(p));
+            }
+        }
+        for (p, m) in buffer.iter_mut().zip(m.iter()) {
+            p.3 *= m;
+        }
+        Ok(())
+    }
+
+    fn duration(&self) -> f64 {
+        self.render.duration()
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("matte");
+        hasher.write_u64(self.render.structural_hash()?);
+        for (mask, matte_type, mask_mode) in self.masks.iter() {
+            hasher.write_u64(mask.structural_hash()?);
+            hasher.write_u8(*matte_type as u8);
+            hasher.write_u8(*mask_mode as u8);
+        }
+        Some(hasher.finish())
+    }
+}
+
+#[test]
+fn test() {
+    use crate::renders::plain::Plain;
+
+    let white = Rgba(1.0, 1.0, 1.0, 1.0);
+    let gray = Rgba(0.5, 0.5, 0.5, 1.0);
+    let transparent = Rgba(0.0, 0.0, 0.0, 0.0);
+    let matte = |masks: Vec<(Rgba, MatteType, MaskMode)>| -> f64 {
+        let matte = Matte {
+            render: Box::new(Plain::new(white)) as Box<dyn Render<Rgba>>,
+            masks: masks.into_iter().map(|(p, t, m)| (Box::new(Plain::new(p)) as Box<dyn Render<Rgba>>, t, m)).collect()
+        };
+        matte.sample(0.0, 0.0, 0.0, (1, 1)).3
+    };
+    assert!((matte(vec![(gray, MatteType::Alpha, MaskMode::Add)]) - 1.0).abs() < 1e-9);
+    assert!((matte(vec![(gray, MatteType::Luma, MaskMode::Add)]) - 0.5).abs() < 1e-9);
+    assert!((matte(vec![(gray, MatteType::InvertedLuma, MaskMode::Add)]) - 0.5).abs() < 1e-9);
+    assert!((matte(vec![(transparent, MatteType::InvertedAlpha, MaskMode::Add)]) - 1.0).abs() < 1e-9);
+    assert!((matte(vec![(gray, MatteType::Luma, MaskMode::Subtract)]) - 0.5).abs() < 1e-9);
+    assert!((matte(vec![
+        (white, MatteType::Alpha, MaskMode::Add),
+        (gray, MatteType::Luma, MaskMode::Intersect)]) - 0.5).abs() < 1e-9);
+    assert!((matte(vec![
+        (gray, MatteType::Luma, MaskMode::Add),
+        (gray, MatteType::Luma, MaskMode::Add)]) - 0.75).abs() < 1e-9);
+}
@@ -16,3 +16,25 @@ pub mod frame;
 pub mod time_extrapolate;
 pub mod rgb_transform;
 pub mod color_sampling;
+pub mod cache;
+pub mod disk_cache;
+pub mod motion_blur;
+pub mod antialias;
+pub mod time_remap;
+pub mod transition;
+pub mod matte;
+pub mod chroma_key;
+pub mod lut3d;
+pub mod levels;
+pub mod curves;
+pub mod hsl_adjust;
+pub mod blur;
+pub mod lens_blur;
+pub mod glow;
+pub mod convolution;
+pub mod displacement;
+pub mod corner_pin;
+pub mod precomp;
+pub mod layer_style;
+pub mod particles;
+pub mod shape;
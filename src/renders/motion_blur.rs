@@ -0,0 +1,140 @@
+use std::hash::Hasher;
+use crate::pixel::Rgba;
+use crate::render::{Res, Render, RenderOpt};
+use crate::framerate::gcd;
+use crate::hash::StructuralHasher;
+use crate::error::KanteraError;
+
+const MAX_DENOMINATOR: usize = 1000;
+
+pub struct MotionBlur<R: Render<Rgba>> {
+    pub render: R,
+    pub shutter_angle: f64, // in degrees, 360.0 keeps the shutter open for the whole frame
+    pub samples: usize
+}
+
+impl<R: Render<Rgba>> MotionBlur<R> {
+    pub fn new(render: R, shutter_angle: f64, samples: usize) -> Self {
+        MotionBlur {
+            render,
+            shutter_angle,
+            samples
+        }
+    }
+
+    // The fraction of the frame the shutter is open, as `(p, q)` for p / q. Taken with a denominator of
+    // at most MAX_DENOMINATOR, which is exact for angles in whole and half degrees.
+    fn shutter(&self) -> Option<(usize, usize)> {
+        if self.samples <= 1 || self.shutter_angle.is_nan() || self.shutter_angle <= 0.0 {
+            return None;
+        }
+        // The convergents of the continued fraction.
+        let (mut p0, mut q0, mut p1, mut q1) = (0, 1, 1, 0);
+        let mut r = self.shutter_angle.min(360.0) / 360.0;
+        loop {
+            let a = r.floor() as usize;
+            let (p2, q2) = (a * p1 + p0, a * q1 + q0);
+            if q2 > MAX_DENOMINATOR {
+                break;
+            }
+            (p0, q0, p1, q1) = (p1, q1, p2, q2);
+            if r - (a as f64) < 1e-9 {
+                break;
+            }
+            r = 1.0 / (r - a as f64);
+        }
+        if p1 == 0 { None } else { Some((p1, q1)) }
+    }
+}
+
+impl<R: Render<Rgba>> Render<Rgba> for MotionBlur<R> {
+    // Without a frame rate the shutter duration is unknown, so sampling is not blurred.
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        self.render.sample(u, v, time, res)
+    }
+
+    fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) -> Result<(), KanteraError> {
+        let (p, q) = match self.shutter() {
+            Some(shutter) => shutter,
+            None => return self.render.try_render(ro, buffer)
+        };
+        let samples = self.samples;
+        // The sub-frames are `p / (q * samples)` of a frame apart. The child is rendered at the frame rate
+        // that has them on whole frames.
+        let g = gcd(p, q * samples);
+        let (n, step) = ((q * samples / g) as i64, (p / g) as i64);
+        let framerate = ro.framerate.mul(n as usize);
+        let frame_size = ((ro.x_range.end - ro.x_range.start) * (ro.y_range.end - ro.y_range.start)) as usize;
+        let mut sub_buffer = vec![Rgba::default(); frame_size * samples];
+        for f in ro.frame_range.clone() {
+            for (s, sub_buffer) in sub_buffer.chunks_mut(frame_size).enumerate() {
+                let frame = i32::try_from(f as i64 * n + s as i64 * step)
+                    .map_err(|_| KanteraError::OutOfRange(format!("sub-frame of frame {}", f)))?;
+                self.render.try_render(&RenderOpt {
+                    frame_range: frame..frame + 1,
+                    framerate,
+                    ..ro.clone()
+                }, sub_buffer)?;
+            }
+            let buffer = &mut buffer[(f - ro.frame_range.start) as usize * frame_size..(f - ro.frame_range.start + 1) as usize * frame_size];
+            for i in 0..frame_size {
+                // Average in premultiplied alpha so that transparent samples don't darken the result.
+                let mut acc = Rgba(0.0, 0.0, 0.0, 0.0);
+                for s in 0..samples {
+                    acc = acc + sub_buffer[s * frame_size + i].premultiply();
+                }
+                buffer[i] = (acc * (1.0 / samples as f64)).unpremultiply();
+            }
+        }
+        Ok(())
+    }
+
+    fn duration(&self) -> f64 {
+        self.render.duration()
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("motion_blur");
+        hasher.write_u64(self.render.structural_hash()?);
+        hasher.write_f64(self.shutter_angle);
+        hasher.write_usize(self.samples);
+        Some(hasher.finish())
+    }
+}
+
+#[test]
+fn test() {
+    use crate::renders::sample::Sample;
+    use crate::framerate::Framerate;
+
+    let ro = RenderOpt {
+        x_range: 0..8,
+        y_range: 0..1,
+        res_x: 8,
+        res_y: 1,
+        frame_range: 0..1,
+        framerate: Framerate::from(1),
+        pixel_center: false
+    };
+    let mut buffer = vec![Rgba::default(); 8];
+
+    // An edge moving right by a frame width per frame, with the shutter open for the whole frame.
+    let edge = Sample::new(Box::new(|u, _, time, _| {
+        if u < time { Rgba(1.0, 1.0, 1.0, 1.0) } else { Rgba(0.0, 0.0, 0.0, 0.0) }
+    }));
+    MotionBlur::new(edge, 360.0, 4).render(&ro, &mut buffer);
+    assert_eq!(buffer[0], Rgba(1.0, 1.0, 1.0, 0.75));
+    assert_eq!(buffer[2], Rgba(1.0, 1.0, 1.0, 0.5));
+    assert_eq!(buffer[7].3, 0.0);
+
+    // Sub-frames are taken at the exact shutter angle, not one rounded to a multiple of 360 / n.
+    let time = || Sample::new(Box::new(|_, _, time, _| Rgba(time, 0.0, 0.0, 1.0)));
+    MotionBlur::new(time(), 100.0, 3).render(&RenderOpt {frame_range: 2..3, ..ro.clone()}, &mut buffer);
+    assert!((buffer[0].0 - (2.0 + 5.0 / 54.0)).abs() < 1e-12);
+    MotionBlur::new(time(), 172.8, 4).render(&ro, &mut buffer);
+    assert!((buffer[0].0 - 0.18).abs() < 1e-12);
+}
@@ -0,0 +1,383 @@
+use std::hash::Hasher;
+use std::sync::{Arc, Mutex};
+use crate::pixel::Rgba;
+use crate::image::Image;
+use crate::render::{Res, Render, RenderOpt};
+use crate::renders::cache::Memo;
+use crate::interpolation::{Interpolation, Bilinear};
+use crate::path::Path;
+use crate::timed::Timed;
+use crate::lerp::Lerp;
+use crate::v::Vec2;
+use crate::util::{noise, u32_noise};
+use crate::hash::{StructuralHash, StructuralHasher};
+use crate::error::KanteraError;
+
+// The simulation step in seconds.
+const STEP: f64 = 1.0 / 120.0;
+
+// The number of states kept, so that tiles of a frame share one simulation.
+const MAX_STATES: usize = 4;
+
+// Where particles are born. Positions are taken at the time of birth, except for `Path`
+// whose points are spread along the whole path regardless of time.
+pub enum Emitter {
+    Point(Arc<dyn Timed<Vec2<f64>>>),
+    Line(Arc<dyn Timed<Vec2<f64>>>, Arc<dyn Timed<Vec2<f64>>>),
+    Path(Path<Vec2<f64>>)
+}
+
+impl Emitter {
+    fn position(&self, time: f64, r: f64) -> Vec2<f64> {
+        match self {
+            Emitter::Point(p) => p.get_value(time),
+            Emitter::Line(a, b) => a.get_value(time).lerp(&b.get_value(time), r),
+            Emitter::Path(path) => path.get_value(path.points.last().unwrap().0 * r)
+        }
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("emitter");
+        match self {
+            Emitter::Point(p) => {
+                hasher.write_u8(0);
+                hasher.write_u64(p.structural_hash()?);
+            }
+            Emitter::Line(a, b) => {
+                hasher.write_u8(1);
+                hasher.write_u64(a.structural_hash()?);
+                hasher.write_u64(b.structural_hash()?);
+            }
+            Emitter::Path(path) => {
+                hasher.write_u8(2);
+                hasher.write_u64(path.structural_hash()?);
+            }
+        }
+        Some(hasher.finish())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Particle {
+    pub id: u64,
+    pub birth: f64,
+    pub lifetime: f64,
+    pub position: Vec2<f64>,
+    pub velocity: Vec2<f64>
+}
+
+// The parameters of `Particles`, which are fixed once built.
+pub struct ParticlesBuilder {
+    pub emitter: Emitter,
+    pub rate: Arc<dyn Timed<f64>>,
+    pub lifetime: f64,
+    pub lifetime_variance: f64,
+    pub velocity: Arc<dyn Timed<Vec2<f64>>>,
+    pub spread: f64,
+    pub speed_variance: f64,
+    pub gravity: Vec2<f64>,
+    pub drag: f64,
+    pub turbulence: f64,
+    pub turbulence_scale: f64,
+    pub size: Arc<dyn Timed<f64>>,
+    pub color: Arc<dyn Timed<Rgba>>,
+    pub alpha: Arc<dyn Timed<f64>>,
+    pub sprite: Option<Arc<Image<Rgba>>>,
+    pub seed: u32
+}
+
+impl ParticlesBuilder {
+    pub fn new(emitter: Emitter, rate: Arc<dyn Timed<f64>>, lifetime: f64) -> Self {
+        ParticlesBuilder {
+            emitter,
+            rate,
+            lifetime,
+            lifetime_variance: 0.0,
+            velocity: Arc::new(Vec2(0.0, 0.0)),
+            spread: 0.0,
+            speed_variance: 0.0,
+            gravity: Vec2(0.0, 0.0),
+            drag: 0.0,
+            turbulence: 0.0,
+            turbulence_scale: 0.2,
+            size: Arc::new(0.01),
+            color: Arc::new(Rgba(1.0, 1.0, 1.0, 1.0)),
+            alpha: Arc::new(1.0),
+            sprite: None,
+            seed: 0
+        }
+    }
+
+    pub fn build(self) -> Particles {
+        Particles {
+            emitter: self.emitter,
+            rate: self.rate,
+            lifetime: self.lifetime,
+            lifetime_variance: self.lifetime_variance,
+            velocity: self.velocity,
+            spread: self.spread,
+            speed_variance: self.speed_variance,
+            gravity: self.gravity,
+            drag: self.drag,
+            turbulence: self.turbulence,
+            turbulence_scale: self.turbulence_scale,
+            size: self.size,
+            color: self.color,
+            alpha: self.alpha,
+            sprite: self.sprite,
+            seed: self.seed,
+            spawned: Mutex::new(vec![0.0]),
+            states: Memo::new(MAX_STATES)
+        }
+    }
+}
+
+// A particle system that can be seeked: each particle is born at a fixed time from the integrated
+// `rate` and simulated from its birth on, so the state at a time doesn't depend on what was rendered
+// before. `size`, `color` and `alpha` are evaluated over the life of a particle, from 0.0 to 1.0.
+// Without a `sprite`, particles are antialiased discs `size` across. Positions, velocities, sizes and
+// `turbulence_scale` are in units of the output height, as in `Shape`: (0, 0) is the top left and
+// (width / height, 1) the bottom right.
+// The parameters are set through `ParticlesBuilder` and fixed, so that the simulation kept in `spawned`
+// and `states` always matches them.
+pub struct Particles {
+    emitter: Emitter,
+    rate: Arc<dyn Timed<f64>>,
+    lifetime: f64,
+    lifetime_variance: f64,
+    velocity: Arc<dyn Timed<Vec2<f64>>>,
+    spread: f64,
+    speed_variance: f64,
+    gravity: Vec2<f64>,
+    drag: f64,
+    turbulence: f64,
+    turbulence_scale: f64,
+    size: Arc<dyn Timed<f64>>,
+    color: Arc<dyn Timed<Rgba>>,
+    alpha: Arc<dyn Timed<f64>>,
+    sprite: Option<Arc<Image<Rgba>>>,
+    seed: u32,
+    // The number of particles born before each step, computed as far as it has been needed.
+    spawned: Mutex<Vec<f64>>,
+    states: Memo<u64, Arc<Vec<Particle>>>
+}
+
+impl Particles {
+    fn random(&self, id: u64, i: u32) -> f64 {
+        u32_noise(self.seed, u32_noise(id as u32, (id >> 32) as u32, 0), i) as f64 / u32::MAX as f64
+    }
+
+    // The number of particles born before `step`.
+    fn spawned_before(&self, step: usize) -> f64 {
+        let mut spawned = self.spawned.lock().unwrap();
+        while spawned.len() <= step {
+            let k = spawned.len() - 1;
+            let rate = self.rate.get_value((k as f64 + 0.5) * STEP).max(0.0);
+            let last = spawned[k];
+            spawned.push(last + rate * STEP);
+        }
+        spawned[step]
+    }
+
+    // The particles alive at `time`, oldest first.
+    pub fn state(&self, time: f64) -> Arc<Vec<Particle>> {
+        self.states.get_or_try_init(time.to_bits(), || Ok(Arc::new(self.simulate_all(time)))).unwrap()
+    }
+
+    fn simulate_all(&self, time: f64) -> Vec<Particle> {
+        let max_lifetime = self.lifetime * (1.0 + self.lifetime_variance.abs());
+        if time < 0.0 || max_lifetime <= 0.0 {
+            return Vec::new();
+        }
+        let first_step = ((time - max_lifetime) / STEP).floor().max(0.0) as usize;
+        let last_step = (time / STEP).floor() as usize;
+        let mut particles = Vec::new();
+        for k in first_step..=last_step {
+            let (before, after) = (self.spawned_before(k), self.spawned_before(k + 1));
+            for id in before.floor() as u64..after.floor() as u64 {
+                // Born when the integrated rate reaches `id + 1`.
+                let birth = (k as f64 + (id as f64 + 1.0 - before) / (after - before)) * STEP;
+                let lifetime = self.lifetime * (1.0 + self.lifetime_variance * (self.random(id, 0) * 2.0 - 1.0));
+                if birth <= time && time < birth + lifetime {
+                    particles.push(self.simulate(id, birth, lifetime, time));
+                }
+            }
+        }
+        particles
+    }
+
+    fn simulate(&self, id: u64, birth: f64, lifetime: f64, time: f64) -> Particle {
+        let mut position = self.emitter.position(birth, self.random(id, 1));
+        let angle = (self.random(id, 2) * 2.0 - 1.0) * self.spread;
+        let speed = 1.0 + self.speed_variance * (self.random(id, 3) * 2.0 - 1.0);
+        let v = self.velocity.get_value(birth);
+        let (sin, cos) = angle.sin_cos();
+        let mut velocity = Vec2(v.0 * cos - v.1 * sin, v.0 * sin + v.1 * cos) * speed;
+
+        // Stepped on a fixed grid from the birth, so that every time sees the same trajectory.
+        let steps = ((time - birth) / STEP).floor() as usize;
+        let fraction = (time - birth) / STEP - steps as f64;
+        for i in 0..=steps {
+            let t = birth + STEP * i as f64;
+            let mut acceleration = self.gravity;
+            if self.turbulence != 0.0 {
+                // Offset so that the noise isn't sampled at negative coordinates.
+                let (x, y) = (position.0 / self.turbulence_scale + 1000.0, position.1 / self.turbulence_scale + 1000.0);
+                acceleration = acceleration + Vec2(noise(x, y, t), noise(x + 31.4, y + 27.2, t)) * self.turbulence;
+            }
+            let next_velocity = (velocity + acceleration * STEP) * (-self.drag * STEP).exp();
+            let next_position = position + next_velocity * STEP;
+            if i < steps {
+                velocity = next_velocity;
+                position = next_position;
+            } else if fraction > 0.0 {
+                // The last partial step is interpolated.
+                velocity = velocity.lerp(&next_velocity, fraction);
+                position = position.lerp(&next_position, fraction);
+            }
+        }
+        Particle {id, birth, lifetime, position, velocity}
+    }
+
+    fn draw(&self, particle: &Particle, time: f64, ro: &RenderOpt, frame: &mut [Rgba]) {
+        let life = ((time - particle.birth) / particle.lifetime).clamp(0.0, 1.0);
+        let scale = ro.res_y as f64;
+        let size = self.size.get_value(life) * scale;
+        let color = self.color.get_value(life);
+        let alpha = self.alpha.get_value(life) * color.3;
+        if size <= 0.0 || alpha <= 0.0 {
+            return;
+        }
+        let x_size = (ro.x_range.end - ro.x_range.start) as usize;
+        let half = size / 2.0;
+        let (px, py) = (particle.position.0 * scale, particle.position.1 * scale);
+        let x_start = ((px - half).floor() as i32).max(ro.x_range.start);
+        let x_end = ((px + half).ceil() as i32 + 1).min(ro.x_range.end);
+        let y_start = ((py - half).floor() as i32).max(ro.y_range.start);
+        let y_end = ((py + half).ceil() as i32 + 1).min(ro.y_range.end);
+        for y in y_start..y_end {
+            for x in x_start..x_end {
+                let (dx, dy) = (x as f64 + 0.5 - px, y as f64 + 0.5 - py);
+                let p = match &self.sprite {
+                    Some(sprite) => {
+                        let (sx, sy) = ((dx / size + 0.5) * sprite.width as f64, (dy / size + 0.5) * sprite.height as f64);
+                        if 0.0 <= sx && sx < sprite.width as f64 && 0.0 <= sy && sy < sprite.height as f64 {
+                            Bilinear.interpolate(sprite, sx, sy)
+                        } else {
+                            continue;
+                        }
+                    }
+                    None => Rgba(1.0, 1.0, 1.0, (half - dx.hypot(dy) + 0.5).clamp(0.0, 1.0))
+                };
+                let a = p.3 * alpha;
+                if a <= 0.0 {
+                    continue;
+                }
+                let i = (y - ro.y_range.start) as usize * x_size + (x - ro.x_range.start) as usize;
+                frame[i] = Rgba(p.0 * color.0 * a, p.1 * color.1 * a, p.2 * color.2 * a, a) + frame[i] * (1.0 - a);
+            }
+        }
+    }
+}
+
+impl Render<Rgba> for Particles {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        self.try_sample(u, v, time, res).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_sample(&self, _u: f64, _v: f64, _time: f64, _res: Res) -> Result<Rgba, KanteraError> {
+        Err(KanteraError::SampleNotSupported("Particles"))
+    }
+
+    fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
+        let frame_size = ((ro.x_range.end - ro.x_range.start) * (ro.y_range.end - ro.y_range.start)) as usize;
+        for (f, frame) in ro.frame_range.clone().zip(buffer.chunks_mut(frame_size)) {
+            let time = ro.framerate.frame_to_time(f as i64);
+            // Drawn premultiplied.
+            frame.iter_mut().for_each(|p| *p = Rgba(0.0, 0.0, 0.0, 0.0));
+            for particle in self.state(time).iter() {
+                self.draw(particle, time, ro, frame);
+            }
+            frame.iter_mut().for_each(|p| *p = p.unpremultiply());
+        }
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("particles");
+        hasher.write_u64(self.emitter.structural_hash()?);
+        hasher.write_u64(self.rate.structural_hash()?);
+        hasher.write_f64(self.lifetime);
+        hasher.write_f64(self.lifetime_variance);
+        hasher.write_u64(self.velocity.structural_hash()?);
+        hasher.write_f64(self.spread);
+        hasher.write_f64(self.speed_variance);
+        hasher.write_u64(self.gravity.hash_value()?);
+        hasher.write_f64(self.drag);
+        hasher.write_f64(self.turbulence);
+        hasher.write_f64(self.turbulence_scale);
+        hasher.write_u64(self.size.structural_hash()?);
+        hasher.write_u64(self.color.structural_hash()?);
+        hasher.write_u64(self.alpha.structural_hash()?);
+        match &self.sprite {
+            Some(sprite) => hasher.write_u64(sprite.hash_value()?),
+            None => hasher.write_u8(0)
+        }
+        hasher.write_u32(self.seed);
+        Some(hasher.finish())
+    }
+}
+
+#[test]
+fn test() {
+    use crate::framerate::Framerate;
+
+    let mut particles = ParticlesBuilder::new(Emitter::Point(Arc::new(Vec2(0.5, 0.125))), Arc::new(10.0), 1.0);
+    particles.velocity = Arc::new(Vec2(0.0, 0.25));
+    particles.spread = 0.5;
+    particles.gravity = Vec2(0.0, 0.5);
+    particles.turbulence = 0.125;
+    particles.lifetime_variance = 0.2;
+    let particles = particles.build();
+
+    // 10 particles per second living 0.8 to 1.2 seconds.
+    let state = particles.state(5.0);
+    assert!((8..=12).contains(&state.len()));
+    assert!(state.iter().all(|p| p.position.1 > 0.125));
+    assert!(Arc::ptr_eq(&state, &particles.state(5.0)));
+
+    // Between steps the motion is interpolated.
+    let mut particles = ParticlesBuilder::new(Emitter::Point(Arc::new(Vec2(0.0, 0.0))), Arc::new(10.0), 1.0);
+    particles.velocity = Arc::new(Vec2(1.0, 0.0));
+    let particles = particles.build();
+    for p in particles.state(0.5023).iter() {
+        assert!((p.position.0 - (0.5023 - p.birth)).abs() < 1e-9);
+    }
+
+    // Seeking gives the same state as stepping through the frames.
+    let new = || {
+        let mut particles = ParticlesBuilder::new(Emitter::Point(Arc::new(Vec2(0.5, 0.125))), Arc::new(10.0), 1.0);
+        particles.size = Arc::new(0.25);
+        particles.build()
+    };
+    let fresh = new();
+    for f in 0..=150 {
+        fresh.state(f as f64 / 30.0);
+    }
+    let ro = RenderOpt {
+        x_range: 0..16,
+        y_range: 0..16,
+        res_x: 16,
+        res_y: 16,
+        frame_range: 150..151,
+        framerate: Framerate::from(30),
+        pixel_center: false
+    };
+    let mut seeked = vec![Rgba::default(); 16 * 16];
+    let mut stepped = vec![Rgba::default(); 16 * 16];
+    new().render(&ro, &mut seeked);
+    fresh.render(&ro, &mut stepped);
+    assert_eq!(seeked, stepped);
+    // Particles without velocity stay at the emitter.
+    assert_eq!(seeked[2 * 16 + 8], Rgba(1.0, 1.0, 1.0, 1.0));
+    assert_eq!(seeked[12 * 16 + 8], Rgba(0.0, 0.0, 0.0, 0.0));
+}
@@ -1,4 +1,6 @@
+use std::hash::Hasher;
 use crate::render::{Res, Render, RenderOpt};
+use crate::hash::{StructuralHash, StructuralHasher};
 use std::marker::PhantomData;
 
 pub struct PixelInto<T: Default + Clone, U: From<T>, R: Render<T>> {
@@ -7,7 +9,7 @@ pub struct PixelInto<T: Default + Clone, U: From<T>, R: Render<T>> {
     pub u: PhantomData<U>
 }
 
-impl <T: Default + Clone, U: From<T>, R: Render<T>> Render<U> for PixelInto<T, U, R> {
+impl <T: Default + Clone + Send + Sync, U: From<T> + StructuralHash + Send + Sync, R: Render<T>> Render<U> for PixelInto<T, U, R> {
     fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> U {
         self.render.sample(u, v, time, res).into()
     }
@@ -24,6 +26,14 @@ impl <T: Default + Clone, U: From<T>, R: Render<T>> Render<U> for PixelInto<T, U
     fn duration(&self) -> f64 {
         self.render.duration()
     }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("pixel_into");
+        hasher.write_u64(self.render.structural_hash()?);
+        // The conversion is identified by the hash of a converted value, which is tagged with its type.
+        hasher.write_u64(U::from(T::default()).hash_value()?);
+        Some(hasher.finish())
+    }
 }
 
 impl <T: Default + Clone, U: From<T>, R: Render<T>> PixelInto<T, U, R> {
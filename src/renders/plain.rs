@@ -1,4 +1,6 @@
+use std::hash::Hasher;
 use crate::render::{Res, Render, RenderOpt};
+use crate::hash::StructuralHasher;
 use crate::timed::Timed;
 use std::marker::PhantomData;
 
@@ -10,7 +12,7 @@ impl<T: Copy, U: Timed<T>> Plain<T, U> {
     }
 }
 
-impl <T: Copy, U: Timed<T>> Render<T> for Plain<T, U> {
+impl <T: Copy + Send + Sync, U: Timed<T>> Render<T> for Plain<T, U> {
     fn sample(&self, _u: f64, _v: f64, time: f64, _res: Res) -> T {
         self.0.get_value(time)
     }
@@ -23,9 +25,15 @@ impl <T: Copy, U: Timed<T>> Render<T> for Plain<T, U> {
             for y in 0..y_size {
                 for x in 0..x_size {
                     buffer[(f - frame_range.start) as usize * x_size * y_size + y * x_size + x] =
-                        self.0.get_value(f as f64 / *framerate as f64);
+                        self.0.get_value(framerate.frame_to_time(f as i64));
                 }
             }
         }
     }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("plain");
+        hasher.write_u64(self.0.structural_hash()?);
+        Some(hasher.finish())
+    }
 }
@@ -11,20 +11,20 @@ pub struct Playback<T> {
 //    pub interpolation: Interpolation
 }
 
-impl <T: Default + Clone> Render<T> for Playback<T> {
+impl <T: Default + Clone + Send + Sync> Render<T> for Playback<T> {
     fn sample(&self, u: f64, v: f64, time: f64, _res: Res) -> T {
         //let Buffer {width, height, frame_num, framerate, vec} = self.buffer;
         let frame_num = self.buffer.frame_num;
         let framerate = self.buffer.framerate;
         let width = self.buffer.width;
         let height = self.buffer.height;
-        let t = (time * framerate as f64).floor() as usize;
+        let t = framerate.frame_at(time);
         let x = (u * width as f64).floor() as usize;
         let y = (v * height as f64).floor() as usize;
         if (0..width).contains(&x) &&
             (0..height).contains(&y) &&
-            (0..frame_num).contains(&t) {
-                self.buffer.vec[t * width * height + y * width + x].clone()
+            (0..frame_num as i64).contains(&t) {
+                self.buffer.vec[t as usize * width * height + y * width + x].clone()
             } else {
                 T::default()
             }
@@ -45,7 +45,7 @@ impl <T: Default + Clone> Render<T> for Playback<T> {
    /}*/
 
     fn duration(&self) -> f64 {
-        self.buffer.frame_num as f64 * self.buffer.framerate as f64
+        self.buffer.framerate.frame_to_time(self.buffer.frame_num as i64)
     }
 }
 
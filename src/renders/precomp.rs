@@ -0,0 +1,184 @@
+use std::hash::Hasher;
+use std::sync::Arc;
+use crate::render::{Res, Render, RenderOpt};
+use crate::renders::image_render::Sizing;
+use crate::renders::cache::Memo;
+use crate::framerate::Framerate;
+use crate::image::Image;
+use crate::lerp::Lerp;
+use crate::interpolation::Interpolation;
+use crate::hash::{StructuralHash, StructuralHasher};
+use crate::error::KanteraError;
+
+// The number of rendered frames kept for sampling.
+const MAX_FRAMES: usize = 4;
+
+// Renders `render` at a fixed `width` x `height` and `framerate`, whatever the output is, and shows it
+// like an `ImageRender`. The child sees the time shifted by `time_offset`, and renders at pixel centers
+// when the output does; `sample` has no pixel grid and uses pixel corners. What the rendered frames
+// depend on is set through setters, which drop the frames kept so far.
+pub struct Precomp<T: Lerp + Copy, R: Render<T>, U: Interpolation<T>> {
+    render: R,
+    width: usize,
+    height: usize,
+    framerate: Framerate,
+    pub sizing: Sizing,
+    pub time_offset: f64,
+    default: T,
+    pub interpolation: U,
+    frames: Memo<(i64, bool), Arc<Image<T>>>
+}
+
+impl<T: Lerp + Copy, R: Render<T>, U: Interpolation<T>> Precomp<T, R, U> {
+    pub fn new(render: R, width: usize, height: usize, framerate: Framerate, default: T, interpolation: U) -> Self {
+        Precomp {
+            render,
+            width,
+            height,
+            framerate,
+            sizing: Sizing::Contain,
+            time_offset: 0.0,
+            default,
+            interpolation,
+            frames: Memo::new(MAX_FRAMES)
+        }
+    }
+
+    pub fn set_render(&mut self, render: R) {
+        self.render = render;
+        self.frames.clear();
+    }
+
+    pub fn set_size(&mut self, width: usize, height: usize) {
+        (self.width, self.height) = (width, height);
+        self.frames.clear();
+    }
+
+    pub fn set_framerate(&mut self, framerate: Framerate) {
+        self.framerate = framerate;
+        self.frames.clear();
+    }
+
+    pub fn set_default(&mut self, default: T) {
+        self.default = default;
+        self.frames.clear();
+    }
+
+    fn frame(&self, frame: i64, pixel_center: bool) -> Result<Arc<Image<T>>, KanteraError> {
+        // Tiles asking for a frame that is being rendered wait for it instead of rendering it again.
+        self.frames.get_or_try_init((frame, pixel_center), || {
+            let mut vec = vec![self.default; self.width * self.height];
+            self.render.try_render(&RenderOpt {
+                x_range: 0..self.width as i32,
+                y_range: 0..self.height as i32,
+                res_x: self.width,
+                res_y: self.height,
+                frame_range: frame as i32..frame as i32 + 1,
+                framerate: self.framerate,
+                pixel_center
+            }, &mut vec)?;
+            Ok(Arc::new(Image {width: self.width, height: self.height, vec}))
+        })
+    }
+
+    fn sample_frame(&self, u: f64, v: f64, time: f64, res: Res, pixel_center: bool) -> Result<T, KanteraError> {
+        let (x, y) = self.sizing.image_position(u, v, (self.width, self.height), res);
+        if !(0.0 <= x && x < self.width as f64 && 0.0 <= y && y < self.height as f64) {
+            return Ok(self.default);
+        }
+        let image = self.frame(self.framerate.frame_at(time + self.time_offset), pixel_center)?;
+        Ok(self.interpolation.interpolate(&image, x, y))
+    }
+}
+
+impl<T: Lerp + Copy + StructuralHash + Send + Sync, R: Render<T>, U: Interpolation<T> + Send + Sync> Render<T> for Precomp<T, R, U> {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> T {
+        self.try_sample(u, v, time, res).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_sample(&self, u: f64, v: f64, time: f64, res: Res) -> Result<T, KanteraError> {
+        self.sample_frame(u, v, time, res, false)
+    }
+
+    fn render(&self, ro: &RenderOpt, buffer: &mut [T]) {
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [T]) -> Result<(), KanteraError> {
+        let RenderOpt {x_range, y_range, res_x, res_y, frame_range, framerate, ..} = ro;
+        let mut i = 0;
+        for f in frame_range.clone() {
+            let time = framerate.frame_to_time(f as i64);
+            for y in y_range.clone() {
+                for x in x_range.clone() {
+                    let (u, v) = ro.uv(x, y);
+                    buffer[i] = self.sample_frame(u, v, time, (*res_x, *res_y), ro.pixel_center)?;
+                    i += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn duration(&self) -> f64 {
+        (self.render.duration() - self.time_offset).max(0.0)
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("precomp");
+        hasher.write_u64(self.render.structural_hash()?);
+        hasher.write_usize(self.width);
+        hasher.write_usize(self.height);
+        hasher.write_usize(self.framerate.num);
+        hasher.write_usize(self.framerate.den);
+        hasher.write_u8(self.sizing as u8);
+        hasher.write_f64(self.time_offset);
+        hasher.write_u64(self.default.hash_value()?);
+        hasher.write_u64(self.interpolation.structural_hash()?);
+        Some(hasher.finish())
+    }
+}
+
+#[test]
+fn test() {
+    use crate::pixel::Rgba;
+    use crate::renders::sample::Sample;
+    use crate::interpolation::NearestNeighbor;
+
+    // The child sees its own resolution and the time at its own frame rate.
+    let child = Sample::new(Box::new(|u, _, time, res: Res| Rgba(u, time, res.0 as f64, 1.0)));
+    let mut precomp = Precomp::new(child, 4, 2, Framerate::from(10), Rgba(0.0, 0.0, 0.0, 0.0), NearestNeighbor);
+    precomp.sizing = Sizing::Fit;
+    precomp.time_offset = 1.0;
+    let p = precomp.sample(0.5, 0.5, 0.25, (100, 100));
+    assert_eq!(p, Rgba(0.5, 1.2, 4.0, 1.0));
+    assert!(precomp.duration().is_infinite());
+
+    // Letterboxed in a square output.
+    precomp.sizing = Sizing::Contain;
+    assert_eq!(precomp.sample(0.5, 0.1, 0.0, (8, 8)), Rgba(0.0, 0.0, 0.0, 0.0));
+    assert_eq!(precomp.sample(0.5, 0.5, 0.0, (8, 8)).2, 4.0);
+
+    // The child renders at pixel centers when the output does.
+    precomp.sizing = Sizing::Fit;
+    let mut ro = RenderOpt {
+        x_range: 0..4,
+        y_range: 0..2,
+        res_x: 4,
+        res_y: 2,
+        frame_range: 0..1,
+        framerate: Framerate::from(10),
+        pixel_center: false
+    };
+    let mut buffer = vec![Rgba::default(); 8];
+    precomp.render(&ro, &mut buffer);
+    assert_eq!(buffer[1].0, 0.25);
+    ro.pixel_center = true;
+    precomp.render(&ro, &mut buffer);
+    assert_eq!(buffer[1].0, 0.375);
+
+    // Resizing drops the frames rendered at the old size.
+    precomp.set_size(8, 4);
+    precomp.render(&ro, &mut buffer);
+    assert_eq!(buffer[1].2, 8.0);
+}
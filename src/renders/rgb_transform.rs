@@ -5,7 +5,7 @@ type Uvt = (f64, f64, f64);
 
 pub struct RgbTransform<R: Render<Rgba>> {
     pub render: R,
-    pub transformer: Box<dyn Fn(f64, f64, f64, Res) -> (Uvt, Uvt, Uvt)>
+    pub transformer: Box<dyn Fn(f64, f64, f64, Res) -> (Uvt, Uvt, Uvt) + Send + Sync>
 }
 
 impl<R: Render<Rgba>> Render<Rgba> for RgbTransform<R> {
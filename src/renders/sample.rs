@@ -1,9 +1,9 @@
 use crate::render::{Res, Render};
 
-pub struct Sample<T>(Box<dyn Fn(f64, f64, f64, Res) -> T>);
+pub struct Sample<T>(Box<dyn Fn(f64, f64, f64, Res) -> T + Send + Sync>);
 
 impl<T> Sample<T> {
-    pub fn new(f: Box<dyn Fn(f64, f64, f64, Res) -> T>) -> Sample<T> {
+    pub fn new(f: Box<dyn Fn(f64, f64, f64, Res) -> T + Send + Sync>) -> Sample<T> {
         Sample(f)
     }
 }
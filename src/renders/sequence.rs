@@ -1,57 +1,113 @@
+use std::hash::Hasher;
 use crate::render::{Res, Render, RenderOpt};
+use crate::lerp::Lerp;
+use crate::hash::{StructuralHash, StructuralHasher};
+use crate::error::KanteraError;
+use crate::renders::transition::Transition;
 
-pub struct Sequence<T: Default, R: Render<T>> {
-    pub pages: Vec<(f64, bool, R)>,
+pub struct Sequence<T: Default + Copy, R: Render<T>> {
+    pub pages: Vec<(f64, bool, R, Option<Transition<T>>)>,
     t: std::marker::PhantomData<T>
 }
 
 const LARGE_F64: f64 = 100000.0;
 
-impl<T: Default, R: Render<T>> Render<T> for Sequence<T, R> {
-    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> T {
+impl<T: Default + Lerp + Send + Sync, R: Render<T>> Sequence<T, R> {
+    // Time offsets of pages, which are reset by `restart`.
+    fn offset_times(&self) -> Vec<f64> {
         let mut offset_time = 0.0;
-        for i in 0..self.pages.len() {
-            let (start, restart, ref render) = self.pages[i];
-            if restart {
-                offset_time = start;
+        self.pages.iter().map(|(start, restart, _, _)| {
+            if *restart {
+                offset_time = *start;
             }
+            offset_time
+        }).collect()
+    }
+}
+
+impl<T: Default + Lerp + StructuralHash + Send + Sync, R: Render<T>> Render<T> for Sequence<T, R> {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> T {
+        let offset_times = self.offset_times();
+        for i in 0..self.pages.len() {
+            let (start, _, ref render, ref transition) = self.pages[i];
             let end = self.pages.get(i + 1).map_or(LARGE_F64, |t| t.0);
             if (start..end).contains(&time) {
-                return render.sample(u, v, time - offset_time, res);
+                if let (Some(transition), Some(prev)) = (transition, i.checked_sub(1).map(|i| &self.pages[i].2)) {
+                    if time < start + transition.duration {
+                        return transition.sample(
+                            transition.progress(time - start), u, v, res,
+                            |u, v| prev.sample(u, v, time - offset_times[i - 1], res),
+                            |u, v| render.sample(u, v, time - offset_times[i], res));
+                    }
+                }
+                return render.sample(u, v, time - offset_times[i], res);
             }
         }
         T::default()
     }
 
     fn render(&self, ro: &RenderOpt, buffer: &mut [T]) {
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [T]) -> Result<(), KanteraError> {
         let RenderOpt {x_range, y_range, frame_range, framerate, ..} = ro;
         let frame_size = ((x_range.end - x_range.start) * (y_range.end - y_range.start)) as usize;
-        let mut offset_frame = 0;
+        let offset_frames: Vec<i32> = self.offset_times().iter().map(|t| framerate.frame_at(*t) as i32).collect();
 
         for i in 0..self.pages.len() {
-            let (start, restart, ref render) = self.pages[i];
+            let (start, _, ref render, ref transition) = self.pages[i];
             let end = self.pages.get(i + 1).map_or(LARGE_F64, |t| t.0);
+            let offset_frame = offset_frames[i];
 
-            if restart {
-                offset_frame = (start * *framerate as f64) as i32;
+            // The transition from the previous page covers the head of this page.
+            let mut head = framerate.frame_at(start) as i32;
+            if let (Some(transition), true) = (transition, 0 < i) {
+                let transition_end = framerate.frame_at(start + transition.duration.min(end - start)) as i32;
+                let prev = &self.pages[i - 1].2;
+                for f in frame_range.start.max(head)..frame_range.end.min(transition_end) {
+                    let progress = transition.progress(framerate.frame_to_time(f as i64) - start);
+                    transition.try_render_frame(
+                        progress,
+                        &RenderOpt {frame_range: f..f + 1, ..ro.clone()},
+                        &mut |ro, buffer| prev.try_render(&RenderOpt {frame_range: f - offset_frames[i - 1]..f - offset_frames[i - 1] + 1, ..ro.clone()}, buffer),
+                        &mut |ro, buffer| render.try_render(&RenderOpt {frame_range: f - offset_frame..f - offset_frame + 1, ..ro.clone()}, buffer),
+                        &mut buffer[(f - frame_range.start) as usize * frame_size..(f - frame_range.start + 1) as usize * frame_size])?;
+                }
+                head = transition_end;
             }
 
-            let left: i32 = (frame_range.start).max((start * *framerate as f64).floor() as i32);
-            let right: i32 = frame_range.end.min((end * *framerate as f64).floor() as i32);
+            let left: i32 = (frame_range.start).max(head);
+            let right: i32 = frame_range.end.min(framerate.frame_at(end) as i32);
 
             if left >= right {
                 continue;
             }
-            render.render(&RenderOpt {
+            render.try_render(&RenderOpt {
                 frame_range: left - offset_frame..right - offset_frame,
                 ..ro.clone()
             }, &mut buffer[(left - frame_range.start as i32) as usize * frame_size..
-                           (right - frame_range.start as i32) as usize * frame_size]);
+                           (right - frame_range.start as i32) as usize * frame_size])?;
+        }
+        Ok(())
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("sequence");
+        for (start, restart, render, transition) in self.pages.iter() {
+            hasher.write_f64(*start);
+            hasher.write_u8(*restart as u8);
+            hasher.write_u64(render.structural_hash()?);
+            match transition {
+                Some(transition) => hasher.write_u64(transition.structural_hash()?),
+                None => hasher.write_u8(0)
+            }
         }
+        Some(hasher.finish())
     }
 }
 
-impl<T: Default, R: Render<T>> Sequence<T, R> {
+impl<T: Default + Lerp, R: Render<T>> Sequence<T, R> {
     pub fn new() -> Self {
         Sequence {
             pages: vec![],
@@ -60,7 +116,13 @@ impl<T: Default, R: Render<T>> Sequence<T, R> {
     }
 
     pub fn append(mut self, time: f64, restart: bool, render: R) -> Self {
-        self.pages.push((time, restart, render));
+        self.pages.push((time, restart, render, None));
+        self
+    }
+
+    // Transitions from the previous page, starting at `time`.
+    pub fn append_with_transition(mut self, time: f64, restart: bool, render: R, transition: Transition<T>) -> Self {
+        self.pages.push((time, restart, render, Some(transition)));
         self
     }
 
@@ -1,17 +1,35 @@
+use std::hash::Hasher;
 use crate::render::{Res, Render, RenderOpt};
 use crate::pixel::Rgba;
+use crate::lerp::Lerp;
+use crate::hash::{StructuralHash, StructuralHasher};
+use crate::error::KanteraError;
+use crate::renders::transition::Transition;
+use crate::renders::composite::{CompositeMode, composite};
+use std::sync::Arc;
+
+// Start time, z, render, transition from the previous clip with the same z and composite mode.
+pub type SequencerClip<T, R> = (f64, usize, R, Option<Transition<T>>, CompositeMode);
 
 pub struct Sequencer<T: Copy, R: Render<T>> {
-    pub clips: Vec<(f64, usize, R)>,
+    pub clips: Vec<SequencerClip<T, R>>,
     pub default: T
 }
 
 impl<R: Render<Rgba>> Render<Rgba> for Sequencer<Rgba, R> {
-    fn sample(&self, _u: f64, _v: f64, _time: f64, _res: Res) -> Rgba {
-        panic!("sequencer not support sample");
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        self.try_sample(u, v, time, res).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_sample(&self, _u: f64, _v: f64, _time: f64, _res: Res) -> Result<Rgba, KanteraError> {
+        Err(KanteraError::SampleNotSupported("Sequencer"))
     }
 
     fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) -> Result<(), KanteraError> {
         let RenderOpt {x_range, y_range, frame_range, framerate, ..} = ro;
         let frame_size = ((x_range.end - x_range.start) * (y_range.end - y_range.start)) as usize;
 
@@ -20,32 +38,95 @@ impl<R: Render<Rgba>> Render<Rgba> for Sequencer<Rgba, R> {
         }
 
         let mut inner_buffer = vec![self.default; buffer.len()];
-        for (start, _, ref render) in self.clips.iter() {
-            let start_frame = ro.frame_range.start - (start * *framerate as f64) as i32;
-            let end_frame = if render.duration().is_infinite() { std::i32::MAX } else { (render.duration() * *framerate as f64) as i32 };
-            let frame_range = start_frame.max(0)..end_frame.min(frame_range.end - (start * *framerate as f64) as i32);
-            if frame_range.start >= frame_range.end {
-                continue;
+        for (k, (start, z, ref render, ref transition, ref mode)) in self.clips.iter().enumerate() {
+            let start_frame = ro.frame_range.start - framerate.frame_at(*start) as i32;
+            let end_frame = if render.duration().is_infinite() { std::i32::MAX } else { framerate.frame_at(render.duration()) as i32 };
+            let end_frame = end_frame.min(frame_range.end - framerate.frame_at(*start) as i32);
+
+            // A transition covers the head of the clip and replaces the previous clip on the same track.
+            let prev = k.checked_sub(1).map(|k| &self.clips[k]).filter(|prev| prev.1 == *z);
+            let head = transition.as_ref().map_or(0, |t| framerate.frame_at(t.duration) as i32).min(end_frame);
+            if let Some(transition) = transition {
+                for f in start_frame.max(0)..head {
+                    let progress = transition.progress(framerate.frame_to_time(f as i64));
+                    let global_frame = f + framerate.frame_at(*start) as i32;
+                    transition.try_render_frame(
+                        progress,
+                        &RenderOpt {frame_range: global_frame..global_frame + 1, ..ro.clone()},
+                        &mut |ro, buffer| match prev {
+                            Some((prev_start, _, prev_render, _, _)) => {
+                                let f = global_frame - framerate.frame_at(*prev_start) as i32;
+                                if prev_render.duration().is_finite() && framerate.frame_at(prev_render.duration()) as i32 <= f {
+                                    buffer.fill(self.default);
+                                    return Ok(());
+                                }
+                                prev_render.try_render(&RenderOpt {frame_range: f..f + 1, ..ro.clone()}, buffer)
+                            }
+                            None => {
+                                buffer.fill(self.default);
+                                Ok(())
+                            }
+                        },
+                        &mut |ro, buffer| render.try_render(&RenderOpt {frame_range: f..f + 1, ..ro.clone()}, buffer),
+                        &mut inner_buffer[..frame_size])?;
+                    let offset = (f - start_frame) as usize * frame_size;
+                    composite(&mut buffer[offset..offset + frame_size], &inner_buffer[..frame_size], framerate.frame_to_time(f as i64), mode);
+                }
             }
-            let ro = RenderOpt {
-                frame_range: frame_range.clone(),
-                ..ro.clone()
+
+            // Frames taken over by the transition of the next clip on the same track.
+            let next = self.clips.get(k + 1).filter(|next| next.1 == *z);
+            let (cut_start, cut_end) = match next {
+                Some((next_start, _, next_render, Some(next_transition), _)) => {
+                    let cut = framerate.frame_at(*next_start) as i32 - framerate.frame_at(*start) as i32;
+                    let next_end = if next_render.duration().is_infinite() { i32::MAX } else { framerate.frame_at(next_render.duration()) as i32 };
+                    (cut, cut.saturating_add((framerate.frame_at(next_transition.duration) as i32).min(next_end)))
+                }
+                _ => (i32::MAX, i32::MAX)
             };
-            render.render(&ro, &mut inner_buffer[..(frame_range.end - frame_range.start) as usize * frame_size]);
-            let offset = (frame_range.start - start_frame) as usize * frame_size;
-            for i in 0..(frame_range.end - frame_range.start) as usize * frame_size {
-                let j = i + offset;
-                buffer[j] = buffer[j].normal_blend(&inner_buffer[i], 1.0);
+
+            for frame_range in [start_frame.max(head).max(0)..end_frame.min(cut_start), start_frame.max(head).max(cut_end).max(0)..end_frame] {
+                if frame_range.start >= frame_range.end {
+                    continue;
+                }
+                let ro = RenderOpt {
+                    frame_range: frame_range.clone(),
+                    ..ro.clone()
+                };
+                render.try_render(&ro, &mut inner_buffer[..(frame_range.end - frame_range.start) as usize * frame_size])?;
+                // The opacity is evaluated in the clip's own time.
+                for f in frame_range.clone() {
+                    let i = (f - frame_range.start) as usize * frame_size;
+                    let j = (f - start_frame) as usize * frame_size;
+                    composite(&mut buffer[j..j + frame_size], &inner_buffer[i..i + frame_size], framerate.frame_to_time(f as i64), mode);
+                }
             }
         }
+        Ok(())
     }
 
     fn duration(&self) -> f64 {
         self.clips.iter().fold(0.0, |acc, x| acc.max(x.0 + x.2.duration()))
     }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("sequencer");
+        hasher.write_u64(self.default.hash_value()?);
+        for (start, z, render, transition, mode) in self.clips.iter() {
+            hasher.write_f64(*start);
+            hasher.write_usize(*z);
+            hasher.write_u64(render.structural_hash()?);
+            match transition {
+                Some(transition) => hasher.write_u64(transition.structural_hash()?),
+                None => hasher.write_u8(0)
+            }
+            hasher.write_u64(mode.structural_hash()?);
+        }
+        Some(hasher.finish())
+    }
 }
 
-impl<T: Copy, R: Render<T>> Sequencer<T, R> {
+impl<T: Lerp + Default, R: Render<T>> Sequencer<T, R> {
     pub fn new(default: T) -> Self {
         Sequencer {
             clips: vec![],
@@ -53,8 +134,17 @@ impl<T: Copy, R: Render<T>> Sequencer<T, R> {
         }
     }
 
-    pub fn append(mut self, time: f64, z: usize, render: R) -> Self {
-        self.clips.push((time, z, render));
+    pub fn append(self, time: f64, z: usize, render: R) -> Self {
+        self.append_clip(time, z, render, None, CompositeMode::Normal(Arc::new(1.0)))
+    }
+
+    // Transitions from the previous clip with the same z, starting at `time`.
+    pub fn append_with_transition(self, time: f64, z: usize, render: R, transition: Transition<T>) -> Self {
+        self.append_clip(time, z, render, Some(transition), CompositeMode::Normal(Arc::new(1.0)))
+    }
+
+    pub fn append_clip(mut self, time: f64, z: usize, render: R, transition: Option<Transition<T>>, mode: CompositeMode) -> Self {
+        self.clips.push((time, z, render, transition, mode));
         self.clips.sort_by(|x, y| (x.1, x.0).partial_cmp(&(y.1, y.0)).unwrap());
         self
     }
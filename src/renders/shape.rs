@@ -0,0 +1,395 @@
+use std::hash::Hasher;
+use std::sync::Arc;
+use crate::pixel::Rgba;
+use crate::render::{Res, Render, RenderOpt};
+use crate::path::{Path, Point};
+use crate::timed::Timed;
+use crate::v::Vec2;
+use crate::hash::StructuralHasher;
+
+// The number of segments a Bézier curve is flattened into.
+const CURVE_SEGMENTS: usize = 16;
+
+// Shapes in units of the output height, so that they keep their proportions at any resolution:
+// (0, 0) is the top left and (width / height, 1) the bottom right. Angles are in radians.
+pub enum Geometry {
+    Rect {
+        center: Arc<dyn Timed<Vec2<f64>>>,
+        size: Arc<dyn Timed<Vec2<f64>>>,
+        corner_radius: Arc<dyn Timed<f64>>
+    },
+    Ellipse {
+        center: Arc<dyn Timed<Vec2<f64>>>,
+        radii: Arc<dyn Timed<Vec2<f64>>>
+    },
+    // A regular polygon.
+    Polygon {
+        center: Arc<dyn Timed<Vec2<f64>>>,
+        radius: Arc<dyn Timed<f64>>,
+        sides: usize,
+        rotation: Arc<dyn Timed<f64>>
+    },
+    Star {
+        center: Arc<dyn Timed<Vec2<f64>>>,
+        outer_radius: Arc<dyn Timed<f64>>,
+        inner_radius: Arc<dyn Timed<f64>>,
+        points: usize,
+        rotation: Arc<dyn Timed<f64>>
+    },
+    // Only stroked.
+    Line {
+        from: Arc<dyn Timed<Vec2<f64>>>,
+        to: Arc<dyn Timed<Vec2<f64>>>
+    },
+    // The path is flattened at each time, so it can be animated. Open paths are only stroked.
+    Path {
+        path: Arc<dyn Timed<Path<Vec2<f64>>>>,
+        closed: bool
+    }
+}
+
+// A path that doesn't change over time.
+struct FixedPath(Path<Vec2<f64>>);
+
+impl Timed<Path<Vec2<f64>>> for FixedPath {
+    fn get_value(&self, _time: f64) -> Path<Vec2<f64>> {
+        self.0.clone()
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        Timed::<Vec2<f64>>::structural_hash(&self.0)
+    }
+}
+
+// The geometry at one point in time.
+enum Outline {
+    Rect {
+        center: Vec2<f64>,
+        size: Vec2<f64>,
+        corner_radius: f64
+    },
+    Ellipse {
+        center: Vec2<f64>,
+        radii: Vec2<f64>
+    },
+    Polyline {
+        points: Vec<Vec2<f64>>,
+        closed: bool
+    }
+}
+
+impl Geometry {
+    pub fn path(path: &Path<Vec2<f64>>, closed: bool) -> Geometry {
+        Geometry::Path {path: Arc::new(FixedPath(path.clone())), closed}
+    }
+
+    fn outline(&self, time: f64) -> Outline {
+        match self {
+            Geometry::Rect {center, size, corner_radius} => Outline::Rect {
+                center: center.get_value(time), size: size.get_value(time), corner_radius: corner_radius.get_value(time)
+            },
+            Geometry::Ellipse {center, radii} => Outline::Ellipse {center: center.get_value(time), radii: radii.get_value(time)},
+            Geometry::Polygon {center, radius, sides, rotation} => {
+                let (c, r, rotation) = (center.get_value(time), radius.get_value(time), rotation.get_value(time));
+                let sides = (*sides).max(3);
+                let points = (0..sides).map(|i| polar(c, r, rotation + i as f64 / sides as f64 * std::f64::consts::TAU)).collect();
+                Outline::Polyline {points, closed: true}
+            }
+            Geometry::Star {center, outer_radius, inner_radius, points, rotation} => {
+                let c = center.get_value(time);
+                let (outer, inner, rotation) = (outer_radius.get_value(time), inner_radius.get_value(time), rotation.get_value(time));
+                let n = (*points).max(2) * 2;
+                let vertices = (0..n).map(|i| {
+                    polar(c, if i % 2 == 0 { outer } else { inner }, rotation + i as f64 / n as f64 * std::f64::consts::TAU)
+                }).collect();
+                Outline::Polyline {points: vertices, closed: true}
+            }
+            Geometry::Line {from, to} => Outline::Polyline {points: vec![from.get_value(time), to.get_value(time)], closed: false},
+            Geometry::Path {path, closed} => Outline::Polyline {points: flatten_path(&path.get_value(time)), closed: *closed}
+        }
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("geometry");
+        match self {
+            Geometry::Rect {center, size, corner_radius} => {
+                hasher.write_u8(0);
+                hasher.write_u64(center.structural_hash()?);
+                hasher.write_u64(size.structural_hash()?);
+                hasher.write_u64(corner_radius.structural_hash()?);
+            }
+            Geometry::Ellipse {center, radii} => {
+                hasher.write_u8(1);
+                hasher.write_u64(center.structural_hash()?);
+                hasher.write_u64(radii.structural_hash()?);
+            }
+            Geometry::Polygon {center, radius, sides, rotation} => {
+                hasher.write_u8(2);
+                hasher.write_u64(center.structural_hash()?);
+                hasher.write_u64(radius.structural_hash()?);
+                hasher.write_usize(*sides);
+                hasher.write_u64(rotation.structural_hash()?);
+            }
+            Geometry::Star {center, outer_radius, inner_radius, points, rotation} => {
+                hasher.write_u8(3);
+                hasher.write_u64(center.structural_hash()?);
+                hasher.write_u64(outer_radius.structural_hash()?);
+                hasher.write_u64(inner_radius.structural_hash()?);
+                hasher.write_usize(*points);
+                hasher.write_u64(rotation.structural_hash()?);
+            }
+            Geometry::Line {from, to} => {
+                hasher.write_u8(4);
+                hasher.write_u64(from.structural_hash()?);
+                hasher.write_u64(to.structural_hash()?);
+            }
+            Geometry::Path {path, closed} => {
+                hasher.write_u8(5);
+                hasher.write_u64(path.structural_hash()?);
+                hasher.write_u8(*closed as u8);
+            }
+        }
+        Some(hasher.finish())
+    }
+}
+
+impl Outline {
+    // The signed distance from `p`, negative inside.
+    fn distance(&self, p: Vec2<f64>) -> f64 {
+        match self {
+            Outline::Rect {center: c, size: s, corner_radius} => {
+                let r = corner_radius.clamp(0.0, s.0.abs().min(s.1.abs()) / 2.0);
+                let (qx, qy) = ((p.0 - c.0).abs() - s.0.abs() / 2.0 + r, (p.1 - c.1).abs() - s.1.abs() / 2.0 + r);
+                qx.max(0.0).hypot(qy.max(0.0)) + qx.max(qy).min(0.0) - r
+            }
+            Outline::Ellipse {center: c, radii: r} => {
+                let (x, y) = (p.0 - c.0, p.1 - c.1);
+                let (rx, ry) = (r.0.abs().max(1e-9), r.1.abs().max(1e-9));
+                // An approximation that is exact on the ellipse, which is what antialiasing needs.
+                let k0 = (x / rx).hypot(y / ry);
+                let k1 = (x / (rx * rx)).hypot(y / (ry * ry));
+                if k1 == 0.0 { -rx.min(ry) } else { k0 * (k0 - 1.0) / k1 }
+            }
+            Outline::Polyline {points, closed} => polygon_distance(points, p, *closed)
+        }
+    }
+
+    fn fillable(&self) -> bool {
+        match self {
+            Outline::Polyline {closed, ..} => *closed,
+            _ => true
+        }
+    }
+}
+
+fn polar(c: Vec2<f64>, r: f64, angle: f64) -> Vec2<f64> {
+    let (sin, cos) = angle.sin_cos();
+    Vec2(c.0 + r * sin, c.1 - r * cos)
+}
+
+// The points of `path` with its curves flattened. Constant points are joined with lines, as in
+// `path_to_image`.
+pub fn flatten_path(path: &Path<Vec2<f64>>) -> Vec<Vec2<f64>> {
+    let mut points = vec![path.points[0].1];
+    for w in path.points.windows(2) {
+        let (start, end) = (w[0].1, w[1].1);
+        match w[1].2 {
+            Point::Constant | Point::Linear => points.push(end),
+            Point::Bezier2(handle) => {
+                for i in 1..=CURVE_SEGMENTS {
+                    let v = i as f64 / CURVE_SEGMENTS as f64;
+                    let iv = 1.0 - v;
+                    points.push(start * iv.powi(2) + handle * (v * iv * 2.0) + end * v.powi(2));
+                }
+            }
+            Point::Bezier3(handle_1, handle_2) => {
+                for i in 1..=CURVE_SEGMENTS {
+                    let v = i as f64 / CURVE_SEGMENTS as f64;
+                    let iv = 1.0 - v;
+                    points.push(start * iv.powi(3) + handle_1 * (3.0 * v * iv.powi(2)) + handle_2 * (3.0 * v.powi(2) * iv) + end * v.powi(3));
+                }
+            }
+        }
+    }
+    points
+}
+
+// The distance to the segments through `points`, signed by the even-odd rule if `closed`.
+fn polygon_distance(points: &[Vec2<f64>], p: Vec2<f64>, closed: bool) -> f64 {
+    let n = points.len();
+    if n == 0 {
+        return f64::INFINITY;
+    }
+    let mut distance = (p.0 - points[0].0).hypot(p.1 - points[0].1);
+    let mut inside = false;
+    let segments = if closed { n } else { n - 1 };
+    for i in 0..segments {
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        let (ex, ey) = (b.0 - a.0, b.1 - a.1);
+        let (wx, wy) = (p.0 - a.0, p.1 - a.1);
+        let len2 = ex * ex + ey * ey;
+        let t = if len2 == 0.0 { 0.0 } else { ((wx * ex + wy * ey) / len2).clamp(0.0, 1.0) };
+        distance = distance.min((wx - ex * t).hypot(wy - ey * t));
+        if closed && ((a.1 <= p.1) != (b.1 <= p.1)) && p.0 < a.0 + (p.1 - a.1) / ey * ex {
+            inside = !inside;
+        }
+    }
+    if inside { -distance } else { distance }
+}
+
+// Fills and strokes `geometry` with analytic antialiasing. The stroke is centered on the outline and
+// `stroke_width` is in units of the output height, as the geometry.
+pub struct Shape {
+    pub geometry: Geometry,
+    pub fill: Arc<dyn Timed<Rgba>>,
+    pub stroke: Arc<dyn Timed<Rgba>>,
+    pub stroke_width: Arc<dyn Timed<f64>>
+}
+
+impl Shape {
+    pub fn new(geometry: Geometry, fill: Arc<dyn Timed<Rgba>>) -> Self {
+        Shape {
+            geometry,
+            fill,
+            stroke: Arc::new(Rgba(0.0, 0.0, 0.0, 0.0)),
+            stroke_width: Arc::new(0.0)
+        }
+    }
+}
+
+// Everything a Shape needs to colour a pixel at one point in time.
+struct Frame {
+    outline: Outline,
+    fill: Rgba,
+    stroke: Rgba,
+    stroke_width: f64
+}
+
+impl Frame {
+    fn color(&self, u: f64, v: f64, res: Res) -> Rgba {
+        let pixel = 1.0 / res.1 as f64;
+        let d = self.outline.distance(Vec2(u * res.0 as f64 / res.1 as f64, v));
+        let fill_coverage = if self.outline.fillable() { (0.5 - d / pixel).clamp(0.0, 1.0) } else { 0.0 };
+        let stroke_coverage = if self.stroke_width > 0.0 { (0.5 - (d.abs() - self.stroke_width / 2.0) / pixel).clamp(0.0, 1.0) } else { 0.0 };
+        let fill = self.fill.premultiply() * fill_coverage;
+        let stroke = self.stroke.premultiply() * stroke_coverage;
+        (stroke + fill * (1.0 - stroke.3)).unpremultiply()
+    }
+}
+
+impl Shape {
+    fn frame(&self, time: f64) -> Frame {
+        Frame {
+            outline: self.geometry.outline(time),
+            fill: self.fill.get_value(time),
+            stroke: self.stroke.get_value(time),
+            stroke_width: self.stroke_width.get_value(time)
+        }
+    }
+}
+
+impl Render<Rgba> for Shape {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> Rgba {
+        self.frame(time).color(u, v, res)
+    }
+
+    // Evaluates the geometry once per frame rather than per pixel.
+    fn render(&self, ro: &RenderOpt, buffer: &mut [Rgba]) {
+        let RenderOpt {x_range, y_range, res_x, res_y, frame_range, framerate, ..} = ro;
+        let res = (*res_x, *res_y);
+        let mut i = 0;
+        for f in frame_range.clone() {
+            let frame = self.frame(framerate.frame_to_time(f as i64));
+            for y in y_range.clone() {
+                for x in x_range.clone() {
+                    let (u, v) = ro.uv(x, y);
+                    buffer[i] = frame.color(u, v, res);
+                    i += 1;
+                }
+            }
+        }
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("shape");
+        hasher.write_u64(self.geometry.structural_hash()?);
+        hasher.write_u64(self.fill.structural_hash()?);
+        hasher.write_u64(self.stroke.structural_hash()?);
+        hasher.write_u64(self.stroke_width.structural_hash()?);
+        Some(hasher.finish())
+    }
+}
+
+#[test]
+fn test() {
+    let white = || Arc::new(Rgba(1.0, 1.0, 1.0, 1.0)) as Arc<dyn Timed<Rgba>>;
+    let center = || Arc::new(Vec2(1.0, 0.5)) as Arc<dyn Timed<Vec2<f64>>>;
+    let transparent = Rgba(0.0, 0.0, 0.0, 0.0);
+    // On a 2:1 frame, so the center is at (1.0, 0.5).
+    let res = (200, 100);
+
+    let rect = Shape::new(Geometry::Rect {center: center(), size: Arc::new(Vec2(0.5, 0.5)), corner_radius: Arc::new(0.1)}, white());
+    assert_eq!(rect.sample(0.5, 0.5, 0.0, res), Rgba(1.0, 1.0, 1.0, 1.0));
+    // The rounded corner is cut off, the edge is half covered.
+    assert_eq!(rect.sample(0.38, 0.26, 0.0, res), transparent);
+    assert!((rect.sample(0.375, 0.5, 0.0, res).3 - 0.5).abs() < 1e-9);
+    // Resolution independent.
+    assert_eq!(rect.sample(0.4, 0.3, 0.0, res), rect.sample(0.4, 0.3, 0.0, (400, 200)));
+
+    let ellipse = Shape::new(Geometry::Ellipse {center: center(), radii: Arc::new(Vec2(0.4, 0.2))}, white());
+    assert_eq!(ellipse.sample(0.5, 0.35, 0.0, res).3, 1.0);
+    assert_eq!(ellipse.sample(0.5, 0.25, 0.0, res), transparent);
+
+    let mut star = Shape::new(Geometry::Star {
+        center: center(), outer_radius: Arc::new(0.4), inner_radius: Arc::new(0.1), points: 5, rotation: Arc::new(0.0)
+    }, white());
+    star.stroke = Arc::new(Rgba(1.0, 0.0, 0.0, 1.0));
+    star.stroke_width = Arc::new(0.02);
+    assert_eq!(star.sample(0.5, 0.5, 0.0, res), Rgba(1.0, 1.0, 1.0, 1.0));
+    // The tip lies on the outline, under the stroke.
+    assert_eq!(star.sample(0.5, 0.1, 0.0, res), Rgba(1.0, 0.0, 0.0, 1.0));
+    assert_eq!(star.sample(0.6, 0.2, 0.0, res), transparent);
+
+    // A triangle with a curved side.
+    let path = Path::new(Vec2(0.5, 0.2))
+        .append(1.0, Vec2(1.5, 0.2), Point::Linear)
+        .append(1.0, Vec2(1.0, 0.8), Point::Bezier3(Vec2(1.5, 0.8), Vec2(1.5, 0.8)));
+    let triangle = Shape::new(Geometry::path(&path, true), white());
+    assert_eq!(triangle.sample(0.5, 0.3, 0.0, res).3, 1.0);
+    assert_eq!(triangle.sample(0.35, 0.7, 0.0, res), transparent);
+    assert_eq!(Shape::new(Geometry::path(&path, false), white()).sample(0.5, 0.3, 0.0, res), transparent);
+
+    assert!(triangle.structural_hash().is_some());
+
+    // Rendering evaluates each frame once and agrees with sampling.
+    use crate::framerate::Framerate;
+    let ro = RenderOpt {
+        x_range: 0..20,
+        y_range: 0..10,
+        res_x: 20,
+        res_y: 10,
+        frame_range: 0..2,
+        framerate: Framerate::from(1),
+        pixel_center: true
+    };
+    let mut buffer = vec![Rgba::default(); 20 * 10 * 2];
+    star.render(&ro, &mut buffer);
+    for (i, p) in buffer.iter().enumerate() {
+        let (x, y, f) = ((i % 20) as i32, (i / 20 % 10) as i32, (i / 200) as f64);
+        let (u, v) = ro.uv(x, y);
+        assert_eq!(*p, star.sample(u, v, f, (20, 10)));
+    }
+
+    // A path that moves right over time.
+    struct Sliding(Path<Vec2<f64>>);
+    impl Timed<Path<Vec2<f64>>> for Sliding {
+        fn get_value(&self, time: f64) -> Path<Vec2<f64>> {
+            Path {points: self.0.points.iter().map(|&(t, p, point)| (t, p + Vec2(time, 0.0), point)).collect()}
+        }
+    }
+    let sliding = Shape::new(Geometry::Path {path: Arc::new(Sliding(path)), closed: true}, white());
+    assert_eq!(sliding.sample(0.5, 0.3, 0.0, res).3, 1.0);
+    assert_eq!(sliding.sample(0.5, 0.3, 1.0, res), transparent);
+    assert_eq!(sliding.sample(0.9, 0.3, 1.0, res).3, 1.0);
+    assert_eq!(sliding.structural_hash(), None);
+}
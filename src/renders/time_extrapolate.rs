@@ -1,4 +1,6 @@
+use std::hash::Hasher;
 use crate::render::{Res, Render};
+use crate::hash::{StructuralHash, StructuralHasher};
 
 #[derive(Debug, Copy, Clone)]
 pub enum ExtrapolationType<T: Copy> {
@@ -15,7 +17,7 @@ pub struct TimeExtrapolate<T: Copy, R: Render<T>> {
     pub extrapolation_type: ExtrapolationType<T>
 }
 
-impl <T: Copy, R: Render<T>> Render<T> for TimeExtrapolate<T, R> {
+impl <T: Copy + StructuralHash + Send + Sync, R: Render<T>> Render<T> for TimeExtrapolate<T, R> {
     fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> T {
         match &self.extrapolation_type {
             ExtrapolationType::None => self.render.sample(u, v, time, res),
@@ -44,4 +46,21 @@ impl <T: Copy, R: Render<T>> Render<T> for TimeExtrapolate<T, R> {
     }
 
     // TODO render
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("time_extrapolate");
+        hasher.write_u64(self.render.structural_hash()?);
+        hasher.write_f64(self.duration);
+        match &self.extrapolation_type {
+            ExtrapolationType::None => hasher.write_u8(0),
+            ExtrapolationType::Constant(t) => {
+                hasher.write_u8(1);
+                hasher.write_u64(t.hash_value()?);
+            }
+            ExtrapolationType::Extend => hasher.write_u8(2),
+            ExtrapolationType::Repeat => hasher.write_u8(3),
+            ExtrapolationType::Reflect => hasher.write_u8(4)
+        }
+        Some(hasher.finish())
+    }
 }
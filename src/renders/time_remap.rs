@@ -0,0 +1,248 @@
+use std::hash::Hasher;
+use std::marker::PhantomData;
+use crate::render::{Res, Render, RenderOpt};
+use crate::path::{Path, Point};
+use crate::timed::Timed;
+use crate::hash::StructuralHasher;
+use crate::error::KanteraError;
+
+// Maps output time to source time with `map`.
+pub struct TimeRemap<T, R: Render<T>, M: Timed<f64>> {
+    pub render: R,
+    pub map: M,
+    pub duration: f64,
+    pub t: PhantomData<T>
+}
+
+impl<T, R: Render<T>, M: Timed<f64>> TimeRemap<T, R, M> {
+    pub fn new(render: R, map: M, duration: f64) -> Self {
+        TimeRemap {
+            render,
+            map,
+            duration,
+            t: PhantomData
+        }
+    }
+}
+
+impl<T, R: Render<T>> TimeRemap<T, R, SpeedRamp> {
+    // Plays at `speed` times the original speed. A negative speed plays backwards from the end.
+    pub fn speed(render: R, speed: f64) -> Result<Self, KanteraError> {
+        Self::speed_ramp(render, Path::new(speed))
+    }
+
+    pub fn reverse(render: R) -> Result<Self, KanteraError> {
+        Self::speed(render, -1.0)
+    }
+
+    // Holds the source frame at `time` for `duration` seconds.
+    pub fn freeze(render: R, time: f64, duration: f64) -> Self {
+        TimeRemap::new(render, SpeedRamp::new(Path::new(0.0), time), duration)
+    }
+
+    // `speed` is the playback speed over output time. Playback starts at the beginning, or at the end if
+    // the initial speed is negative, and lasts until it leaves the source. Playing backwards fails on a
+    // render of infinite duration.
+    pub fn speed_ramp(render: R, speed: Path<f64>) -> Result<Self, KanteraError> {
+        let source_duration = render.duration();
+        let start = if speed.get_value(0.0) < 0.0 {
+            if !source_duration.is_finite() {
+                return Err(KanteraError::InfiniteDuration);
+            }
+            source_duration
+        } else {
+            0.0
+        };
+        let map = SpeedRamp::new(speed, start);
+        let duration = map.exit_time(0.0, source_duration);
+        Ok(TimeRemap::new(render, map, duration))
+    }
+}
+
+impl<T: Default + Clone + Send + Sync, R: Render<T>, M: Timed<f64>> Render<T> for TimeRemap<T, R, M> {
+    fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> T {
+        self.render.sample(u, v, self.map.get_value(time), res)
+    }
+
+    fn render(&self, ro: &RenderOpt, buffer: &mut [T]) {
+        self.try_render(ro, buffer).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_render(&self, ro: &RenderOpt, buffer: &mut [T]) -> Result<(), KanteraError> {
+        let frame_size = ((ro.x_range.end - ro.x_range.start) * (ro.y_range.end - ro.y_range.start)) as usize;
+        let half_frame = ro.framerate.frame_to_time(1) / 2.0;
+        for f in ro.frame_range.clone() {
+            // The map is evaluated at the middle of the frame so that rounding can't pick a neighbouring
+            // source frame, e.g. when reversing.
+            let time = self.map.get_value(ro.framerate.frame_to_time(f as i64) + half_frame);
+            let source_frame = ro.framerate.time_to_frame(time).floor() as i32;
+            self.render.try_render(&RenderOpt {
+                frame_range: source_frame..source_frame + 1,
+                ..ro.clone()
+            }, &mut buffer[(f - ro.frame_range.start) as usize * frame_size..(f - ro.frame_range.start + 1) as usize * frame_size])?;
+        }
+        Ok(())
+    }
+
+    fn duration(&self) -> f64 {
+        self.duration
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("time_remap");
+        hasher.write_u64(self.render.structural_hash()?);
+        hasher.write_u64(self.map.structural_hash()?);
+        hasher.write_f64(self.duration);
+        Some(hasher.finish())
+    }
+}
+
+// Source time as the integral of a speed curve, starting from `start`. Outside of the curve the speed
+// is held at its first and last values.
+#[derive(Debug, Clone)]
+pub struct SpeedRamp {
+    pub speed: Path<f64>,
+    pub start: f64,
+    source_times: Vec<f64>
+}
+
+impl SpeedRamp {
+    pub fn new(speed: Path<f64>, start: f64) -> Self {
+        let mut source_times = vec![start];
+        for (i, w) in speed.points.windows(2).enumerate() {
+            source_times.push(source_times[i] + segment_integral(&w[0], &w[1], w[0].0, w[1].0));
+        }
+        SpeedRamp {
+            speed,
+            start,
+            source_times
+        }
+    }
+
+    // The first output time after 0 at which the source time leaves [lo, hi].
+    pub fn exit_time(&self, lo: f64, hi: f64) -> f64 {
+        let outside = |time: f64| {
+            let v = self.get_value(time);
+            v < lo || hi < v
+        };
+        let points = &self.speed.points;
+        let mut times: Vec<f64> = points.iter().map(|p| p.0).filter(|t| *t > 0.0).collect();
+        times.insert(0, 0.0);
+        // Within a segment the source time is a polynomial of degree 4 at most, so it's bracketed by
+        // stepping and refined by bisection.
+        for w in times.windows(2) {
+            let steps = 64;
+            for i in 0..steps {
+                let a = w[0] + (w[1] - w[0]) * i as f64 / steps as f64;
+                let b = w[0] + (w[1] - w[0]) * (i + 1) as f64 / steps as f64;
+                if outside(b) {
+                    return bisect(a, b, outside);
+                }
+            }
+        }
+        // Past the last point the speed is constant.
+        let last = *times.last().unwrap();
+        let (value, speed) = (self.get_value(last), points.last().unwrap().1);
+        if speed > 0.0 && hi.is_finite() {
+            last + (hi - value) / speed
+        } else if speed < 0.0 && lo.is_finite() {
+            last + (lo - value) / speed
+        } else {
+            f64::INFINITY
+        }
+    }
+}
+
+fn bisect(mut a: f64, mut b: f64, outside: impl Fn(f64) -> bool) -> f64 {
+    for _ in 0..60 {
+        let m = (a + b) / 2.0;
+        if outside(m) {
+            b = m;
+        } else {
+            a = m;
+        }
+    }
+    a
+}
+
+// The speed in the segment between `left` and `right`, as in `Path::get_value`.
+fn segment_speed(left: &(f64, f64, Point<f64>), right: &(f64, f64, Point<f64>), time: f64) -> f64 {
+    let v = if right.0 > left.0 { (time - left.0) / (right.0 - left.0) } else { 0.0 };
+    let iv = 1.0 - v;
+    match right.2 {
+        Point::Constant => left.1,
+        Point::Linear => left.1 * iv + right.1 * v,
+        Point::Bezier2(handle) => left.1 * iv.powi(2) + handle * (v * iv * 2.0) + right.1 * v.powi(2),
+        Point::Bezier3(handle_1, handle_2) =>
+            left.1 * iv.powi(3) + handle_1 * (3.0 * v * iv.powi(2)) + handle_2 * (3.0 * v.powi(2) * iv) + right.1 * v.powi(3)
+    }
+}
+
+// Simpson's rule, which is exact because the speed is at most cubic in a segment.
+fn segment_integral(left: &(f64, f64, Point<f64>), right: &(f64, f64, Point<f64>), a: f64, b: f64) -> f64 {
+    (b - a) / 6.0 * (segment_speed(left, right, a) + 4.0 * segment_speed(left, right, (a + b) / 2.0) + segment_speed(left, right, b))
+}
+
+impl Timed<f64> for SpeedRamp {
+    fn get_value(&self, time: f64) -> f64 {
+        let points = &self.speed.points;
+        if time < points[0].0 {
+            return self.start + (time - points[0].0) * points[0].1;
+        }
+        for (i, w) in points.windows(2).enumerate() {
+            if w[0].0 <= time && time < w[1].0 {
+                return self.source_times[i] + segment_integral(&w[0], &w[1], w[0].0, time);
+            }
+        }
+        let last = points.last().unwrap();
+        self.source_times.last().unwrap() + (time - last.0) * last.1
+    }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("speed_ramp");
+        hasher.write_u64(self.speed.structural_hash()?);
+        hasher.write_f64(self.start);
+        Some(hasher.finish())
+    }
+}
+
+#[test]
+fn test() {
+    use crate::pixel::Rgba;
+    use crate::renders::{plain::Plain, clip::Clip};
+    use crate::framerate::Framerate;
+
+    let source = || Clip::new(Plain::new(Rgba(1.0, 1.0, 1.0, 1.0)), 0.0, 2.0);
+    assert_eq!(TimeRemap::speed(source(), 2.0).unwrap().duration(), 1.0);
+    assert_eq!(TimeRemap::speed(source(), -0.5).unwrap().duration(), 4.0);
+    assert!(TimeRemap::reverse(Plain::new(Rgba(1.0, 1.0, 1.0, 1.0))).is_err());
+    assert_eq!(TimeRemap::freeze(source(), 1.0, 3.0).duration(), 3.0);
+
+    let reverse = TimeRemap::reverse(source()).unwrap();
+    assert_eq!(reverse.duration(), 2.0);
+    assert_eq!(reverse.map.get_value(0.5), 1.5);
+
+    // Speeds up linearly from 1x to 3x over a second, then continues at 3x.
+    let ramp = SpeedRamp::new(Path::new(1.0).append(1.0, 3.0, Point::Linear), 0.0);
+    assert_eq!(ramp.get_value(1.0), 2.0);
+    assert_eq!(ramp.get_value(2.0), 5.0);
+    assert!((ramp.exit_time(0.0, 2.0) - 1.0).abs() < 1e-9);
+
+    // Output frames of a reversed render pick source frames from the end.
+    let ro = RenderOpt {
+        x_range: 0..1,
+        y_range: 0..1,
+        res_x: 1,
+        res_y: 1,
+        frame_range: 0..4,
+        framerate: Framerate::from(2),
+        pixel_center: false
+    };
+    let counter = crate::renders::functional_render::FunctionalRender(Box::new(|_ro: &RenderOpt, time: f64, buffer: &mut [f64]| {
+        buffer[0] = time;
+    }));
+    let reverse = TimeRemap::new(counter, SpeedRamp::new(Path::new(-1.0), 2.0), 2.0);
+    let mut buffer = vec![0.0; 4];
+    reverse.render(&ro, &mut buffer);
+    assert_eq!(buffer, vec![1.5, 1.0, 0.5, 0.0]);
+}
@@ -1,7 +1,7 @@
 use crate::render::{Res, Render};
 use crate::util::noise;
 
-pub type TransformFn = dyn Fn(f64, f64, f64, Res) -> (f64, f64, f64);
+pub type TransformFn = dyn Fn(f64, f64, f64, Res) -> (f64, f64, f64) + Send + Sync;
 
 pub struct Transform<T, R: Render<T>> {
     pub render: R,
@@ -15,7 +15,7 @@ impl<T, R: Render<T>> Transform<T, R> {
     }
 }
 
-impl<T, R: Render<T>> Render<T> for Transform<T, R> {
+impl<T: Send + Sync, R: Render<T>> Render<T> for Transform<T, R> {
     fn sample(&self, u: f64, v: f64, time: f64, res: Res) -> T {
         let (u, v, time) = (self.transformer)(u, v, time, res);
         self.render.sample(u, v, time, res)
@@ -86,6 +86,131 @@ impl Mat {
     }
 }
 
+// A 3x3 projective matrix in row-major order. Unlike `Mat` it holds the forward mapping, so
+// `Mat3::new().translate(10.0, 0.0)` moves the content 10 pixels to the right; each method applies
+// its transformation after `self`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Mat3(pub [f64; 9]);
+
+impl Mat3 {
+    pub fn new() -> Mat3 {
+        Mat3([1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0])
+    }
+
+    pub fn translate(&self, x: f64, y: f64) -> Mat3 {
+        Mat3([1.0, 0.0, x, 0.0, 1.0, y, 0.0, 0.0, 1.0]) * *self
+    }
+
+    pub fn scale(&self, x: f64, y: f64) -> Mat3 {
+        Mat3([x, 0.0, 0.0, 0.0, y, 0.0, 0.0, 0.0, 1.0]) * *self
+    }
+
+    pub fn rotate(&self, rad: f64) -> Mat3 {
+        let (sin, cos) = rad.sin_cos();
+        Mat3([cos, -sin, 0.0, sin, cos, 0.0, 0.0, 0.0, 1.0]) * *self
+    }
+
+    // Shears by the angles `x` along the x axis and `y` along the y axis.
+    pub fn skew(&self, x: f64, y: f64) -> Mat3 {
+        Mat3([1.0, x.tan(), 0.0, y.tan(), 1.0, 0.0, 0.0, 0.0, 1.0]) * *self
+    }
+
+    pub fn determinant(&self) -> f64 {
+        let m = &self.0;
+        m[0] * (m[4] * m[8] - m[5] * m[7])
+            - m[1] * (m[3] * m[8] - m[5] * m[6])
+            + m[2] * (m[3] * m[7] - m[4] * m[6])
+    }
+
+    pub fn inverse(&self) -> Option<Mat3> {
+        let det = self.determinant();
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let m = &self.0;
+        Some(Mat3([
+            (m[4] * m[8] - m[5] * m[7]) / det,
+            (m[2] * m[7] - m[1] * m[8]) / det,
+            (m[1] * m[5] - m[2] * m[4]) / det,
+            (m[5] * m[6] - m[3] * m[8]) / det,
+            (m[0] * m[8] - m[2] * m[6]) / det,
+            (m[2] * m[3] - m[0] * m[5]) / det,
+            (m[3] * m[7] - m[4] * m[6]) / det,
+            (m[1] * m[6] - m[0] * m[7]) / det,
+            (m[0] * m[4] - m[1] * m[3]) / det
+        ]))
+    }
+
+    // Maps the unit square onto `quad`, given clockwise from the corner at (0, 0).
+    pub fn square_to_quad(quad: [(f64, f64); 4]) -> Mat3 {
+        let [(x0, y0), (x1, y1), (x2, y2), (x3, y3)] = quad;
+        let (sx, sy) = (x0 - x1 + x2 - x3, y0 - y1 + y2 - y3);
+        let (g, h) = if sx == 0.0 && sy == 0.0 {
+            (0.0, 0.0)
+        } else {
+            let (dx1, dx2, dy1, dy2) = (x1 - x2, x3 - x2, y1 - y2, y3 - y2);
+            let det = dx1 * dy2 - dx2 * dy1;
+            ((sx * dy2 - dx2 * sy) / det, (dx1 * sy - sx * dy1) / det)
+        };
+        Mat3([
+            x1 - x0 + g * x1, x3 - x0 + h * x3, x0,
+            y1 - y0 + g * y1, y3 - y0 + h * y3, y0,
+            g, h, 1.0
+        ])
+    }
+
+    pub fn quad_to_quad(from: [(f64, f64); 4], to: [(f64, f64); 4]) -> Option<Mat3> {
+        Some(Mat3::square_to_quad(to) * Mat3::square_to_quad(from).inverse()?)
+    }
+
+    // Returns None for points mapped to or behind the horizon.
+    pub fn apply(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let m = &self.0;
+        let w = x * m[6] + y * m[7] + m[8];
+        if w <= 1e-12 {
+            return None;
+        }
+        Some(((x * m[0] + y * m[1] + m[2]) / w, (x * m[3] + y * m[4] + m[5]) / w))
+    }
+
+    // Samples through the inverse, in pixels. Points without a preimage are sent out of the frame.
+    pub fn get_transformer(&self) -> Box<TransformFn> {
+        let inv = self.inverse().unwrap_or(Mat3([0.0, 0.0, -1.0, 0.0, 0.0, -1.0, 0.0, 0.0, 1.0]));
+        Box::new(move |u, v, time, res| {
+            let (x, y) = inv.apply(u * res.0 as f64, v * res.1 as f64).unwrap_or((-1.0, -1.0));
+            (x / res.0 as f64, y / res.1 as f64, time)
+        })
+    }
+}
+
+impl Default for Mat3 {
+    fn default() -> Self {
+        Mat3::new()
+    }
+}
+
+impl std::ops::Mul for Mat3 {
+    type Output = Mat3;
+
+    fn mul(self, rhs: Mat3) -> Mat3 {
+        let (a, b) = (&self.0, &rhs.0);
+        let mut m = [0.0; 9];
+        for r in 0..3 {
+            for c in 0..3 {
+                m[r * 3 + c] = a[r * 3] * b[c] + a[r * 3 + 1] * b[3 + c] + a[r * 3 + 2] * b[6 + c];
+            }
+        }
+        Mat3(m)
+    }
+}
+
+impl From<Mat> for Mat3 {
+    // `Mat` holds the inverse mapping.
+    fn from(mat: Mat) -> Mat3 {
+        Mat3([mat.0, mat.1, mat.2, mat.3, mat.4, mat.5, 0.0, 0.0, 1.0]).inverse().unwrap_or_default()
+    }
+}
+
 use crate::{path::{Path, Timed}, v::Vec2};
 pub fn path_to_transformer(
     translation_path: Path<Vec2<f64>>,
@@ -130,3 +255,17 @@ pub fn timed_to_transformer<T: Timed<Vec2<f64>> + 'static, S: Timed<Vec2<f64>> +
         )
     })
 }
+
+#[test]
+fn test() {
+    let m = Mat3::new().scale(2.0, 3.0).skew(0.3, 0.0).rotate(0.5).translate(4.0, -1.0);
+    let (x, y) = (m.inverse().unwrap() * m).apply(5.0, 7.0).unwrap();
+    assert!((x - 5.0).abs() < 1e-9 && (y - 7.0).abs() < 1e-9);
+    assert_eq!(Mat3::new().translate(3.0, 0.0).scale(2.0, 2.0).apply(1.0, 1.0), Some((8.0, 2.0)));
+    let quad = [(1.0, 2.0), (9.0, 1.0), (8.0, 7.0), (2.0, 6.0)];
+    let h = Mat3::square_to_quad(quad);
+    for (p, q) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].iter().zip(quad.iter()) {
+        let (x, y) = h.apply(p.0, p.1).unwrap();
+        assert!((x - q.0).abs() < 1e-9 && (y - q.1).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,256 @@
+use std::hash::Hasher;
+use std::sync::Arc;
+use crate::render::{Res, RenderOpt};
+use crate::lerp::Lerp;
+use crate::path::{Path, Point};
+use crate::timed::Timed;
+use crate::hash::{StructuralHash, StructuralHasher};
+use crate::error::KanteraError;
+use crate::util::noise;
+
+// The direction in which the incoming render moves.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down
+}
+
+impl Direction {
+    fn vector(&self) -> (f64, f64) {
+        match self {
+            Direction::Left => (-1.0, 0.0),
+            Direction::Right => (1.0, 0.0),
+            Direction::Up => (0.0, -1.0),
+            Direction::Down => (0.0, 1.0)
+        }
+    }
+}
+
+pub type FrameRender<'a, T> = dyn FnMut(&RenderOpt, &mut [T]) -> Result<(), KanteraError> + 'a;
+
+// Softnesses are fractions of the whole transition, 0.0 gives a hard edge.
+#[derive(Debug, Copy, Clone)]
+pub enum TransitionType<T: Copy> {
+    Crossfade,
+    Dip(T),
+    Wipe(Direction, f64),
+    Slide(Direction),
+    Push(Direction),
+    Iris(f64),
+    Dissolve(f64, f64) // noise scale and softness
+}
+
+#[derive(Clone)]
+pub struct Transition<T: Copy> {
+    pub transition_type: TransitionType<T>,
+    pub duration: f64,
+    pub easing: Arc<dyn Timed<f64>> // maps linear progress in [0, 1] to eased progress
+}
+
+fn ramp(progress: f64, s: f64, softness: f64) -> f64 {
+    if softness <= 0.0 {
+        if s < progress { 1.0 } else { 0.0 }
+    } else {
+        ((progress * (1.0 + softness) - s) / softness).clamp(0.0, 1.0)
+    }
+}
+
+impl<T: Lerp + Default> Transition<T> {
+    pub fn new(transition_type: TransitionType<T>, duration: f64) -> Self {
+        Transition {
+            transition_type,
+            duration,
+            easing: Arc::new(Path::new(0.0).append(1.0, 1.0, Point::Linear))
+        }
+    }
+
+    pub fn easing(mut self, easing: Arc<dyn Timed<f64>>) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    // Eased progress at `time` seconds after the transition started.
+    pub fn progress(&self, time: f64) -> f64 {
+        let linear = if self.duration > 0.0 { (time / self.duration).clamp(0.0, 1.0) } else { 1.0 };
+        self.easing.get_value(linear)
+    }
+
+    // Offsets of the outgoing and incoming renders in uv.
+    fn offsets(&self, progress: f64) -> ((f64, f64), (f64, f64)) {
+        match self.transition_type {
+            TransitionType::Slide(direction) => {
+                let (dx, dy) = direction.vector();
+                ((0.0, 0.0), (-dx * (1.0 - progress), -dy * (1.0 - progress)))
+            }
+            TransitionType::Push(direction) => {
+                let (dx, dy) = direction.vector();
+                ((dx * progress, dy * progress), (-dx * (1.0 - progress), -dy * (1.0 - progress)))
+            }
+            _ => ((0.0, 0.0), (0.0, 0.0))
+        }
+    }
+
+    // How much of the incoming render is shown at (u, v).
+    fn mask(&self, progress: f64, u: f64, v: f64, res: Res, b_offset: (f64, f64)) -> f64 {
+        match self.transition_type {
+            TransitionType::Crossfade | TransitionType::Dip(_) => progress,
+            TransitionType::Wipe(direction, softness) => {
+                let s = match direction {
+                    Direction::Left => 1.0 - u,
+                    Direction::Right => u,
+                    Direction::Up => 1.0 - v,
+                    Direction::Down => v
+                };
+                ramp(progress, s, softness)
+            }
+            TransitionType::Slide(_) | TransitionType::Push(_) => {
+                let (u, v) = (u - b_offset.0, v - b_offset.1);
+                if (0.0..1.0).contains(&u) && (0.0..1.0).contains(&v) { 1.0 } else { 0.0 }
+            }
+            TransitionType::Iris(softness) => {
+                // Distance from the centre in pixels, normalized so that the corners are at 1.
+                let (x, y) = ((u - 0.5) * res.0 as f64, (v - 0.5) * res.1 as f64);
+                let d = (x * x + y * y).sqrt() / ((res.0 * res.0 + res.1 * res.1) as f64).sqrt() * 2.0;
+                ramp(progress, d, softness)
+            }
+            TransitionType::Dissolve(scale, softness) => {
+                let n = (noise(u * scale, v * scale * res.1 as f64 / res.0 as f64, 0.5) + 0.5).clamp(0.0, 1.0);
+                ramp(progress, n, softness)
+            }
+        }
+    }
+
+    fn mix(&self, progress: f64, a: T, b: T, mask: f64) -> T {
+        match self.transition_type {
+            TransitionType::Dip(color) =>
+                if progress < 0.5 {
+                    a.lerp(&color, progress * 2.0)
+                } else {
+                    color.lerp(&b, progress * 2.0 - 1.0)
+                },
+            _ => a.lerp(&b, mask)
+        }
+    }
+
+    pub fn sample(&self, progress: f64, u: f64, v: f64, res: Res, a: impl Fn(f64, f64) -> T, b: impl Fn(f64, f64) -> T) -> T {
+        let (a_offset, b_offset) = self.offsets(progress);
+        let mask = self.mask(progress, u, v, res, b_offset);
+        self.mix(progress, a(u - a_offset.0, v - a_offset.1), b(u - b_offset.0, v - b_offset.1), mask)
+    }
+
+    // Renders a single frame, `ro.frame_range` must be one frame long. The renders are called with
+    // shifted `x_range` and `y_range` for slides and pushes.
+    pub fn try_render_frame(
+        &self,
+        progress: f64,
+        ro: &RenderOpt,
+        render_a: &mut FrameRender<T>,
+        render_b: &mut FrameRender<T>,
+        buffer: &mut [T]) -> Result<(), KanteraError> {
+        let RenderOpt {x_range, y_range, res_x, res_y, ..} = ro;
+        let x_size = (x_range.end - x_range.start) as usize;
+        let (a_offset, b_offset) = self.offsets(progress);
+        // Shift by whole pixels so that nothing needs to be resampled.
+        let to_pixels = |(u, v): (f64, f64)| ((u * *res_x as f64).round() as i32, (v * *res_y as f64).round() as i32);
+        let (a_offset, b_offset) = (to_pixels(a_offset), to_pixels(b_offset));
+        let mut buffer_a = vec![T::default(); buffer.len()];
+        let mut buffer_b = vec![T::default(); buffer.len()];
+        render_a(&RenderOpt {
+            x_range: x_range.start - a_offset.0..x_range.end - a_offset.0,
+            y_range: y_range.start - a_offset.1..y_range.end - a_offset.1,
+            ..ro.clone()
+        }, &mut buffer_a)?;
+        render_b(&RenderOpt {
+            x_range: x_range.start - b_offset.0..x_range.end - b_offset.0,
+            y_range: y_range.start - b_offset.1..y_range.end - b_offset.1,
+            ..ro.clone()
+        }, &mut buffer_b)?;
+        let b_offset = (b_offset.0 as f64 / *res_x as f64, b_offset.1 as f64 / *res_y as f64);
+        for y in y_range.clone() {
+            for x in x_range.clone() {
+                let i = (y - y_range.start) as usize * x_size + (x - x_range.start) as usize;
+                let (u, v) = ((x as f64 + 0.5) / *res_x as f64, (y as f64 + 0.5) / *res_y as f64);
+                let mask = self.mask(progress, u, v, (*res_x, *res_y), b_offset);
+                buffer[i] = self.mix(progress, buffer_a[i], buffer_b[i], mask);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Lerp + Default + StructuralHash> Transition<T> {
+    pub fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("transition");
+        match self.transition_type {
+            TransitionType::Crossfade => hasher.write_u8(0),
+            TransitionType::Dip(color) => {
+                hasher.write_u8(1);
+                hasher.write_u64(color.hash_value()?);
+            }
+            TransitionType::Wipe(direction, softness) => {
+                hasher.write_u8(2);
+                hasher.write_u8(direction as u8);
+                hasher.write_f64(softness);
+            }
+            TransitionType::Slide(direction) => {
+                hasher.write_u8(3);
+                hasher.write_u8(direction as u8);
+            }
+            TransitionType::Push(direction) => {
+                hasher.write_u8(4);
+                hasher.write_u8(direction as u8);
+            }
+            TransitionType::Iris(softness) => {
+                hasher.write_u8(5);
+                hasher.write_f64(softness);
+            }
+            TransitionType::Dissolve(scale, softness) => {
+                hasher.write_u8(6);
+                hasher.write_f64(scale);
+                hasher.write_f64(softness);
+            }
+        }
+        hasher.write_f64(self.duration);
+        hasher.write_u64(self.easing.structural_hash()?);
+        Some(hasher.finish())
+    }
+}
+
+#[test]
+fn test() {
+    use crate::pixel::Rgba;
+    use crate::render::Render;
+    use crate::renders::{plain::Plain, sequence::Sequence};
+    use crate::framerate::Framerate;
+
+    let red = Rgba(1.0, 0.0, 0.0, 1.0);
+    let blue = Rgba(0.0, 0.0, 1.0, 1.0);
+    let ro = RenderOpt {
+        x_range: 0..4,
+        y_range: 0..1,
+        res_x: 4,
+        res_y: 1,
+        frame_range: 0..4,
+        framerate: Framerate::from(2),
+        pixel_center: true
+    };
+
+    let sequence = Sequence::new()
+        .append(0.0, true, Plain::new(red))
+        .append_with_transition(0.5, true, Plain::new(blue), Transition::new(TransitionType::Crossfade, 1.0));
+    let mut buffer = vec![Rgba::default(); 4 * 4];
+    sequence.render(&ro, &mut buffer);
+    assert_eq!(buffer[4], red);
+    assert_eq!(buffer[8], Rgba(0.5, 0.0, 0.5, 1.0));
+    assert_eq!(buffer[12], blue);
+    assert_eq!(sequence.sample(0.5, 0.5, 1.0, (4, 1)), Rgba(0.5, 0.0, 0.5, 1.0));
+
+    // Halfway through a push to the right, the left half shows the incoming render.
+    let sequence = Sequence::new()
+        .append(0.0, true, Plain::new(red))
+        .append_with_transition(0.5, true, Plain::new(blue), Transition::new(TransitionType::Push(Direction::Right), 1.0));
+    sequence.render(&ro, &mut buffer);
+    assert_eq!(&buffer[8..12], &[blue, blue, red, red]);
+}
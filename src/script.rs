@@ -1,4 +1,5 @@
 use std::rc::Rc;
+use std::sync::Arc;
 use std::cell::RefCell;
 use std::fmt::Write;
 use std::iter::Peekable;
@@ -24,7 +25,11 @@ use crate::{
     path::{Path, Point},
     timed::Timed,
     lerp::Lerp,
+    hash::StructuralHash,
     v::{Vec2, Vec3},
+    renders::transition::Transition,
+    renders::composite::CompositeMode,
+    blend::BlendMode,
     interpolation
 };
 
@@ -265,34 +270,346 @@ fn init_runtime(rt: &mut Runtime) {
     rt.insert("plain", r(Box::new(|vec: Vec<Val>| {
         let first = vec.get_(0)?;
         if let Some(p) = first.ref_as::<Rgba>().copied() {
-            Ok(r(Rc::new(crate::renders::plain::Plain::new(p)) as Rc<dyn Render<Rgba>>))
+            Ok(r(Arc::new(crate::renders::plain::Plain::new(p)) as Arc<dyn Render<Rgba>>))
         } else if let Some(p) = clone_timed(first) {
-            Ok(r(Rc::new(crate::renders::plain::Plain::new(p.clone())) as Rc<dyn Render<Rgba>>))
+            Ok(r(Arc::new(crate::renders::plain::Plain::new(p.clone())) as Arc<dyn Render<Rgba>>))
         } else {
             Err(GlutenError::Str("arguments mismatch".to_owned()))
         }
     }) as NativeFn));
     rt.insert("clip", r(Box::new(|vec: Vec<Val>| {
-        let render = vec.get_(0)?.ref_as::<Rc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
         let start = vec.get_(1)?.ref_as::<f64>().copied().ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
         let end = vec.get_(2)?.ref_as::<f64>().copied().ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
-        Ok(r(Rc::new(crate::renders::clip::Clip::new(render, start, end)) as Rc<dyn Render<Rgba>>))
+        Ok(r(Arc::new(crate::renders::clip::Clip::new(render, start, end)) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("cache", r(Box::new(|vec: Vec<Val>| {
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let megabytes = match vec.get(1) {
+            Some(v) => v.ref_as::<i32>().copied().ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?,
+            None => 512
+        };
+        Ok(r(Arc::new(crate::renders::cache::Cache::new(render, megabytes.max(0) as usize * 1024 * 1024)) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("disk_cache", r(Box::new(|vec: Vec<Val>| {
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let dir = match vec.get(1) {
+            Some(v) => std::path::PathBuf::from(v.ref_as::<String>().ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?),
+            None => std::env::temp_dir().join("kantera_cache")
+        };
+        Ok(r(Arc::new(crate::renders::disk_cache::DiskCache::new(render, dir)) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("motion_blur", r(Box::new(|vec: Vec<Val>| {
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let shutter_angle = vec.get_(1)?.ref_as::<f64>().copied().ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
+        let samples = vec.get_(2)?.ref_as::<i32>().copied().ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
+        Ok(r(Arc::new(crate::renders::motion_blur::MotionBlur::new(render, shutter_angle, samples.max(1) as usize)) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("antialias", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::antialias::{Antialias, AntialiasPattern};
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let pattern = match vec.get_(1)?.ref_as::<Symbol>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?.0.as_str() {
+            "grid" => AntialiasPattern::Grid,
+            "rotated_grid" => AntialiasPattern::RotatedGrid,
+            "jittered" => AntialiasPattern::Jittered,
+            _ => { return Err(GlutenError::Str("invalid pattern".to_owned())) }
+        };
+        let samples = vec.get_(2)?.ref_as::<i32>().copied().ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
+        Ok(r(Arc::new(Antialias::new(render, pattern, samples.max(1) as usize)) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("time_remap", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::time_remap::TimeRemap;
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let map = clone_timed::<f64>(vec.get_(1)?).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
+        let duration = vec.get_(2)?.ref_as::<f64>().copied().ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
+        Ok(r(Arc::new(TimeRemap::new(render, map, duration)) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("speed", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::time_remap::TimeRemap;
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let speed = if let Some(speed) = vec.get_(1)?.ref_as::<f64>() {
+            Path::new(*speed)
+        } else {
+            vec.get_(1)?.ref_as::<Arc<Path<f64>>>().map(|path| path.as_ref().clone()).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?
+        };
+        let remap = TimeRemap::speed_ramp(render, speed).map_err(|e| GlutenError::Str(e.to_string()))?;
+        Ok(r(Arc::new(remap) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("reverse", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::time_remap::TimeRemap;
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let remap = TimeRemap::reverse(render).map_err(|e| GlutenError::Str(e.to_string()))?;
+        Ok(r(Arc::new(remap) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("freeze", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::time_remap::TimeRemap;
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let time = vec.get_(1)?.ref_as::<f64>().copied().ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
+        let duration = vec.get_(2)?.ref_as::<f64>().copied().ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
+        Ok(r(Arc::new(TimeRemap::freeze(render, time, duration)) as Arc<dyn Render<Rgba>>))
     }) as NativeFn));
     rt.insert("frame", r(Box::new(|vec: Vec<Val>| {
-        use crate::renders::frame::{Frame, FrameType};
-        let render = vec.get_(0)?.ref_as::<Rc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
-        let frame_type = match vec.get_(1)?.ref_as::<Symbol>().unwrap().0.as_str() {
-            "constant" => FrameType::Constant(vec.get_(2)?.ref_as::<Rgba>().copied().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?),
-            "extend" => FrameType::Extend,
-            "repeat" => FrameType::Repeat,
-            "reflect" => FrameType::Reflect,
-            _ => { return Err(GlutenError::Str(format!("invalid frame_type"))) }
+        use crate::renders::frame::Frame;
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let frame_type = frame_type(&vec[1..])?;
+        Ok(r(Arc::new(Frame {render, frame_type}) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("blur", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::blur::{Blur, BlurType, BlurDirection};
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let blur_type = match vec.get_(1)?.ref_as::<Symbol>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?.0.as_str() {
+            "gaussian" => BlurType::Gaussian,
+            "box" => BlurType::Box,
+            _ => { return Err(GlutenError::Str("invalid blur type".to_owned())) }
+        };
+        let radius = clone_timed::<f64>(vec.get_(2)?).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
+        let mut blur = Blur::new(render, blur_type, radius);
+        // `(blur render type radius ['both|'horizontal|'vertical|angle] [frame_type|'none])`
+        let mut rest = &vec[3..];
+        if let Some(val) = rest.first() {
+            let direction = match val.ref_as::<Symbol>().map(|s| s.0.as_str()) {
+                Some("both") => Some(BlurDirection::Both),
+                Some("horizontal") => Some(BlurDirection::Horizontal),
+                Some("vertical") => Some(BlurDirection::Vertical),
+                Some(_) => None,
+                None => Some(BlurDirection::Angle(clone_timed::<f64>(val).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?))
+            };
+            if let Some(direction) = direction {
+                blur.direction = direction;
+                rest = &rest[1..];
+            }
+        }
+        if !rest.is_empty() {
+            blur.frame_type = match rest[0].ref_as::<Symbol>() {
+                Some(s) if s.0.as_str() == "none" => None,
+                _ => Some(frame_type(rest)?)
+            };
+        }
+        Ok(r(Arc::new(blur) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("lens_blur", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::lens_blur::{LensBlur, Aperture};
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        // The aperture is 'disc, 'hexagon or an image whose luma is the kernel.
+        let aperture = if let Some(image) = vec.get_(1)?.ref_as::<Arc<Image<Rgba>>>() {
+            Aperture::Custom(Arc::new(image.map(|p| p.premultiply().luma())))
+        } else {
+            match vec.get_(1)?.ref_as::<Symbol>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?.0.as_str() {
+                "disc" => Aperture::Disc,
+                "hexagon" => Aperture::Hexagon,
+                _ => { return Err(GlutenError::Str("invalid aperture".to_owned())) }
+            }
         };
-        Ok(r(Rc::new(Frame {render, frame_type}) as Rc<dyn Render<Rgba>>))
+        let timed = |i: usize| clone_timed::<f64>(vec.get_(i)?).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()));
+        let mut lens_blur = LensBlur::new(render, aperture, timed(2)?);
+        // `(lens_blur render aperture radius [highlight_threshold highlight_gain [depth [focus]]])`
+        if vec.len() > 3 {
+            lens_blur.highlight_threshold = timed(3)?;
+            lens_blur.highlight_gain = timed(4)?;
+        }
+        if vec.len() > 5 {
+            lens_blur.depth = Some(vec.get_(5)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?);
+        }
+        if vec.len() > 6 {
+            lens_blur.focus = Some(timed(6)?);
+        }
+        Ok(r(Arc::new(lens_blur) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("glow", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::glow::{Glow, MAX_LAYERS};
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let timed = |i: usize| clone_timed::<f64>(vec.get_(i)?).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()));
+        // `(glow render threshold radius intensity [tint [layers]])`
+        let mut glow = Glow::new(render, timed(1)?, timed(2)?, timed(3)?);
+        if let Some(val) = vec.get(4) {
+            glow.tint = clone_timed::<Rgba>(val).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
+        }
+        if let Some(val) = vec.get(5) {
+            glow.layers = val.ref_as::<i32>().copied().ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?.clamp(1, MAX_LAYERS as i32) as usize;
+        }
+        Ok(r(Arc::new(glow) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("convolve", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::{convolution::Convolve, filter::check_kernel};
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        // The kernel is given as rows, e.g. `(vec (vec 0.0 -1.0 0.0) (vec -1.0 5.0 -1.0) (vec 0.0 -1.0 0.0))`.
+        let rows = vec.get_(1)?.ref_as::<Vec<Val>>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let mut kernel = Image {width: 0, height: rows.len(), vec: Vec::new()};
+        for row in rows.iter() {
+            let row = row.ref_as::<Vec<Val>>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+            kernel.width = row.len();
+            for v in row.iter() {
+                kernel.vec.push(v.ref_as::<f64>().copied().ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?);
+            }
+        }
+        check_kernel(&kernel).map_err(|e| GlutenError::Str(e.to_string()))?;
+        Ok(r(Arc::new(Convolve {render, kernel}) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("sharpen", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::{convolution::Convolve, filter::make_sharpen_kernel};
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let amount = vec.get_(1)?.ref_as::<f64>().copied().ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
+        Ok(r(Arc::new(Convolve {render, kernel: make_sharpen_kernel(amount)}) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("emboss", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::{convolution::Convolve, filter::make_emboss_kernel};
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let angle = vec.get_(1)?.ref_as::<f64>().copied().ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
+        let strength = vec.get_(2)?.ref_as::<f64>().copied().ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
+        Ok(r(Arc::new(Convolve {render, kernel: make_emboss_kernel(angle, strength)}) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("edge_detect", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::convolution::{EdgeDetect, EdgeOperator};
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let operator = match vec.get(1).map(|val| val.ref_as::<Symbol>().map(|s| s.0.as_str())) {
+            None | Some(Some("sobel")) => EdgeOperator::Sobel,
+            Some(Some("laplacian")) => EdgeOperator::Laplacian,
+            _ => { return Err(GlutenError::Str("invalid operator".to_owned())) }
+        };
+        Ok(r(Arc::new(EdgeDetect {render, operator}) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("unsharp_mask", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::convolution::UnsharpMask;
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let timed = |i: usize| clone_timed::<f64>(vec.get_(i)?).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()));
+        let threshold = if vec.len() > 3 { timed(3)? } else { Arc::new(0.0) };
+        Ok(r(Arc::new(UnsharpMask {render, amount: timed(1)?, radius: timed(2)?, threshold}) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("displace", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::displacement::{Displacement, Channel};
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let map = vec.get_(1)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let timed = |i: usize| clone_timed::<f64>(vec.get_(i)?).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()));
+        let channel = |i: usize| {
+            let name = vec.get_(i)?.ref_as::<Symbol>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?.0.as_str();
+            Channel::from_name(name).ok_or_else(|| GlutenError::Str(format!("invalid channel: {}", name)))
+        };
+        // `(displace render map scale_x scale_y [x_channel y_channel])`
+        let mut displacement = Displacement::new(render, map, timed(2)?);
+        displacement.scale_y = timed(3)?;
+        if vec.len() > 4 {
+            displacement.x_channel = channel(4)?;
+            displacement.y_channel = channel(5)?;
+        }
+        Ok(r(Arc::new(displacement) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("corner_pin", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::corner_pin::CornerPin;
+        // `(corner_pin render top_left top_right bottom_right bottom_left)`
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let corner = |i: usize| timed_vec2(vec.get_(i)?).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()));
+        Ok(r(Arc::new(CornerPin::new(render, [corner(1)?, corner(2)?, corner(3)?, corner(4)?])) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("precomp", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::{precomp::Precomp, image_render::Sizing};
+        use crate::framerate::Framerate;
+        // `(precomp render width height framerate [sizing [time_offset]])`, where framerate is a number or a string like "30000/1001"
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let f64_ = |i: usize| vec.get_(i)?.ref_as::<f64>().copied().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()));
+        let framerate = if let Some(s) = vec.get_(3)?.ref_as::<String>() {
+            s.parse::<Framerate>().map_err(GlutenError::Str)?
+        } else {
+            f64_(3)?.to_string().parse::<Framerate>().map_err(GlutenError::Str)?
+        };
+        let mut precomp = Precomp::new(render, f64_(1)? as usize, f64_(2)? as usize, framerate, Rgba(0.0, 0.0, 0.0, 0.0), interpolation::Bilinear);
+        if vec.len() > 4 {
+            let name = vec.get_(4)?.ref_as::<Symbol>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?.0.as_str();
+            precomp.sizing = Sizing::from_name(name).ok_or_else(|| GlutenError::Str(format!("invalid sizing: {}", name)))?;
+        }
+        if vec.len() > 5 {
+            precomp.time_offset = f64_(5)?;
+        }
+        Ok(r(Arc::new(precomp) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("layer_style", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::layer_style::{LayerStyle, Style};
+        // `(layer_style render style ...)` where a style is one of
+        // `(vec 'drop_shadow offset blur color opacity)`, `(vec 'inner_shadow offset blur color opacity)`,
+        // `(vec 'outer_glow radius color opacity)` or `(vec 'stroke width color opacity)`.
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let mut styles = Vec::new();
+        for val in vec[1..].iter() {
+            let s = val.ref_as::<Vec<Val>>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+            let timed = |i: usize| clone_timed::<f64>(s.get_(i)?).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()));
+            let color = |i: usize| clone_timed::<Rgba>(s.get_(i)?).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()));
+            let offset = || timed_vec2(s.get_(1)?).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()));
+            let name = s.get_(0)?.ref_as::<Symbol>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?.0.as_str();
+            styles.push(match name {
+                "drop_shadow" => Style::DropShadow {offset: offset()?, blur: timed(2)?, color: color(3)?, opacity: timed(4)?},
+                "inner_shadow" => Style::InnerShadow {offset: offset()?, blur: timed(2)?, color: color(3)?, opacity: timed(4)?},
+                "outer_glow" => Style::OuterGlow {radius: timed(1)?, color: color(2)?, opacity: timed(3)?},
+                "stroke" => Style::Stroke {width: timed(1)?, color: color(2)?, opacity: timed(3)?},
+                _ => return Err(GlutenError::Str(format!("invalid layer style: {}", name)))
+            });
+        }
+        Ok(r(Arc::new(LayerStyle {render, styles}) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("particles", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::particles::{ParticlesBuilder, Emitter};
+        // `(particles emitter rate lifetime [velocity spread gravity drag turbulence size color alpha sprite])`
+        // where emitter is `(vec 'point position)`, `(vec 'line a b)` or `(vec 'path path)`, in units of the output height.
+        let e = vec.get_(0)?.ref_as::<Vec<Val>>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let vec2 = |val: &Val| timed_vec2(val).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()));
+        let emitter = match e.get_(0)?.ref_as::<Symbol>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?.0.as_str() {
+            "point" => Emitter::Point(vec2(e.get_(1)?)?),
+            "line" => Emitter::Line(vec2(e.get_(1)?)?, vec2(e.get_(2)?)?),
+            "path" => Emitter::Path(e.get_(1)?.ref_as::<Arc<Path<Vec2<f64>>>>().map(|p| p.as_ref().clone()).ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?),
+            name => return Err(GlutenError::Str(format!("invalid emitter: {}", name)))
+        };
+        let timed = |i: usize| clone_timed::<f64>(vec.get_(i)?).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()));
+        let f64_ = |i: usize| vec.get_(i)?.ref_as::<f64>().copied().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()));
+        let mut particles = ParticlesBuilder::new(emitter, timed(1)?, f64_(2)?);
+        let len = vec.len();
+        if len > 3 { particles.velocity = vec2(vec.get_(3)?)?; }
+        if len > 4 { particles.spread = f64_(4)?; }
+        if len > 5 { particles.gravity = vec2(vec.get_(5)?)?.get_value(0.0); }
+        if len > 6 { particles.drag = f64_(6)?; }
+        if len > 7 { particles.turbulence = f64_(7)?; }
+        if len > 8 { particles.size = timed(8)?; }
+        if len > 9 { particles.color = clone_timed::<Rgba>(vec.get_(9)?).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?; }
+        if len > 10 { particles.alpha = timed(10)?; }
+        if len > 11 {
+            particles.sprite = Some(vec.get_(11)?.ref_as::<Arc<Image<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?);
+        }
+        Ok(r(Arc::new(particles.build()) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("shape", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::shape::{Shape, Geometry};
+        // `(shape geometry fill [stroke stroke_width])` where geometry is one of
+        // `(vec 'rect center size [corner_radius])`, `(vec 'ellipse center radii)`,
+        // `(vec 'polygon center radius sides [rotation])`, `(vec 'star center outer inner points [rotation])`,
+        // `(vec 'line from to)` or `(vec 'path path ['open])`, in units of the output height.
+        let g = vec.get_(0)?.ref_as::<Vec<Val>>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let vec2 = |i: usize| timed_vec2(g.get_(i)?).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()));
+        let timed = |i: usize| clone_timed::<f64>(g.get_(i)?).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()));
+        let timed_or = |i: usize, default: f64| if g.len() > i { timed(i) } else { Ok(Arc::new(default) as Arc<dyn Timed<f64>>) };
+        let usize_ = |i: usize| g.get_(i)?.ref_as::<f64>().map(|x| *x as usize).ok_or_else(|| GlutenError::Str("type mismatch".to_owned()));
+        let geometry = match g.get_(0)?.ref_as::<Symbol>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?.0.as_str() {
+            "rect" => Geometry::Rect {center: vec2(1)?, size: vec2(2)?, corner_radius: timed_or(3, 0.0)?},
+            "ellipse" => Geometry::Ellipse {center: vec2(1)?, radii: vec2(2)?},
+            "polygon" => Geometry::Polygon {center: vec2(1)?, radius: timed(2)?, sides: usize_(3)?, rotation: timed_or(4, 0.0)?},
+            "star" => Geometry::Star {
+                center: vec2(1)?, outer_radius: timed(2)?, inner_radius: timed(3)?, points: usize_(4)?, rotation: timed_or(5, 0.0)?
+            },
+            "line" => Geometry::Line {from: vec2(1)?, to: vec2(2)?},
+            "path" => {
+                let path = g.get_(1)?.ref_as::<Arc<Path<Vec2<f64>>>>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+                let open = g.get(2).and_then(|v| v.ref_as::<Symbol>()).is_some_and(|s| s.0.as_str() == "open");
+                Geometry::path(path, !open)
+            }
+            name => return Err(GlutenError::Str(format!("invalid geometry: {}", name)))
+        };
+        let color = |i: usize| clone_timed::<Rgba>(vec.get_(i)?).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()));
+        let mut shape = Shape::new(geometry, color(1)?);
+        if vec.len() > 2 {
+            shape.stroke = color(2)?;
+            shape.stroke_width = clone_timed::<f64>(vec.get_(3)?).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
+        }
+        Ok(r(Arc::new(shape) as Arc<dyn Render<Rgba>>))
     }) as NativeFn));
     rt.insert("color_sample", r(Box::new(|vec: Vec<Val>| {
         use crate::renders::color_sampling::{ColorSampling, ColorSamplingType};
-        let render = vec.get_(0)?.ref_as::<Rc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
         let r#type = match vec.get_(1)?.ref_as::<Symbol>().unwrap().0.as_str() {
             "t444" => ColorSamplingType::T444,
             "t422" => ColorSamplingType::T422,
@@ -300,7 +617,7 @@ fn init_runtime(rt: &mut Runtime) {
             "t411" => ColorSamplingType::T411,
             _ => { return Err(GlutenError::Str(format!("invalid frame_type"))) }
         };
-        Ok(r(Rc::new(ColorSampling {render, r#type}) as Rc<dyn Render<Rgba>>))
+        Ok(r(Arc::new(ColorSampling {render, r#type}) as Arc<dyn Render<Rgba>>))
     }) as NativeFn));
     rt.insert("sequence", r(Box::new(|vec: Vec<Val>| {
         let mut sequence = crate::renders::sequence::Sequence::new();
@@ -308,10 +625,13 @@ fn init_runtime(rt: &mut Runtime) {
             let p = p.ref_as::<Vec<Val>>().unwrap().clone();
             let time = *p[0].ref_as::<f64>().unwrap();
             let restart = *p[1].ref_as::<bool>().unwrap();
-            let render = p[2].ref_as::<Rc<dyn Render<Rgba>>>().unwrap().clone();
-            sequence = sequence.append(time, restart, render);
+            let render = p[2].ref_as::<Arc<dyn Render<Rgba>>>().unwrap().clone();
+            sequence = match p.get(3) {
+                Some(transition) => sequence.append_with_transition(time, restart, render, transition.ref_as::<Arc<Transition<Rgba>>>().unwrap().as_ref().clone()),
+                None => sequence.append(time, restart, render)
+            };
         }
-        r(Rc::new(sequence) as Rc<dyn Render<Rgba>>)
+        r(Arc::new(sequence) as Arc<dyn Render<Rgba>>)
     }) as MyFn));
     rt.insert("sequencer", r(Box::new(|vec: Vec<Val>| {
         let mut sequencer = crate::renders::sequencer::Sequencer::new(Rgba(0.0, 0.0, 0.0, 0.0)); // TODO
@@ -319,49 +639,204 @@ fn init_runtime(rt: &mut Runtime) {
             let p = p.ref_as::<Vec<Val>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
             let time = p.get_(0)?.ref_as::<f64>().copied().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
             let z = p.get_(1)?.ref_as::<i32>().copied().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
-            let render = p.get_(2)?.ref_as::<Rc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
-            sequencer = sequencer.append(time, z as usize, render);
+            let render = p.get_(2)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+            // Followed by an optional transition and an optional composite mode.
+            let mut rest = &p[3.min(p.len())..];
+            let transition = rest.first().and_then(|v| v.ref_as::<Arc<Transition<Rgba>>>()).map(|t| t.as_ref().clone());
+            if transition.is_some() {
+                rest = &rest[1..];
+            }
+            let mode = if rest.is_empty() { CompositeMode::Normal(Arc::new(1.0)) } else { composite_mode(rest)? };
+            sequencer = sequencer.append_clip(time, z as usize, render, transition, mode);
         }
-        Ok(r(Rc::new(sequencer) as Rc<dyn Render<Rgba>>))
+        Ok(r(Arc::new(sequencer) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("transition", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::transition::{TransitionType, Direction};
+        fn direction(val: &Val) -> Result<Direction, GlutenError> {
+            match val.ref_as::<Symbol>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?.0.as_str() {
+                "left" => Ok(Direction::Left),
+                "right" => Ok(Direction::Right),
+                "up" => Ok(Direction::Up),
+                "down" => Ok(Direction::Down),
+                _ => Err(GlutenError::Str("invalid direction".to_owned()))
+            }
+        }
+        let f64_or = |i: usize, default: f64| -> Result<f64, GlutenError> {
+            match vec.get(i) {
+                Some(v) => v.ref_as::<f64>().copied().ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned())),
+                None => Ok(default)
+            }
+        };
+        let duration = vec.get_(1)?.ref_as::<f64>().copied().ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
+        let transition_type = match vec.get_(0)?.ref_as::<Symbol>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?.0.as_str() {
+            "crossfade" => TransitionType::Crossfade,
+            "dip" => TransitionType::Dip(vec.get_(2)?.ref_as::<Rgba>().copied().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?),
+            "wipe" => TransitionType::Wipe(direction(vec.get_(2)?)?, f64_or(3, 0.0)?),
+            "slide" => TransitionType::Slide(direction(vec.get_(2)?)?),
+            "push" => TransitionType::Push(direction(vec.get_(2)?)?),
+            "iris" => TransitionType::Iris(f64_or(2, 0.0)?),
+            "dissolve" => TransitionType::Dissolve(f64_or(2, 16.0)?, f64_or(3, 0.1)?),
+            _ => { return Err(GlutenError::Str("invalid transition_type".to_owned())) }
+        };
+        Ok(r(Arc::new(Transition::new(transition_type, duration))))
+    }) as NativeFn));
+    rt.insert("transition/ease", r(Box::new(|vec: Vec<Val>| {
+        let transition = vec.get_(0)?.ref_as::<Arc<Transition<Rgba>>>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let easing = clone_timed::<f64>(vec.get_(1)?).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
+        Ok(r(Arc::new(transition.as_ref().clone().easing(easing))))
     }) as NativeFn));
     rt.insert("image_render", r(Box::new(|vec: Vec<Val>| {
-        let image = vec[0].ref_as::<Rc<Image<Rgba>>>().unwrap().clone();
+        let image = vec[0].ref_as::<Arc<Image<Rgba>>>().unwrap().clone();
         let default = *vec[1].ref_as::<Rgba>().unwrap();
-        r(Rc::new(crate::renders::image_render::ImageRender {
+        r(Arc::new(crate::renders::image_render::ImageRender {
             image: image,
             sizing: crate::renders::image_render::Sizing::Contain,
             default: default,
             interpolation: interpolation::Bilinear // TODO
-        }) as Rc<dyn Render<Rgba>>)
+        }) as Arc<dyn Render<Rgba>>)
     }) as MyFn));
     rt.insert("text_to_image", r(Box::new(|vec: Vec<Val>| {
         let string = vec[0].ref_as::<String>().unwrap().clone();
         let scale = *vec[1].ref_as::<f64>().unwrap();
-        let font = vec.get(2).and_then(|v| v.ref_as::<Rc<crate::text::Font>>().cloned()).unwrap_or_else(|| {
+        let font = vec.get(2).and_then(|v| v.ref_as::<Arc<crate::text::Font>>().cloned()).unwrap_or_else(|| {
             let font_path = "./tmp/IPAexfont00401/ipaexg.ttf";
             let bytes = std::fs::read(font_path).unwrap();
-            Rc::new(crate::text::Font::from_bytes(bytes).unwrap())
+            Arc::new(crate::text::Font::from_bytes(bytes).unwrap())
         });
-        r(Rc::new(crate::text::render(&font, scale as f32, &string).map(|v| Rgba(0.0, 0.0, 0.0, *v))))
+        r(Arc::new(crate::text::render(&font, scale as f32, &string).map(|v| Rgba(0.0, 0.0, 0.0, *v))))
     }) as MyFn));
+    rt.insert("matte", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::matte::{Matte, MatteType, MaskMode};
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let mut matte = Matte { render, masks: vec![] };
+        // Either `(matte render mask [type])` or `(matte render (vec mask type [mode]) ...)`.
+        if let Some(mask) = vec.get_(1)?.ref_as::<Arc<dyn Render<Rgba>>>() {
+            let matte_type = match vec.get(2) {
+                Some(val) => matte_type(val)?,
+                None => MatteType::Alpha
+            };
+            matte.masks.push((mask.clone(), matte_type, MaskMode::Add));
+        } else {
+            for val in vec[1..].iter() {
+                let m = val.ref_as::<Vec<Val>>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+                let mask = m.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+                let matte_type = matte_type(m.get_(1)?)?;
+                let mask_mode = match m.get(2) {
+                    Some(val) => {
+                        let name = val.ref_as::<Symbol>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?.0.as_str();
+                        MaskMode::from_name(name).ok_or_else(|| GlutenError::Str(format!("invalid mask mode: {}", name)))?
+                    }
+                    None => MaskMode::Add
+                };
+                matte.masks.push((mask, matte_type, mask_mode));
+            }
+        }
+        Ok(r(Arc::new(matte) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("chroma_key", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::chroma_key::ChromaKey;
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let key_color = clone_timed::<Rgba>(vec.get_(1)?).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
+        let mut chroma_key = ChromaKey::new(render, key_color);
+        // `(chroma_key render key_color [tolerance [softness [spill [choke [feather]]]]])`
+        let params = [&mut chroma_key.tolerance, &mut chroma_key.softness, &mut chroma_key.spill, &mut chroma_key.choke, &mut chroma_key.feather];
+        for (param, val) in params.into_iter().zip(vec[2..].iter()) {
+            *param = clone_timed::<f64>(val).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
+        }
+        Ok(r(Arc::new(chroma_key) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("lut3d", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::lut3d::Lut3d;
+        use crate::lut::{Lut, LutInterpolation};
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let lut = vec.get_(1)?.ref_as::<Arc<Lut>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let mut lut3d = Lut3d::new(render, lut);
+        // `(lut3d render lut ['trilinear|'tetrahedral] [mix])`
+        let mut rest = &vec[2..];
+        if let Some(name) = rest.first().and_then(|val| val.ref_as::<Symbol>()) {
+            lut3d.interpolation = match name.0.as_str() {
+                "trilinear" => LutInterpolation::Trilinear,
+                "tetrahedral" => LutInterpolation::Tetrahedral,
+                _ => { return Err(GlutenError::Str("invalid interpolation".to_owned())) }
+            };
+            rest = &rest[1..];
+        }
+        if let Some(val) = rest.first() {
+            lut3d.mix = clone_timed::<f64>(val).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
+        }
+        Ok(r(Arc::new(lut3d) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("levels", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::levels::Levels;
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        // `(levels render black white gamma [output_black output_white])`, numbers apply to red, green
+        // and blue while colours apply per channel.
+        let channels = |val: &Val, alpha: f64| val.ref_as::<f64>().map(|v| Arc::new(Rgba(*v, *v, *v, alpha)) as Arc<dyn Timed<Rgba>>)
+            .or_else(|| clone_timed::<Rgba>(val))
+            .ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()));
+        let mut levels = Levels::new(render, 0.0, 1.0, 1.0);
+        levels.black = channels(vec.get_(1)?, 0.0)?;
+        levels.white = channels(vec.get_(2)?, 1.0)?;
+        levels.gamma = channels(vec.get_(3)?, 1.0)?;
+        if vec.len() > 4 {
+            levels.output_black = channels(vec.get_(4)?, 0.0)?;
+            levels.output_white = channels(vec.get_(5)?, 1.0)?;
+        }
+        Ok(r(Arc::new(levels) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("curves", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::curves::Curves;
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let mut curves = Curves::new(render);
+        // `(curves render 'rgb (vec (vec 0.0 0.0) (vec 0.5 0.6) (vec 1.0 1.0)) 'red (vec ...) ...)`
+        for pair in vec[1..].chunks(2) {
+            let name = pair[0].ref_as::<Symbol>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?.0.as_str();
+            let points = pair.get_(1)?.ref_as::<Vec<Val>>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+            let curve = points.iter().map(|p| timed_vec2(p).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned())))
+                .collect::<Result<Vec<_>, _>>()?;
+            match name {
+                "rgb" => curves.set_rgb(curve),
+                "red" => curves.set_red(curve),
+                "green" => curves.set_green(curve),
+                "blue" => curves.set_blue(curve),
+                "luma" => curves.set_luma(curve),
+                _ => { return Err(GlutenError::Str(format!("invalid curve: {}", name))) }
+            }
+        }
+        Ok(r(Arc::new(curves) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
+    rt.insert("hsl_adjust", r(Box::new(|vec: Vec<Val>| {
+        use crate::renders::hsl_adjust::HslAdjust;
+        let render = vec.get_(0)?.ref_as::<Arc<dyn Render<Rgba>>>().cloned().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let timed = |i: usize| clone_timed::<f64>(vec.get_(i)?).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()));
+        Ok(r(Arc::new(HslAdjust::new(render, timed(1)?, timed(2)?, timed(3)?)) as Arc<dyn Render<Rgba>>))
+    }) as NativeFn));
     rt.insert("composite", r(Box::new(|vec: Vec<Val>| {
-        use crate::renders::composite::{Composite, CompositeMode};
+        use crate::renders::composite::Composite;
+        use crate::renders::matte::{Matte, MatteType};
         let layers = vec.into_iter().map(|p| {
             let p = p.ref_as::<Vec<Val>>().unwrap().clone();
-            let render = p[0].ref_as::<Rc<dyn Render<Rgba>>>().unwrap().clone();
-            let mode = p[1].ref_as::<Symbol>().unwrap().0.to_owned();
-            let mode = match mode.as_str() {
-                "none" => CompositeMode::None,
-                "normal" => CompositeMode::Normal(
-                    p.get(2).and_then(clone_timed::<f64>).unwrap_or_else(|| Rc::new(1.0))
-                ),
-                _ => panic!("illegal CompositeMode")
-            };
+            let render = p[0].ref_as::<Arc<dyn Render<Rgba>>>().unwrap().clone();
+            // A track matte layer: `(vec render 'luma_matte matte [mode [opacity]])`.
+            let track_matte = p.get(1).and_then(|v| v.ref_as::<Symbol>())
+                .and_then(|s| s.0.strip_suffix("_matte").and_then(MatteType::from_name));
+            if let Some(matte_type) = track_matte {
+                let matte = p[2].ref_as::<Arc<dyn Render<Rgba>>>().unwrap().clone();
+                let render = Arc::new(Matte::new(render, matte, matte_type)) as Arc<dyn Render<Rgba>>;
+                let mode = if p.len() > 3 {
+                    composite_mode(&p[3..]).unwrap_or_else(|_| panic!("illegal CompositeMode"))
+                } else {
+                    CompositeMode::Normal(Arc::new(1.0))
+                };
+                return (render, mode);
+            }
+            let mode = composite_mode(&p[1..]).unwrap_or_else(|_| panic!("illegal CompositeMode"));
             (render, mode)
         }).collect();
-        r(Rc::new(Composite {
+        r(Arc::new(Composite {
             layers: layers
-        }) as Rc<dyn Render<Rgba>>)
+        }) as Arc<dyn Render<Rgba>>)
     }) as MyFn));
     fn vec_to_vec2<T: 'static + num_traits::Num + Lerp>(val: &Val) -> Vec2<T> {
         let val = val;
@@ -396,7 +871,7 @@ fn init_runtime(rt: &mut Runtime) {
                 };
                 path = path.append(d_time, vec, point);
             }
-            r(Rc::new(path))
+            r(Arc::new(path))
         }
         if let Some(v) = it.next() {
             if let Some(v) = v.ref_as::<f64>() {
@@ -421,10 +896,10 @@ fn init_runtime(rt: &mut Runtime) {
     }) as MyFn));
     rt.insert("timed/cycle", r(Box::new(|vec: Vec<Val>| {
         use crate::timed::Cycle;
-        fn f<T: 'static + Lerp>(vec: &Vec<Val>) -> Option<Val> {
+        fn f<T: 'static + Lerp + StructuralHash + Send + Sync>(vec: &Vec<Val>) -> Option<Val> {
             let timed = clone_timed(&vec[0])?;
             let duration = *vec[1].ref_as::<f64>().unwrap();
-            Some(r(Rc::new(Cycle::new(timed, duration)) as Rc<dyn Timed<T>>))
+            Some(r(Arc::new(Cycle::new(timed, duration)) as Arc<dyn Timed<T>>))
         }
         f::<f64>(&vec).or_else(|| f::<Vec2<f64>>(&vec)).or_else(|| f::<Vec3<f64>>(&vec)).or_else(|| f::<Rgba>(&vec)).unwrap()
     }) as MyFn));
@@ -434,23 +909,23 @@ fn init_runtime(rt: &mut Runtime) {
             let initial_phase = *vec[0].ref_as::<f64>().unwrap();
             let frequency = vec[1].ref_as::<f64>().unwrap().clone();
             let amplitude = vec[2].ref_as::<T>().unwrap().clone();
-            Some(r(Rc::new(Sine::new(initial_phase, frequency, amplitude)) as Rc<dyn Timed<f64>>))
+            Some(r(Arc::new(Sine::new(initial_phase, frequency, amplitude)) as Arc<dyn Timed<f64>>))
         }
-        f::<f64>(&vec).or_else(|| f::<Rc<dyn Timed<f64>>>(&vec)).unwrap()
+        f::<f64>(&vec).or_else(|| f::<Arc<dyn Timed<f64>>>(&vec)).unwrap()
     }) as MyFn));
     rt.insert("timed/add", r(Box::new(|vec: Vec<Val>| {
-        fn f<T: 'static + Lerp>(vec: &Vec<Val>) -> Option<Val> {
+        fn f<T: 'static + Lerp + StructuralHash + Send + Sync>(vec: &Vec<Val>) -> Option<Val> {
             let a = clone_timed(&vec[0])?;
             let b = clone_timed(&vec[1])?;
-            Some(r(Rc::new(crate::timed::Add::new(a, b)) as Rc<dyn Timed<T>>))
+            Some(r(Arc::new(crate::timed::Add::new(a, b)) as Arc<dyn Timed<T>>))
         }
         f::<f64>(&vec).or_else(|| f::<Vec2<f64>>(&vec)).or_else(|| f::<Vec3<f64>>(&vec)).unwrap()
     }) as MyFn));
     rt.insert("timed/mul", r(Box::new(|vec: Vec<Val>| {
-        fn f<T: 'static + Lerp + std::ops::Mul<Output = T>>(vec: &Vec<Val>) -> Option<Val> {
+        fn f<T: 'static + Lerp + StructuralHash + Send + Sync + std::ops::Mul<Output = T>>(vec: &Vec<Val>) -> Option<Val> {
             let a = clone_timed(&vec[0])?;
             let b = clone_timed(&vec[1])?;
-            Some(r(Rc::new(crate::timed::Mul::new(a, b)) as Rc<dyn Timed<T>>))
+            Some(r(Arc::new(crate::timed::Mul::new(a, b)) as Arc<dyn Timed<T>>))
         }
         f::<f64>(&vec).or_else(|| f::<Vec2<f64>>(&vec)).or_else(|| f::<Vec3<f64>>(&vec)).unwrap()
     }) as MyFn));
@@ -458,16 +933,16 @@ fn init_runtime(rt: &mut Runtime) {
         use crate::timed::Map;
         fn f<T: 'static + Clone + Timed<f64>>(vec: &Vec<Val>) -> Option<Val> {
             let timed = vec[0].ref_as::<T>()?.clone();
-            Some(r(Rc::new(Map::new(timed, |x| x.sin())) as Rc<dyn Timed<f64>>))
+            Some(r(Arc::new(Map::new(timed, |x| x.sin())) as Arc<dyn Timed<f64>>))
         }
-        f::<f64>(&vec).or_else(|| f::<Rc<dyn Timed<f64>>>(&vec)).or_else(
-            || Some(r(Rc::new(Map::new(clone_timed(&vec[0])?, |x| x.sin())) as Rc<dyn Timed<f64>>))
+        f::<f64>(&vec).or_else(|| f::<Arc<dyn Timed<f64>>>(&vec)).or_else(
+            || Some(r(Arc::new(Map::new(clone_timed(&vec[0])?, |x| x.sin())) as Arc<dyn Timed<f64>>))
         ).unwrap()
     }) as MyFn));
     rt.insert("transform", r(Box::new(|vec: Vec<Val>| {
         use crate::{renders::transform::{Transform, timed_to_transformer}};
-        let render = vec[0].ref_as::<Rc<dyn Render<Rgba>>>().unwrap().clone();
-        fn get_timed_vec2(val: &Val) -> Rc<dyn Timed<Vec2<f64>>> {
+        let render = vec[0].ref_as::<Arc<dyn Render<Rgba>>>().unwrap().clone();
+        fn get_timed_vec2(val: &Val) -> Arc<dyn Timed<Vec2<f64>>> {
             if let Some(timed) = clone_timed::<Vec2<f64>>(val) {
                 timed
             } else {
@@ -475,34 +950,34 @@ fn init_runtime(rt: &mut Runtime) {
                 let v = val.ref_as::<Vec<Val>>().unwrap();
                 let a = *v[0].ref_as::<f64>().unwrap();
                 let b = *v[1].ref_as::<f64>().unwrap();
-                Rc::new(Vec2(a, b))
+                Arc::new(Vec2(a, b))
             }
         }
-        fn get_timed_f64(val: &Val) -> Rc<dyn Timed<f64>> {
+        fn get_timed_f64(val: &Val) -> Arc<dyn Timed<f64>> {
             if let Some(timed) = clone_timed::<f64>(val) {
                 timed
             } else {
-                Rc::new(val.ref_as::<f64>().unwrap().clone())
+                Arc::new(val.ref_as::<f64>().unwrap().clone())
             }
         }
         let translation_timed = get_timed_vec2(&vec[1]);
         let scale_timed = get_timed_vec2(&vec[2]);
         let rotation_timed = get_timed_f64(&vec[3]);
-        r(Rc::new(Transform::new(
+        r(Arc::new(Transform::new(
             render,
             timed_to_transformer(translation_timed, scale_timed, rotation_timed)
-        )) as Rc<dyn Render<Rgba>>)
+        )) as Arc<dyn Render<Rgba>>)
     }) as MyFn));
     rt.insert("audio_buffer_render", r(Box::new(|vec: Vec<Val>| {
-        let audio_buffer = vec[0].ref_as::<Rc<AudioBuffer<u16>>>().unwrap().clone();
-        r(Rc::new(audio_renders::audio_buffer::AudioBufferRender {
+        let audio_buffer = vec[0].ref_as::<Arc<AudioBuffer<u16>>>().unwrap().clone();
+        r(Arc::new(audio_renders::audio_buffer::AudioBufferRender {
             audio_buffer: audio_buffer,
             interpolation: interpolation::NearestNeighbor
-        }) as Rc<dyn AudioRender>)
+        }) as Arc<dyn AudioRender>)
     }) as MyFn));
     rt.insert("audio_clip", r(Box::new(|vec: Vec<Val>| {
-        let audio_render = vec[0].ref_as::<Rc<dyn AudioRender>>().unwrap().clone();
-        r(Rc::new(audio_renders::audio_clip::AudioClip {
+        let audio_render = vec[0].ref_as::<Arc<dyn AudioRender>>().unwrap().clone();
+        r(Arc::new(audio_renders::audio_clip::AudioClip {
             audio_render: audio_render,
             gain: *vec[1].ref_as::<f64>().unwrap(),
             pan: *vec[2].ref_as::<f64>().unwrap(),
@@ -511,20 +986,20 @@ fn init_runtime(rt: &mut Runtime) {
             pitch: *vec[5].ref_as::<f64>().unwrap(),
             fadein: *vec[6].ref_as::<f64>().unwrap(),
             fadeout: *vec[7].ref_as::<f64>().unwrap()
-        }) as Rc<dyn AudioRender>)
+        }) as Arc<dyn AudioRender>)
     }) as MyFn));
     rt.insert("audio_sequencer", r(Box::new(|vec: Vec<Val>| {
         let renders = vec.into_iter().map(|p| {
             let p = p.ref_as::<Vec<Val>>().unwrap().clone();
             let time = p[0].ref_as::<f64>().unwrap().clone();
-            let render = p[1].ref_as::<Rc<dyn AudioRender>>().unwrap().clone();
+            let render = p[1].ref_as::<Arc<dyn AudioRender>>().unwrap().clone();
             (time, render)
         }).collect();
-        r(Rc::new(audio_renders::sequencer::Sequencer {renders}) as Rc<dyn AudioRender>)
+        r(Arc::new(audio_renders::sequencer::Sequencer {renders}) as Arc<dyn AudioRender>)
     }) as MyFn));
     rt.insert("audio/timed", r(Box::new(|vec: Vec<Val>| {
-        let timed = vec.get_(0)?.ref_as::<Rc<dyn Timed<f64>>>().cloned().ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
-        Ok(r(Rc::new(timed) as Rc<dyn AudioRender>))
+        let timed = vec.get_(0)?.ref_as::<Arc<dyn Timed<f64>>>().cloned().ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?;
+        Ok(r(Arc::new(timed) as Arc<dyn AudioRender>))
     }) as NativeFn));
     rt.insert("test_audio", r(Box::new(|_vec: Vec<Val>| {
         use crate::audio_renders::{note::Note, sequencer::Sequencer};
@@ -536,8 +1011,8 @@ fn init_runtime(rt: &mut Runtime) {
                 pan: pan
             })
         }
-        //r(Rc::new(note(1.0, 60, 0.3, 1.0)) as Rc<dyn AudioRender>)
-        r(Rc::new(Sequencer::new()
+        //r(Arc::new(note(1.0, 60, 0.3, 1.0)) as Arc<dyn AudioRender>)
+        r(Arc::new(Sequencer::new()
             .append(0.00, note(1.0, 60, 0.2, -1.0))
             .append(1.00, note(1.0, 64, 0.2, -1.0))
             .append(2.00, note(1.0, 62, 0.2, -1.0))
@@ -563,29 +1038,34 @@ fn init_runtime(rt: &mut Runtime) {
             .append(6.00, note(0.25, 72, 0.1, 0.0))
             .append(6.50, note(0.25, 72, 0.1, 0.0))
             .append(7.00, note(0.25, 72, 0.1, 0.0))
-            .append(7.50, note(0.50, 74, 0.1, 0.0))) as Rc<dyn AudioRender>)
+            .append(7.50, note(0.50, 74, 0.1, 0.0))) as Arc<dyn AudioRender>)
     }) as MyFn));
     rt.insert("path_to_image", r(Box::new(|vec: Vec<Val>| {
         use crate::path_to_image::{closed_path_rect, closed_path_to_image, expand_rect};
-        let path = vec[0].ref_as::<Rc<Path<Vec2<f64>>>>().unwrap().clone();
+        let path = vec[0].ref_as::<Arc<Path<Vec2<f64>>>>().unwrap().clone();
         let line_width = 3.0f64;
         let rect = expand_rect(closed_path_rect(&path), line_width.ceil() as i32);
-        r(Rc::new(closed_path_to_image(rect, Rgba(1.0, 0.0, 0.0, 1.0), Rgba(1.0, 1.0, 1.0, 1.0), line_width, &path)))
+        r(Arc::new(closed_path_to_image(rect, Rgba(1.0, 0.0, 0.0, 1.0), Rgba(1.0, 1.0, 1.0, 1.0), line_width, &path)))
     }) as MyFn));
     rt.insert("import_image", r(Box::new(|vec: Vec<Val>| {
         let filepath = vec[0].ref_as::<String>().unwrap().clone();
-        r(Rc::new(crate::image_import::load_image(&filepath)))
+        r(Arc::new(crate::image_import::load_image(&filepath)))
     }) as MyFn));
+    rt.insert("import_lut", r(Box::new(|vec: Vec<Val>| {
+        let filepath = vec.get_(0)?.ref_as::<String>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?;
+        let lut = crate::lut::load_cube(filepath).map_err(|e| GlutenError::Str(e.to_string()))?;
+        Ok(r(Arc::new(lut)))
+    }) as NativeFn));
     #[cfg(feature = "ffmpeg")]
     rt.insert("import_audio", r(Box::new(|vec: Vec<Val>| {
         let filepath = vec[0].ref_as::<String>().unwrap().clone();
-        r(Rc::new(crate::ffmpeg::import_audio(&filepath)))
+        r(Arc::new(crate::ffmpeg::import_audio(&filepath)))
     }) as MyFn));
     rt.insert("import_ttf", r(Box::new(|vec: Vec<Val>| {
         let filepath = vec[0].ref_as::<String>().unwrap().clone();
         let bytes = std::fs::read(filepath).unwrap();
         let font = crate::text::Font::from_bytes(bytes).unwrap();
-        r(Rc::new(font))
+        r(Arc::new(font))
     }) as MyFn));
     rt.insert("hash_map_get", r(Box::new(|vec: Vec<Val>| {
         let hash_map = vec[0].ref_as::<RefCell<std::collections::HashMap<String, Val>>>().unwrap().borrow_mut();
@@ -636,16 +1116,55 @@ fn init_runtime(rt: &mut Runtime) {
     }) as NativeFn));
 }
 
-fn clone_timed<T: 'static + Lerp>(val: &Val) -> Option<Rc<dyn Timed<T>>> {
-    val.ref_as::<Rc<dyn Timed<T>>>().cloned()
-        .or_else(|| val.ref_as::<Rc<Path<T>>>().map(|x| x.clone() as Rc<dyn Timed<T>>))
-        .or_else(|| val.ref_as::<T>().map(|x| Rc::new(*x) as Rc<dyn Timed<T>>))
+// A mode name followed by an optional opacity.
+fn composite_mode(vals: &[Val]) -> Result<CompositeMode, GlutenError> {
+    let name = vals.get_(0)?.ref_as::<Symbol>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?.0.to_owned();
+    let opacity = match vals.get(1) {
+        Some(val) => clone_timed::<f64>(val).ok_or_else(|| GlutenError::Str("arguments mismatch".to_owned()))?,
+        None => Arc::new(1.0)
+    };
+    match name.as_str() {
+        "none" => Ok(CompositeMode::None),
+        "normal" => Ok(CompositeMode::Normal(opacity)),
+        _ => BlendMode::from_name(&name).map(|mode| CompositeMode::Blend(mode, opacity))
+            .ok_or_else(|| GlutenError::Str(format!("invalid composite mode: {}", name)))
+    }
+}
+
+fn frame_type(vals: &[Val]) -> Result<crate::renders::frame::FrameType<Rgba>, GlutenError> {
+    use crate::renders::frame::FrameType;
+    Ok(match vals.get_(0)?.ref_as::<Symbol>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?.0.as_str() {
+        "constant" => FrameType::Constant(vals.get_(1)?.ref_as::<Rgba>().copied().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?),
+        "extend" => FrameType::Extend,
+        "repeat" => FrameType::Repeat,
+        "reflect" => FrameType::Reflect,
+        _ => { return Err(GlutenError::Str("invalid frame_type".to_owned())) }
+    })
+}
+
+fn matte_type(val: &Val) -> Result<crate::renders::matte::MatteType, GlutenError> {
+    let name = val.ref_as::<Symbol>().ok_or_else(|| GlutenError::Str("type mismatch".to_owned()))?.0.as_str();
+    crate::renders::matte::MatteType::from_name(name).ok_or_else(|| GlutenError::Str(format!("invalid matte type: {}", name)))
+}
+
+// A point given as `(vec x y)` or as something timed.
+fn timed_vec2(val: &Val) -> Option<Arc<dyn Timed<Vec2<f64>>>> {
+    clone_timed::<Vec2<f64>>(val).or_else(|| {
+        let v = val.ref_as::<Vec<Val>>()?;
+        Some(Arc::new(Vec2(*v.first()?.ref_as::<f64>()?, *v.get(1)?.ref_as::<f64>()?)) as Arc<dyn Timed<Vec2<f64>>>)
+    })
+}
+
+fn clone_timed<T: 'static + Lerp + StructuralHash + Send + Sync>(val: &Val) -> Option<Arc<dyn Timed<T>>> {
+    val.ref_as::<Arc<dyn Timed<T>>>().cloned()
+        .or_else(|| val.ref_as::<Arc<Path<T>>>().map(|x| x.clone() as Arc<dyn Timed<T>>))
+        .or_else(|| val.ref_as::<T>().map(|x| Arc::new(*x) as Arc<dyn Timed<T>>))
 }
 
 trait FnArgs {
     fn get_(&self, i: usize) -> Result<&Val, GlutenError>;
 }
-impl FnArgs for Vec<Val> {
+impl FnArgs for [Val] {
     fn get_(&self, i: usize) -> Result<&Val, GlutenError> {
         self.get(i).ok_or_else(|| GlutenError::Str("argument missing".to_owned()))
     }
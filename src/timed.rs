@@ -1,22 +1,38 @@
-use std::rc::Rc;
+use std::hash::Hasher;
+use std::sync::Arc;
 use crate::lerp::Lerp;
+use crate::hash::{StructuralHash, StructuralHasher};
 
-pub trait Timed<T> {
+pub trait Timed<T>: Send + Sync {
     fn get_value(&self, time: f64) -> T;
+
+    // Returns None if the value depends on something that can't be hashed, e.g. a closure.
+    fn structural_hash(&self) -> Option<u64> {
+        None
+    }
 }
 
-impl<T: Lerp> Timed<T> for T {
+impl<T: Lerp + StructuralHash + Send + Sync> Timed<T> for T {
     #[inline(always)]
     fn get_value(&self, _time: f64) -> T {
         self.clone()
     }
+
+    fn structural_hash(&self) -> Option<u64> {
+        self.hash_value()
+    }
 }
 
-impl<T: 'static> Timed<T> for Rc<dyn Timed<T>> {
+impl<T: 'static> Timed<T> for Arc<dyn Timed<T>> {
     #[inline(always)]
     fn get_value(&self, time: f64) -> T {
         self.as_ref().get_value(time)
     }
+
+    #[inline(always)]
+    fn structural_hash(&self) -> Option<u64> {
+        self.as_ref().structural_hash()
+    }
 }
 
 #[derive(Debug)]
@@ -36,10 +52,17 @@ impl<T, U: Timed<T>> Cycle<T, U> {
     }
 }
 
-impl<T, U: Timed<T>> Timed<T> for Cycle<T, U> {
+impl<T: Send + Sync, U: Timed<T>> Timed<T> for Cycle<T, U> {
     fn get_value(&self, time: f64) -> T {
         self.timed.get_value(time % self.duration)
     }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("cycle");
+        hasher.write_u64(self.timed.structural_hash()?);
+        hasher.write_f64(self.duration);
+        Some(hasher.finish())
+    }
 }
 
 #[derive(Debug)]
@@ -63,6 +86,14 @@ impl<T: Timed<f64>> Timed<f64> for Sine<T> {
     fn get_value(&self, time: f64) -> f64 {
         ((self.initial_phase + time) * self.frequency * std::f64::consts::PI * 2.0).sin() * self.amplitude.get_value(time)
     }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("sine");
+        hasher.write_f64(self.initial_phase);
+        hasher.write_f64(self.frequency);
+        hasher.write_u64(self.amplitude.structural_hash()?);
+        Some(hasher.finish())
+    }
 }
 
 #[derive(Debug)]
@@ -77,7 +108,7 @@ impl<T: Timed<f64>, F: Fn(f64) -> f64> Map<T, F> {
     }
 }
 
-impl<T: Timed<f64>, F: Fn(f64) -> f64> Timed<f64> for Map<T, F> {
+impl<T: Timed<f64>, F: Fn(f64) -> f64 + Send + Sync> Timed<f64> for Map<T, F> {
     fn get_value(&self, time: f64) -> f64 {
         (self.f)(self.timed.get_value(time))
     }
@@ -99,10 +130,17 @@ impl<T: std::ops::Add<Output = T>, A: Timed<T>, B: Timed<T>> Add<T, A, B> {
     }
 }
 
-impl<T: std::ops::Add<Output = T>, A: Timed<T>, B: Timed<T>> Timed<T> for Add<T, A, B> {
+impl<T: std::ops::Add<Output = T> + Send + Sync, A: Timed<T>, B: Timed<T>> Timed<T> for Add<T, A, B> {
     fn get_value(&self, time: f64) -> T {
         self.a.get_value(time) + self.b.get_value(time)
     }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("add");
+        hasher.write_u64(self.a.structural_hash()?);
+        hasher.write_u64(self.b.structural_hash()?);
+        Some(hasher.finish())
+    }
 }
 
 #[derive(Debug)]
@@ -121,8 +159,15 @@ impl<T: std::ops::Mul<Output = T>, A: Timed<T>, B: Timed<T>> Mul<T, A, B> {
     }
 }
 
-impl<T: std::ops::Mul<Output = T>, A: Timed<T>, B: Timed<T>> Timed<T> for Mul<T, A, B> {
+impl<T: std::ops::Mul<Output = T> + Send + Sync, A: Timed<T>, B: Timed<T>> Timed<T> for Mul<T, A, B> {
     fn get_value(&self, time: f64) -> T {
         self.a.get_value(time) * self.b.get_value(time)
     }
+
+    fn structural_hash(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("mul");
+        hasher.write_u64(self.a.structural_hash()?);
+        hasher.write_u64(self.b.structural_hash()?);
+        Some(hasher.finish())
+    }
 }
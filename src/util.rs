@@ -37,8 +37,8 @@ pub fn rgb_to_hsl(r: f64, g: f64, b: f64) -> (f64, f64, f64) {
 
 pub fn u32_noise(x: u32, y: u32, z: u32) -> u32 {
     // This is experimental implement.
-    let w = x * 2777 + y * 2999 + z * 3252 + 0xa241ee91;
-    (((x ^ w) + z) * ((y ^ w) + z) ^ (x + w) * (y + w) * (z + w)) + 0x9a6246f3
+    let w = x.wrapping_mul(2777).wrapping_add(y.wrapping_mul(2999)).wrapping_add(z.wrapping_mul(3252)).wrapping_add(0xa241ee91);
+    ((x ^ w).wrapping_add(z).wrapping_mul((y ^ w).wrapping_add(z)) ^ x.wrapping_add(w).wrapping_mul(y.wrapping_add(w)).wrapping_mul(z.wrapping_add(w))).wrapping_add(0x9a6246f3)
 }
 
 // https://mrl.nyu.edu/~perlin/noise/
@@ -1,4 +1,6 @@
 use crate::lerp::Lerp;
+use crate::hash::{StructuralHash, StructuralHasher};
+use std::hash::Hasher;
 use std::ops::{Add, Sub, Mul, Div, Rem};
 use num_traits::Num;
 use num_traits::identities::{Zero, One};
@@ -75,6 +77,14 @@ impl<T: Num + Lerp> Mul<f64> for Vec2<T> {
     }
 }
 impl<T: Num + Lerp> Lerp for Vec2<T> {}
+impl<T: Num + Lerp + StructuralHash> StructuralHash for Vec2<T> {
+    fn hash_value(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("vec2");
+        hasher.write_u64(self.0.hash_value()?);
+        hasher.write_u64(self.1.hash_value()?);
+        Some(hasher.finish())
+    }
+}
 
 impl<T: Num + Lerp> Add for Vec3<T> {
     type Output = Self;
@@ -145,6 +155,15 @@ impl<T: Num + Lerp> Mul<f64> for Vec3<T> {
     }
 }
 impl<T: Num + Lerp> Lerp for Vec3<T> {}
+impl<T: Num + Lerp + StructuralHash> StructuralHash for Vec3<T> {
+    fn hash_value(&self) -> Option<u64> {
+        let mut hasher = StructuralHasher::new("vec3");
+        hasher.write_u64(self.0.hash_value()?);
+        hasher.write_u64(self.1.hash_value()?);
+        hasher.write_u64(self.2.hash_value()?);
+        Some(hasher.finish())
+    }
+}
 
 #[test]
 fn test() {